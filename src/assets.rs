@@ -0,0 +1,735 @@
+//!---------------------------------------------------------------------!
+//! This file contains the asset catalog, every image professorBot      !
+//! shows is looked up here by name instead of a hardcoded link         !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - assets                                                    !
+//!     [x] - asset_set                                                 !
+//!     [x] - asset_add                                                 !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - get / random / nth                                        !
+//!     [x] - validate                                                  !
+//!     [x] - attach                                                    !
+//!---------------------------------------------------------------------!
+
+use crate::clips::check_mod;
+use crate::data;
+use crate::storage::write_atomic;
+use crate::{serenity, Context, Error};
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::CreateAttachment;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+pub const ASSET_DIR: &str = "assets";
+const MANIFEST_FILE: &str = "manifest.toml";
+const FILES_DIR: &str = "files";
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+// Days ahead a discord link's expiry gets pointed out
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// Every name the code asks for, and where it shows up
+pub const REQUIRED: [(&str, &str); 23] = [
+    (
+        "account.created",
+        "welcome gif for freshly registered users",
+    ),
+    ("bonus.claimed", "/claim_bonus after the roll"),
+    ("bonus.not_ready", "/claim_bonus when the bonus isn't ready"),
+    ("bonus.rolling", "/claim_bonus while the dice roll"),
+    (
+        "clip.invalid_link",
+        "/submit_clip with a link that isn't youtube or medal",
+    ),
+    ("clip.max_reached", "/submit_clip without free clip slots"),
+    ("clip.mine_empty", "/my_clips without any clips"),
+    ("clip.none_left", "/next_clip when every clip was rated"),
+    ("clip.rated", "/next_clip final score"),
+    ("clip.removed", "/my_clips after removing a clip"),
+    ("clip.server_empty", "/server_clips without any clips"),
+    ("daily.meme", "/uwu memes"),
+    ("daily.orb", "/uwu pondering orb"),
+    ("daily.ponder", "/uwu ponder art"),
+    ("dice.faces", "d20 faces, the nth entry shows a roll of n"),
+    ("dice.rolling", "dice thumbnail while rolling"),
+    ("doodle.failed", "reply when a doodle couldn't be drawn"),
+    ("mod.give", "/give_creds"),
+    (
+        "mod.over_limit",
+        "/give_creds and /take_creds over the limit",
+    ),
+    ("mod.take", "/take_creds"),
+    ("ping.pong", "/ping"),
+    ("shop.cancel", "/buy_tickets when nothing was bought"),
+    ("shop.success", "/buy_tickets after a purchase"),
+];
+
+const MANIFEST_HEADER: &str = "\
+# professorBot's images, keyed by the name the code asks for.
+#
+# A source is either an https url or a file in assets/files (like
+# \"files/shop_success.gif\"), local files get uploaded with the message so
+# they never expire. A list of sources is picked from at random, except for
+# dice.faces where the nth entry is the face for n.
+#
+# A file that isn't in assets/files yet is left out of embeds until it is
+# uploaded, drop it in under the name listed or use /asset_set.
+#
+# Moderators can change entries with /asset_set and /asset_add, which
+# rewrite this file. Check it with /assets.
+
+";
+
+/// One source or a list of them, so single images don't need brackets
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<Entry> for Vec<String> {
+    fn from(entry: Entry) -> Self {
+        match entry {
+            Entry::One(source) => vec![source],
+            Entry::Many(sources) => sources,
+        }
+    }
+}
+
+impl From<&Vec<String>> for Entry {
+    fn from(sources: &Vec<String>) -> Self {
+        match sources.as_slice() {
+            [one] => Entry::One(one.clone()),
+            _ => Entry::Many(sources.clone()),
+        }
+    }
+}
+
+/// An image ready to put in an embed, neither a link nor a file while it
+/// still has to be uploaded
+#[derive(Debug, Clone)]
+pub struct Asset {
+    name: String,
+    link: Option<String>,
+    file: Option<PathBuf>,
+}
+
+impl Asset {
+    fn unset(name: &str) -> Self {
+        Asset {
+            name: name.to_string(),
+            link: None,
+            file: None,
+        }
+    }
+
+    /// Whether there is an image to show
+    pub fn is_set(&self) -> bool {
+        self.link.is_some() || self.file.is_some()
+    }
+
+    /// What to pass to `.image()` or `.thumbnail()`, local files need `attach` too
+    pub fn url(&self) -> String {
+        match &self.link {
+            Some(link) => link.clone(),
+            None => format!("attachment://{}", self.file_name()),
+        }
+    }
+
+    /// The https link, None for local files
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    /// Upload name of a local file, named after the asset so two files can share a message
+    fn file_name(&self) -> String {
+        let ext = self
+            .file
+            .as_ref()
+            .and_then(|f| f.extension())
+            .and_then(|e| e.to_str())
+            .unwrap_or("png");
+        format!("{}.{}", self.name.replace('.', "_"), ext)
+    }
+}
+
+/// Embeds that show an asset, an asset that isn't uploaded yet is left out
+pub trait ShowAsset: Sized {
+    fn asset_image(self, asset: &Asset) -> Self;
+    fn asset_thumbnail(self, asset: &Asset) -> Self;
+}
+
+impl ShowAsset for serenity::CreateEmbed {
+    fn asset_image(self, asset: &Asset) -> Self {
+        match asset.is_set() {
+            true => self.image(asset.url()),
+            false => self,
+        }
+    }
+
+    fn asset_thumbnail(self, asset: &Asset) -> Self {
+        match asset.is_set() {
+            true => self.thumbnail(asset.url()),
+            false => self,
+        }
+    }
+}
+
+/// Builders that can carry the local files an embed points at
+pub trait Attach: Sized {
+    fn attach_file(self, file: CreateAttachment) -> Self;
+}
+
+impl Attach for poise::CreateReply {
+    fn attach_file(self, file: CreateAttachment) -> Self {
+        self.attachment(file)
+    }
+}
+
+impl Attach for serenity::EditMessage {
+    fn attach_file(self, file: CreateAttachment) -> Self {
+        self.new_attachment(file)
+    }
+}
+
+impl Attach for serenity::CreateMessage {
+    fn attach_file(self, file: CreateAttachment) -> Self {
+        self.add_file(file)
+    }
+}
+
+/// Upload the local files among `assets` with the message, links need nothing
+pub async fn attach<T: Attach>(mut builder: T, assets: &[&Asset]) -> T {
+    let mut seen = HashSet::new();
+    for asset in assets {
+        let path = match &asset.file {
+            Some(path) => path,
+            None => continue,
+        };
+        if !seen.insert(asset.file_name()) {
+            continue;
+        }
+        match CreateAttachment::path(path).await {
+            Ok(mut file) => {
+                file.filename = asset.file_name();
+                builder = builder.attach_file(file);
+            }
+            Err(e) => println!("Failed to read asset {}: {}", asset.name, e),
+        }
+    }
+    builder
+}
+
+pub struct AssetCatalog {
+    dir: PathBuf,
+    entries: RwLock<BTreeMap<String, Vec<String>>>,
+}
+
+impl AssetCatalog {
+    /// Read the manifest in `dir`, a manifest that doesn't parse is an error
+    pub fn load(dir: &str) -> Result<AssetCatalog, Error> {
+        let path = Path::new(dir).join(MANIFEST_FILE);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let raw: BTreeMap<String, Entry> =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let entries = raw.into_iter().map(|(k, v)| (k, v.into())).collect();
+        Ok(AssetCatalog {
+            dir: PathBuf::from(dir),
+            entries: RwLock::new(entries),
+        })
+    }
+
+    /// Whether `source` is a link or a file that was uploaded
+    fn is_available(&self, source: &str) -> bool {
+        is_link(source) || self.dir.join(source).is_file()
+    }
+
+    fn resolve(&self, name: &str, source: &str) -> Asset {
+        if is_link(source) {
+            return Asset {
+                name: name.to_string(),
+                link: Some(source.to_string()),
+                file: None,
+            };
+        }
+        match self.dir.join(source) {
+            path if path.is_file() => Asset {
+                name: name.to_string(),
+                link: None,
+                file: Some(path),
+            },
+            _ => Asset::unset(name),
+        }
+    }
+
+    fn pick(&self, name: &str, choose: impl FnOnce(&[String]) -> Option<&String>) -> Asset {
+        let entries = self.entries.read().unwrap();
+        let source = entries.get(name).and_then(|sources| choose(sources));
+        match source {
+            Some(source) => self.resolve(name, source),
+            None => {
+                // validate() keeps required names around, this is a typo in the code
+                println!("Missing asset: {}", name);
+                Asset::unset(name)
+            }
+        }
+    }
+
+    /// The asset called `name`, the first one if it is a list
+    pub fn get(&self, name: &str) -> Asset {
+        self.pick(name, |sources| sources.first())
+    }
+
+    /// A random one out of the list called `name`, only from the uploaded ones
+    pub fn random(&self, name: &str, rng: &mut impl Rng) -> Asset {
+        self.pick(name, |sources| {
+            let available: Vec<&String> = sources.iter().filter(|s| self.is_available(s)).collect();
+            available.choose(rng).copied().or(sources.first())
+        })
+    }
+
+    /// The nth entry of the list called `name`, past the end gives the last one
+    pub fn nth(&self, name: &str, n: usize) -> Asset {
+        self.pick(name, |sources| sources.get(n).or(sources.last()))
+    }
+
+    pub fn names(&self) -> Vec<(String, usize)> {
+        let entries = self.entries.read().unwrap();
+        entries.iter().map(|(k, v)| (k.clone(), v.len())).collect()
+    }
+
+    /// Problems in the catalog, the fatal ones break commands and stop startup
+    pub fn validate(&self) -> Vec<Problem> {
+        let entries = self.entries.read().unwrap();
+        let mut problems = Vec::new();
+
+        for (name, _) in REQUIRED {
+            if entries.get(name).is_none_or(|sources| sources.is_empty()) {
+                problems.push(Problem::fatal(format!("{} is missing", name)));
+            }
+        }
+
+        let now = Utc::now();
+        for (name, sources) in entries.iter() {
+            let mut waiting = Vec::new();
+            for source in sources {
+                if is_link(source) {
+                    // a dead link shows a broken image, so it counts like a missing file
+                    match discord_expiry(source) {
+                        Some(expiry) if expiry < now => problems.push(Problem::fatal(format!(
+                            "{} has a discord link that expired {}",
+                            name,
+                            expiry.format("%Y-%m-%d")
+                        ))),
+                        Some(expiry) if expiry < now + TimeDelta::days(EXPIRY_WARNING_DAYS) => {
+                            problems.push(Problem::warning(format!(
+                                "{} has a discord link that expires {}",
+                                name,
+                                expiry.format("%Y-%m-%d")
+                            )))
+                        }
+                        _ => (),
+                    }
+                } else if source.starts_with("http://") || source.contains("://") {
+                    problems.push(Problem::fatal(format!(
+                        "{} has a link that isn't https: {}",
+                        name, source
+                    )));
+                } else if !self.dir.join(source).is_file() {
+                    waiting.push(source.as_str());
+                }
+            }
+
+            // files named in the manifest but not uploaded yet, embeds go without them
+            match waiting.as_slice() {
+                [] => (),
+                [source] => problems.push(Problem::warning(format!(
+                    "{} is waiting for {} to be uploaded",
+                    name, source
+                ))),
+                [first, ..] => problems.push(Problem::warning(format!(
+                    "{} is waiting for {} of {} files to be uploaded, like {}",
+                    name,
+                    waiting.len(),
+                    sources.len(),
+                    first
+                ))),
+            }
+        }
+        problems
+    }
+
+    /// Replace the sources of `name` and rewrite the manifest
+    pub fn set(&self, name: &str, sources: Vec<String>) -> Result<(), Error> {
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(name.to_string(), sources);
+        self.write_manifest(&entries)
+    }
+
+    /// Add a source to the list called `name` and rewrite the manifest
+    pub fn add(&self, name: &str, source: String) -> Result<(), Error> {
+        let mut entries = self.entries.write().unwrap();
+        entries.entry(name.to_string()).or_default().push(source);
+        self.write_manifest(&entries)
+    }
+
+    fn write_manifest(&self, entries: &BTreeMap<String, Vec<String>>) -> Result<(), Error> {
+        let raw: BTreeMap<&String, Entry> = entries.iter().map(|(k, v)| (k, v.into())).collect();
+        let text = MANIFEST_HEADER.to_string() + &toml::to_string_pretty(&raw)?;
+        write_atomic(&self.dir.join(MANIFEST_FILE), text.as_bytes())?;
+        Ok(())
+    }
+
+    /// Save an uploaded image next to the manifest, returns the source to put in it
+    async fn store_upload(
+        &self,
+        name: &str,
+        upload: &serenity::Attachment,
+    ) -> Result<String, String> {
+        let ext = Path::new(&upload.filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+            return Err(format!(
+                "Only {} images can be used",
+                IMAGE_EXTENSIONS.join(", ")
+            ));
+        }
+
+        let bytes = upload
+            .download()
+            .await
+            .map_err(|e| format!("Failed to download the upload: {}", e))?;
+
+        // a fresh name every time, so an old message never shows a replaced file
+        let file = format!(
+            "{}/{}-{}.{}",
+            FILES_DIR,
+            name.replace('.', "_"),
+            Utc::now().format("%Y%m%d%H%M%S"),
+            ext
+        );
+        fs::create_dir_all(self.dir.join(FILES_DIR)).map_err(|e| e.to_string())?;
+        write_atomic(&self.dir.join(&file), &bytes).map_err(|e| e.to_string())?;
+        Ok(file)
+    }
+}
+
+pub struct Problem {
+    pub fatal: bool,
+    pub message: String,
+}
+
+impl Problem {
+    fn fatal(message: String) -> Self {
+        Problem {
+            fatal: true,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Problem {
+            fatal: false,
+            message,
+        }
+    }
+}
+
+fn is_link(source: &str) -> bool {
+    source.starts_with("https://")
+}
+
+/// Signed discord cdn links stop working at their `ex=` time (hex unix seconds)
+fn discord_expiry(link: &str) -> Option<DateTime<Utc>> {
+    if !link.starts_with("https://cdn.discordapp.com/")
+        && !link.starts_with("https://media.discordapp.net/")
+    {
+        return None;
+    }
+    let query = link.split_once('?')?.1;
+    let ex = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("ex="))?;
+    let secs = i64::from_str_radix(ex, 16).ok()?;
+    DateTime::from_timestamp(secs, 0)
+}
+
+/// Load the catalog at startup, fatal problems stop the bot before anything breaks later
+pub fn load_checked() -> AssetCatalog {
+    let assets =
+        AssetCatalog::load(ASSET_DIR).unwrap_or_else(|e| panic!("Failed to load assets: {}", e));
+
+    let problems = assets.validate();
+    for problem in problems.iter() {
+        println!("Asset problem: {}", problem.message);
+    }
+    if problems.iter().any(|p| p.fatal) {
+        panic!("Refusing to start with broken assets, see the problems above");
+    }
+    assets
+}
+
+async fn autocomplete_asset(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    ctx.data()
+        .assets
+        .names()
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(partial))
+        .collect()
+}
+
+/// pick the source for /asset_set and /asset_add, an upload or an https link
+async fn new_source(
+    ctx: Context<'_>,
+    name: &str,
+    link: Option<String>,
+    upload: Option<serenity::Attachment>,
+) -> Result<String, String> {
+    match (link, upload) {
+        (Some(_), Some(_)) | (None, None) => Err("Give either a link or an upload".to_string()),
+        (None, Some(upload)) => ctx.data().assets.store_upload(name, &upload).await,
+        (Some(link), None) => {
+            if !is_link(&link) {
+                return Err("Links have to start with https://".to_string());
+            }
+            if discord_expiry(&link).is_some() {
+                return Err("Discord attachment links expire, upload the image instead".to_string());
+            }
+            Ok(link)
+        }
+    }
+}
+
+async fn reply(
+    ctx: Context<'_>,
+    title: &str,
+    desc: String,
+    color: serenity::Color,
+) -> Result<(), Error> {
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title(title)
+                .description(desc)
+                .color(color)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - list professorBot's images and anything wrong with them
+#[poise::command(slash_command, check = "check_mod")]
+pub async fn assets(ctx: Context<'_>) -> Result<(), Error> {
+    let catalog = &ctx.data().assets;
+
+    let mut desc = String::from("```yaml\n");
+    for (name, count) in catalog.names() {
+        desc += &format!("{:<20} {:>3}\n", name, count);
+    }
+    desc += "```";
+
+    let problems = catalog.validate();
+    if problems.is_empty() {
+        desc += "\nNo problems found!";
+    } else {
+        desc += "\n**Problems**\n";
+        for problem in problems.iter().take(15) {
+            desc += &format!("- {}\n", problem.message);
+        }
+        if problems.len() > 15 {
+            desc += &format!("...and {} more\n", problems.len() - 15);
+        }
+    }
+
+    reply(ctx, "Assets", desc, data::EMBED_MOD).await
+}
+
+/// [!] MODERATOR - replace an image with a link or an uploaded file, for every server
+#[poise::command(slash_command, check = "check_mod")]
+pub async fn asset_set(
+    ctx: Context<'_>,
+    #[description = "asset name, like shop.success"]
+    #[autocomplete = "autocomplete_asset"]
+    name: String,
+    #[description = "https link to the image"] link: Option<String>,
+    #[description = "image file to upload"] upload: Option<serenity::Attachment>,
+) -> Result<(), Error> {
+    let source = match new_source(ctx, &name, link, upload).await {
+        Ok(source) => source,
+        Err(e) => return reply(ctx, "Asset Set", e, data::EMBED_ERROR).await,
+    };
+
+    ctx.data().assets.set(&name, vec![source])?;
+    let asset = ctx.data().assets.get(&name);
+
+    let reply = poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title("Asset Set")
+            .description(format!("**{}** now shows this", name))
+            .asset_image(&asset)
+            .color(data::EMBED_MOD)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    );
+    ctx.send(attach(reply, &[&asset]).await).await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - add another image to a random pick list, like daily.meme, for every server
+#[poise::command(slash_command, check = "check_mod")]
+pub async fn asset_add(
+    ctx: Context<'_>,
+    #[description = "asset name, like daily.meme"]
+    #[autocomplete = "autocomplete_asset"]
+    name: String,
+    #[description = "https link to the image"] link: Option<String>,
+    #[description = "image file to upload"] upload: Option<serenity::Attachment>,
+) -> Result<(), Error> {
+    let source = match new_source(ctx, &name, link, upload).await {
+        Ok(source) => source,
+        Err(e) => return reply(ctx, "Asset Add", e, data::EMBED_ERROR).await,
+    };
+
+    ctx.data().assets.add(&name, source)?;
+    let count = ctx
+        .data()
+        .assets
+        .names()
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, count)| count)
+        .unwrap_or_default();
+    let asset = ctx.data().assets.nth(&name, count.saturating_sub(1));
+
+    let reply = poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title("Asset Add")
+            .description(format!("Added to **{}** ({} images)", name, count))
+            .asset_image(&asset)
+            .color(data::EMBED_MOD)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    );
+    ctx.send(attach(reply, &[&asset]).await).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(entries: &[(&str, &str)]) -> AssetCatalog {
+        let mut all: BTreeMap<String, Vec<String>> = REQUIRED
+            .iter()
+            .map(|(name, _)| {
+                (
+                    name.to_string(),
+                    vec!["https://example.com/a.png".to_string()],
+                )
+            })
+            .collect();
+        for (name, source) in entries {
+            all.insert(name.to_string(), vec![source.to_string()]);
+        }
+        AssetCatalog {
+            dir: PathBuf::from(ASSET_DIR),
+            entries: RwLock::new(all),
+        }
+    }
+
+    fn signed(expiry: DateTime<Utc>) -> String {
+        format!(
+            "https://cdn.discordapp.com/attachments/1/2/a.gif?ex={:x}&is=0&hm=0&",
+            expiry.timestamp()
+        )
+    }
+
+    #[test]
+    fn shipped_manifest_is_valid() {
+        let assets = AssetCatalog::load(ASSET_DIR).unwrap();
+        let fatal: Vec<String> = assets
+            .validate()
+            .into_iter()
+            .filter(|p| p.fatal)
+            .map(|p| p.message)
+            .collect();
+        assert!(fatal.is_empty(), "{:?}", fatal);
+    }
+
+    #[test]
+    fn expired_discord_links_are_fatal() {
+        let expired = signed(Utc::now() - TimeDelta::days(1));
+        let problems = catalog(&[("ping.pong", &expired)]).validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].fatal);
+
+        let soon = signed(Utc::now() + TimeDelta::days(2));
+        let problems = catalog(&[("ping.pong", &soon)]).validate();
+        assert_eq!(problems.len(), 1);
+        assert!(!problems[0].fatal);
+
+        let later = signed(Utc::now() + TimeDelta::days(30));
+        assert!(catalog(&[("ping.pong", &later)]).validate().is_empty());
+    }
+
+    #[test]
+    fn missing_files_are_warnings() {
+        let problems = catalog(&[("ping.pong", "files/not_there.png")]).validate();
+        assert_eq!(problems.len(), 1);
+        assert!(!problems[0].fatal);
+
+        let assets = catalog(&[]);
+        assets.entries.write().unwrap().insert(
+            "daily.meme".to_string(),
+            vec![
+                "files/not_there.png".to_string(),
+                "https://example.com/b.png".to_string(),
+                "files/not_there_either.png".to_string(),
+            ],
+        );
+        let problems = assets.validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("2 of 3"));
+    }
+
+    #[test]
+    fn missing_files_are_left_out() {
+        let assets = catalog(&[("ping.pong", "files/not_there.png")]);
+        let asset = assets.get("ping.pong");
+        assert!(!asset.is_set());
+        let embed = serenity::CreateEmbed::new().asset_image(&asset);
+        assert_eq!(embed, serenity::CreateEmbed::new());
+
+        // a random pick skips the files still waiting to be uploaded
+        assets.entries.write().unwrap().insert(
+            "daily.meme".to_string(),
+            vec![
+                "files/not_there.png".to_string(),
+                "https://example.com/b.png".to_string(),
+            ],
+        );
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            assert!(assets.random("daily.meme", &mut rng).is_set());
+        }
+    }
+}
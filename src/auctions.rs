@@ -0,0 +1,593 @@
+//!---------------------------------------------------------------------!
+//! This file contains the auction house, users sell inventory items    !
+//! and raffle tickets to each other with bids held in escrow           !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - auction_item                                              !
+//!     [x] - auction_tickets                                           !
+//!     [x] - bid                                                       !
+//!     [x] - auctions                                                  !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - place_bid                                                 !
+//!     [x] - bid_button                                                !
+//!     [x] - spawn                                                     !
+//!---------------------------------------------------------------------!
+
+use crate::config::Config;
+use crate::data::{self, Auction, AuctionLot, Bid, Data, GuildData, UserData};
+use crate::saver::SaveCoordinator;
+use crate::{serenity, Context, Error};
+use chrono::Utc;
+use dashmap::DashMap;
+use poise::serenity_prelude::{EditMessage, UserId};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Custom id prefix of the bid button, followed by the auction id
+pub const BID_BUTTON: &str = "auction-bid-";
+
+// How many seconds between checks for ended auctions
+const AUCTION_CHECK: u64 = 30;
+
+fn min_bid(auction: &Auction, config: &Config) -> i32 {
+    match &auction.bid {
+        Some(bid) => config.next_bid(bid.amount),
+        None => auction.reserve,
+    }
+}
+
+fn auction_embed(auction: &Auction, config: &Config) -> serenity::CreateEmbed {
+    let top = match &auction.bid {
+        Some(bid) => format!("**{}** creds by <@{}>", bid.amount, bid.user),
+        None => "no bids yet".to_string(),
+    };
+    serenity::CreateEmbed::new()
+        .title(format!("Auction #{}", auction.id))
+        .description(format!(
+            "<@{}> is selling **{}**\n\nReserve: **{}** creds\nTop bid: {}\nEnds <t:{}:R>\n\nBid with the button or **/bid {} {}**",
+            auction.seller,
+            auction.lot.describe(),
+            auction.reserve,
+            top,
+            auction.ends.timestamp(),
+            auction.id,
+            min_bid(auction, config)
+        ))
+        .color(data::EMBED_GOLD)
+        .footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ))
+}
+
+fn auction_components(auction: &Auction, config: &Config) -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{}{}", BID_BUTTON, auction.id))
+            .label(format!("Bid {}", min_bid(auction, config)))
+            .style(poise::serenity_prelude::ButtonStyle::Primary),
+    ])]
+}
+
+fn give_lot(user: &mut UserData, lot: &AuctionLot) {
+    match lot {
+        AuctionLot::Item { name, count } => {
+            user.add_item(name, *count);
+        }
+        AuctionLot::Tickets { count } => {
+            user.add_tickets(*count);
+        }
+    }
+}
+
+/// Bid on an auction, the creds are held until the auction ends and the
+/// bid it beat gets its creds back
+pub async fn place_bid(
+    guild_id: serenity::GuildId,
+    guild: &GuildData,
+    saver: &SaveCoordinator,
+    config: &Config,
+    user_id: UserId,
+    id: u32,
+    amount: i32,
+) -> Result<Auction, String> {
+    let u = match guild.users.get(&user_id) {
+        Some(u) => Arc::clone(&u),
+        None => return Err("You need a professorBot account to bid, try **/wallet**".to_string()),
+    };
+
+    let (auction, outbid) = {
+        let mut bidder = u.write().await;
+        let placed = guild.update_auctions(|house| {
+            let auction = match house.open.iter_mut().find(|a| a.id == id) {
+                Some(auction) if auction.ends > Utc::now() => auction,
+                _ => return Err(format!("Auction #{} is over or doesn't exist", id)),
+            };
+            if auction.seller == user_id {
+                return Err("You can't bid on your own auction".to_string());
+            }
+            if auction.bid.as_ref().is_some_and(|b| b.user == user_id) {
+                return Err("You already have the top bid".to_string());
+            }
+            let min = min_bid(auction, config);
+            if amount < min {
+                return Err(format!("The lowest bid right now is **{}** creds", min));
+            }
+            if amount > bidder.get_creds() {
+                return Err(format!(
+                    "You only have **{}** creds, you can't bid **{}**",
+                    bidder.get_creds(),
+                    amount
+                ));
+            }
+
+            let outbid = auction.bid.replace(Bid {
+                user: user_id,
+                amount,
+            });
+            Ok((auction.clone(), outbid))
+        })?;
+        bidder.sub_creds(amount, data::CredReason::AuctionBid { id });
+        placed
+    };
+    saver.mark_dirty(guild_id, user_id);
+    saver.mark_guild_dirty(guild_id);
+
+    if let Some(outbid) = outbid {
+        let u = Arc::clone(&guild.users.entry(outbid.user).or_default());
+        u.write()
+            .await
+            .add_creds(outbid.amount, data::CredReason::AuctionRefund { id });
+        saver.mark_dirty(guild_id, outbid.user);
+    }
+
+    Ok(auction)
+}
+
+/// Handle a click on an auction's bid button, it bids the lowest amount allowed
+pub async fn bid_button(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let (guild_id, id) = match (
+        interaction.guild_id,
+        interaction
+            .data
+            .custom_id
+            .strip_prefix(BID_BUTTON)
+            .and_then(|id| id.parse::<u32>().ok()),
+    ) {
+        (Some(guild_id), Some(id)) => (guild_id, id),
+        _ => return Ok(()),
+    };
+    let guild = data.guild(guild_id);
+    let config = data.config.get();
+
+    let amount = guild
+        .auctions()
+        .open
+        .iter()
+        .find(|a| a.id == id)
+        .map(|a| min_bid(a, &config))
+        .unwrap_or(0);
+    let placed = place_bid(
+        guild_id,
+        &guild,
+        &data.saver,
+        &config,
+        interaction.user.id,
+        id,
+        amount,
+    )
+    .await;
+
+    let response = match placed {
+        Ok(auction) => serenity::CreateInteractionResponse::UpdateMessage(
+            serenity::CreateInteractionResponseMessage::new()
+                .embed(auction_embed(&auction, &config))
+                .components(auction_components(&auction, &config)),
+        ),
+        Err(e) => serenity::CreateInteractionResponse::Message(
+            serenity::CreateInteractionResponseMessage::new()
+                .content(e)
+                .ephemeral(true),
+        ),
+    };
+    interaction.create_response(ctx, response).await?;
+    Ok(())
+}
+
+/// Hand out everything of an ended auction and announce who won
+async fn settle(
+    http: &serenity::Http,
+    guild_id: serenity::GuildId,
+    guild: &GuildData,
+    saver: &SaveCoordinator,
+    auction: &Auction,
+) {
+    let seller = Arc::clone(&guild.users.entry(auction.seller).or_default());
+    let (mention, desc, color) = match &auction.bid {
+        Some(bid) => {
+            seller
+                .write()
+                .await
+                .add_creds(bid.amount, data::CredReason::AuctionSale { id: auction.id });
+            let winner = Arc::clone(&guild.users.entry(bid.user).or_default());
+            give_lot(&mut *winner.write().await, &auction.lot);
+            saver.mark_dirty(guild_id, bid.user);
+            (
+                format!("<@{}> <@{}>", auction.seller, bid.user),
+                format!(
+                    "<@{}> won **{}** for **{}** creds!",
+                    bid.user,
+                    auction.lot.describe(),
+                    bid.amount
+                ),
+                data::EMBED_SUCCESS,
+            )
+        }
+        None => {
+            give_lot(&mut *seller.write().await, &auction.lot);
+            (
+                format!("<@{}>", auction.seller),
+                format!(
+                    "No one bid on **{}**, it went back to <@{}>",
+                    auction.lot.describe(),
+                    auction.seller
+                ),
+                data::EMBED_ERROR,
+            )
+        }
+    };
+    saver.mark_dirty(guild_id, auction.seller);
+    saver.mark_guild_dirty(guild_id);
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Auction #{} Ended", auction.id))
+        .description(desc)
+        .color(color)
+        .footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ));
+
+    if let Some(message) = auction.message {
+        let edited = auction
+            .channel
+            .edit_message(
+                http,
+                message,
+                EditMessage::new()
+                    .embed(embed.clone())
+                    .components(Vec::new()),
+            )
+            .await;
+        if let Err(e) = edited {
+            println!("Failed to close auction #{} message: {}", auction.id, e);
+        }
+    }
+    let sent = auction
+        .channel
+        .send_message(
+            http,
+            serenity::CreateMessage::new().content(mention).embed(embed),
+        )
+        .await;
+    if let Err(e) = sent {
+        println!("Failed to announce auction #{}: {}", auction.id, e);
+    }
+}
+
+/// Settle ended auctions in the background, open auctions are saved so a
+/// restart settles whatever ended while professorBot was down
+pub fn spawn(
+    guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
+    saver: Arc<SaveCoordinator>,
+    http: Arc<serenity::Http>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let list: Vec<(serenity::GuildId, Arc<GuildData>)> = guilds
+                .iter()
+                .map(|g| (*g.key(), Arc::clone(g.value())))
+                .collect();
+
+            for (guild_id, guild) in list {
+                // while the raffle is closed its entries are frozen, tickets
+                // handed out now would skip the draw's reset, so they wait for it
+                let held = guild.raffle().is_closed();
+                let ended: Vec<Auction> = guild.update_auctions(|house| {
+                    let (ended, open) =
+                        std::mem::take(&mut house.open).into_iter().partition(|a| {
+                            a.ends <= now && !(held && matches!(a.lot, AuctionLot::Tickets { .. }))
+                        });
+                    house.open = open;
+                    ended
+                });
+                for auction in ended {
+                    settle(&http, guild_id, &guild, &saver, &auction).await;
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(AUCTION_CHECK)).await;
+        }
+    });
+}
+
+fn auction_reply(title: &str, desc: String, color: serenity::Color) -> poise::CreateReply {
+    poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title(title)
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    )
+}
+
+/// Take the lot from the seller and open the auction
+async fn list(ctx: Context<'_>, lot: AuctionLot, reserve: u32, hours: u32) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+
+    if hours > config.auctions.max_hours {
+        ctx.send(auction_reply(
+            "Auction",
+            format!(
+                "Auctions can run for at most **{}** hours",
+                config.auctions.max_hours
+            ),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+    let listed = guild
+        .auctions()
+        .open
+        .iter()
+        .filter(|a| a.seller == user_id)
+        .count();
+    if listed >= config.auctions.max_listings {
+        ctx.send(auction_reply(
+            "Auction",
+            format!(
+                "You already have **{}** auctions running, wait for one to end",
+                listed
+            ),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // the frozen raffle entries would still count tickets sold now
+    if matches!(lot, AuctionLot::Tickets { .. }) && guild.raffle().is_closed() {
+        ctx.send(auction_reply(
+            "Auction",
+            "Ticket sales are closed until the raffle is drawn".to_string(),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    {
+        let u = guild.users.get(&user_id).unwrap();
+        let mut user_data = u.write().await;
+        let taken = match &lot {
+            AuctionLot::Item { name, count } => user_data.remove_item(name, *count),
+            AuctionLot::Tickets { count } => user_data.remove_tickets(*count),
+        };
+        if !taken {
+            drop(user_data);
+            ctx.send(auction_reply(
+                "Auction",
+                format!("You don't have **{}** to sell", lot.describe()),
+                data::EMBED_ERROR,
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let auction = guild.update_auctions(|house| {
+        house.next_id += 1;
+        let auction = Auction {
+            id: house.next_id,
+            seller: user_id,
+            lot,
+            reserve: reserve.min(i32::MAX as u32) as i32,
+            ends: Utc::now() + chrono::Duration::hours(hours as i64),
+            bid: None,
+            channel: ctx.channel_id(),
+            message: None,
+        };
+        house.open.push(auction.clone());
+        auction
+    });
+    ctx.data().saver.mark_dirty(guild_id, user_id);
+    ctx.data().saver.mark_guild_dirty(guild_id);
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(auction_embed(&auction, &config))
+                .components(auction_components(&auction, &config)),
+        )
+        .await?;
+
+    // remembered so the result can close the bid button later
+    let message = reply.message().await?.id;
+    guild.update_auctions(|house| {
+        if let Some(a) = house.open.iter_mut().find(|a| a.id == auction.id) {
+            a.message = Some(message);
+        }
+    });
+    ctx.data().saver.mark_guild_dirty(guild_id);
+    Ok(())
+}
+
+async fn autocomplete_inventory(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Vec::new(),
+    };
+    let u = match ctx.data().guild(guild_id).users.get(&ctx.author().id) {
+        Some(u) => Arc::clone(&u),
+        None => return Vec::new(),
+    };
+    let user_data = u.read().await;
+    user_data
+        .get_inventory()
+        .keys()
+        .filter(|name| name.to_lowercase().starts_with(&partial.to_lowercase()))
+        .take(25)
+        .cloned()
+        .collect()
+}
+
+/// put items from your inventory up for auction
+#[poise::command(slash_command, guild_only)]
+pub async fn auction_item(
+    ctx: Context<'_>,
+    #[description = "item from your inventory"]
+    #[autocomplete = "autocomplete_inventory"]
+    item: String,
+    #[description = "lowest bid you accept"]
+    #[min = 1]
+    reserve: u32,
+    #[description = "how many hours the auction runs"]
+    #[min = 1]
+    hours: u32,
+    #[description = "how many to sell together (default 1)"]
+    #[min = 1]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let lot = AuctionLot::Item {
+        name: item,
+        count: count.unwrap_or(1),
+    };
+    list(ctx, lot, reserve, hours).await
+}
+
+/// put raffle tickets up for auction
+#[poise::command(slash_command, guild_only)]
+pub async fn auction_tickets(
+    ctx: Context<'_>,
+    #[description = "how many tickets to sell together"]
+    #[min = 1]
+    count: u32,
+    #[description = "lowest bid you accept"]
+    #[min = 1]
+    reserve: u32,
+    #[description = "how many hours the auction runs"]
+    #[min = 1]
+    hours: u32,
+) -> Result<(), Error> {
+    let lot = AuctionLot::Tickets {
+        count: count.min(i32::MAX as u32) as i32,
+    };
+    list(ctx, lot, reserve, hours).await
+}
+
+/// bid creds on an auction, they are held until you are outbid or it ends
+#[poise::command(slash_command, guild_only)]
+pub async fn bid(
+    ctx: Context<'_>,
+    #[description = "auction number, see /auctions"] id: u32,
+    #[description = "how many creds to bid"]
+    #[min = 1]
+    amount: u32,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+
+    let placed = place_bid(
+        guild_id,
+        &guild,
+        &ctx.data().saver,
+        &config,
+        ctx.author().id,
+        id,
+        amount.min(i32::MAX as u32) as i32,
+    )
+    .await;
+
+    let auction = match placed {
+        Ok(auction) => auction,
+        Err(e) => {
+            ctx.send(auction_reply("Bid", e, data::EMBED_ERROR)).await?;
+            return Ok(());
+        }
+    };
+
+    // keep the announcement and its button up to date
+    if let Some(message) = auction.message {
+        let edited = auction
+            .channel
+            .edit_message(
+                ctx,
+                message,
+                EditMessage::new()
+                    .embed(auction_embed(&auction, &config))
+                    .components(auction_components(&auction, &config)),
+            )
+            .await;
+        if let Err(e) = edited {
+            println!("Failed to update auction #{} message: {}", auction.id, e);
+        }
+    }
+
+    ctx.send(auction_reply(
+        "Bid",
+        format!(
+            "You have the top bid on auction #{} with **{}** creds! It ends <t:{}:R>",
+            auction.id,
+            amount,
+            auction.ends.timestamp()
+        ),
+        data::EMBED_GOLD,
+    ))
+    .await?;
+    Ok(())
+}
+
+/// see every auction that is still running
+#[poise::command(slash_command, guild_only)]
+pub async fn auctions(ctx: Context<'_>) -> Result<(), Error> {
+    let mut open = ctx.data().guild(ctx.guild_id().unwrap()).auctions().open;
+    open.sort_by_key(|a| a.ends);
+
+    let mut desc = String::new();
+    for auction in open.iter().take(15) {
+        let top = match &auction.bid {
+            Some(bid) => format!("top bid **{}**", bid.amount),
+            None => format!("reserve **{}**", auction.reserve),
+        };
+        let ends = match auction.ends <= Utc::now() {
+            true => "ended, settles after the raffle draw".to_string(),
+            false => format!("ends <t:{}:R>", auction.ends.timestamp()),
+        };
+        desc += format!(
+            "**#{}** {} from <@{}>, {}, {}\n",
+            auction.id,
+            auction.lot.describe(),
+            auction.seller,
+            top,
+            ends
+        )
+        .as_str();
+    }
+    if open.is_empty() {
+        desc += "Nothing is up for auction, sell something with **/auction_item** or **/auction_tickets**";
+    }
+
+    ctx.send(auction_reply("Auctions", desc, data::EMBED_GOLD))
+        .await?;
+    Ok(())
+}
@@ -7,22 +7,28 @@
 //!     [x] - uwu                                                       !
 //!     [x] - claim_bonus                                               !
 //!     [-] - wallet                                                    !
+//!     [x] - history                                                   !
 //!     [-] - leaderboard                                               !
 //!     [x] - buy_tickets                                               !
+//!     [x] - pay                                                       !
 //!     [x] - voice_status                                              !
 //!     [x] - info                                                      !
 //!---------------------------------------------------------------------!
 
+use crate::assets::{self, ShowAsset};
+use crate::clock;
+use crate::config::Config;
 use crate::data::{self, VoiceUser};
+use crate::dice;
 use crate::gpt::gpt_string;
 use crate::helper::get_leaderboard;
+use crate::modifiers::{self, Game};
 use crate::reminder;
 use crate::{serenity, Context, Error};
 use chrono::prelude::Utc;
 use poise::serenity_prelude::futures::StreamExt;
 use poise::serenity_prelude::{EditMessage, ReactionType, UserId};
-use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use serenity::Color;
 use std::collections::HashMap;
 use std::env;
@@ -34,44 +40,52 @@ use tokio::sync::RwLock;
 #[poise::command(slash_command)]
 pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
     let author = ctx.author();
-    let pong_image = ctx.data().pong.choose(&mut thread_rng()).unwrap();
+    let mut rng = ctx.data().rng.draw(&format!("ping {}", author.id));
+    let pong_image = ctx.data().assets.random("ping.pong", &mut rng);
     let latency: f32 =
         (ctx.created_at().time() - Utc::now().time()).num_milliseconds() as f32 / 1000.0;
 
-    ctx.send(
-        poise::CreateReply::default().embed(
-            serenity::CreateEmbed::new()
-                .title("Pong!")
-                .description(format!(
-                    "Right back at you <@{}>! ProfessorBot is live! ({}s)",
-                    author.id, latency
-                ))
-                .color(data::EMBED_CYAN)
-                .image(pong_image)
-                .footer(serenity::CreateEmbedFooter::new(
-                    "@~ powered by UwUntu & RustyBamboo",
-                )),
-        ),
-    )
-    .await?;
+    let reply = poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title("Pong!")
+            .description(format!(
+                "Right back at you <@{}>! ProfessorBot is live! ({}s)",
+                author.id, latency
+            ))
+            .color(data::EMBED_CYAN)
+            .asset_image(&pong_image)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    );
+    ctx.send(assets::attach(reply, &[&pong_image]).await)
+        .await?;
     Ok(())
 }
 
 /// claim your daily, 500xp (Once a day)
-#[poise::command(slash_command)]
+#[poise::command(slash_command, guild_only)]
 pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
     let user = ctx.author();
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
     let u = data.get(&user.id).unwrap();
     let mut user_data = u.write().await;
+    let config = ctx.data().config.get();
+    let tz = config.time.timezone;
 
     // check if daily is available
-    if !user_data.check_daily() {
+    if !user_data.check_daily(tz) {
+        let reset = clock::next_reset(tz);
         ctx.send(
             poise::CreateReply::default().embed(
                 serenity::CreateEmbed::new()
                     .title("Daily")
-                    .description("Your next **/uwu** is tomorrow")
+                    .description(format!(
+                        "Your next **/uwu** is in **{}** (<t:{}:t>)",
+                        clock::until(reset),
+                        reset.timestamp()
+                    ))
                     .color(data::EMBED_ERROR)
                     .thumbnail(user.avatar_url().unwrap_or_default()),
             ),
@@ -80,14 +94,25 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
         return Ok(());
     }
 
-    let d20 = thread_rng().gen_range(1..21);
-    let check = thread_rng().gen_range(6..15);
+    let (streak, freezes_used) = user_data.next_streak(tz, &config.streaks);
+    let roles = match ctx.author_member().await {
+        Some(member) => member.roles.clone(),
+        None => Vec::new(),
+    };
+    let modifiers = modifiers::gather(Game::Daily, &user_data, streak, &roles, &config);
+
+    let mut rng = ctx
+        .data()
+        .rng
+        .draw(&format!("uwu {} {}", ctx.guild_id().unwrap(), user.id));
+    let d20 = dice::d20(&mut rng);
+    let check = rng.gen_range(6..15);
 
-    let bonus = 0; // change this to scale with level
+    let bonus = modifiers.roll();
 
     let low = (check - 1) * 50;
     let high = check * 50;
-    let fortune = thread_rng().gen_range(low..high);
+    let fortune = rng.gen_range(low..high);
 
     let total: i32;
     let roll_str: String;
@@ -104,7 +129,7 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
         roll_str = "**Critical Failure!**".to_string();
         roll_context = "-".to_string();
         roll_color = data::EMBED_FAIL;
-    } else if d20 >= check {
+    } else if d20 + bonus >= check {
         total = fortune;
         roll_str = "Yippee, you passed.".to_string();
         roll_context = "+".to_string();
@@ -116,39 +141,45 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
         roll_color = data::EMBED_ERROR;
     };
 
-    let base_ref = ctx.data().d20f.get(28);
+    // modifiers only make wins bigger, a critical failure costs the same
+    let total = match d20 {
+        1 => total,
+        _ => modifiers.payout(total),
+    };
+
+    let assets = &ctx.data().assets;
+    let base_ref = assets.get("dice.rolling");
     let roll_ref = if d20 == 20 || d20 == 1 {
-        ctx.data().d20f.get((d20 - 1) as usize)
+        assets.nth("dice.faces", (d20 - 1) as usize)
     } else {
-        ctx.data().d20f.get((d20 + bonus - 1) as usize)
+        assets.nth("dice.faces", ((d20 + bonus).max(1) - 1) as usize)
     };
 
     // generate daily orb/animeme
-    let random_meme = thread_rng().gen_range(0..100);
+    let random_meme = rng.gen_range(0..100);
     let ponder_image = if random_meme < 50 {
-        "https://cdn.discordapp.com/attachments/1260223476766343188/1262189235558027274/pondering-my-orb-header-art.png?ex=6695b0d4&is=66945f54&hm=e704148f7bda31c186f2b9385ec81c0c5ab6c631cea0166d9a0bb677b84274a4&"
+        assets.get("daily.orb")
     } else if (50..75).contains(&random_meme) {
-        ctx.data().ponder.choose(&mut thread_rng()).unwrap()
+        assets.random("daily.ponder", &mut rng)
     } else {
-        ctx.data().meme.choose(&mut thread_rng()).unwrap()
+        assets.random("daily.meme", &mut rng)
     };
 
     // temporary message to roll the dice
     let desc = format!("---\nYou needed a **{}** to pass...\n\n---\n---", check);
+    let rolling = poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title("Daily")
+            .description(&desc)
+            .asset_thumbnail(&base_ref)
+            .color(data::EMBED_DEFAULT)
+            .asset_image(&ponder_image)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    );
     let reply = ctx
-        .send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::new()
-                    .title("Daily")
-                    .description(&desc)
-                    .thumbnail(base_ref.unwrap().to_string())
-                    .color(data::EMBED_DEFAULT)
-                    .image(ponder_image)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
-        )
+        .send(assets::attach(rolling, &[&base_ref, &ponder_image]).await)
         .await?;
 
     // generate fortune readings with gpt3.5
@@ -162,8 +193,9 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
     let mut tries = 0;
     let reading;
     let gpt_key: String = env::var("API_KEY").expect("missing GPT API_KEY");
+    let gpt_config = &config.gpt;
     loop {
-        match gpt_string(gpt_key.clone(), prompt.to_string()).await {
+        match gpt_string(gpt_key.clone(), &gpt_config.chat_model, prompt.to_string()).await {
             Ok(result) => {
                 reading = result;
                 break;
@@ -171,7 +203,7 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
             Err(e) => {
                 println!("An error occurred: {:?}, retrying...", e);
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                if tries > 5 {
+                if tries + 1 >= gpt_config.retries {
                     return Err(Box::new(e));
                 }
             }
@@ -179,37 +211,59 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
         tries += 1;
     }
 
+    let debt = &config.debt;
+    let debt_note = if d20 == 1 {
+        match user_data.charge(total, data::CredReason::Daily { roll: d20 }, debt) {
+            0 => String::new(),
+            owed => format!(" (**{}** added to your debt)", owed),
+        }
+    } else {
+        match user_data.earn(total, data::CredReason::Daily { roll: d20 }, debt) {
+            0 => String::new(),
+            repaid => format!(" (**{}** went to paying off your debt)", repaid),
+        }
+    };
+
+    let mut streak_note = format!("🔥 **{}** day streak", streak);
+    if freezes_used > 0 {
+        streak_note += format!(", saved by **{}** streak freeze(s)", freezes_used).as_str();
+    } else if streak == 1 && user_data.get_best_streak() > 1 {
+        streak_note += format!(", your best is **{}**", user_data.get_best_streak()).as_str();
+    }
+
+    let rolled_text = match bonus {
+        0 => format!("**{}**", d20),
+        _ => format!("**{}** ({} {:+})", d20 + bonus, d20, bonus),
+    };
+
     // final message with updated dice roll, creds earned and fortune reading
     let desc = format!(
-        "{} **{}{}** creds.\nYou needed a **{}** to pass, you rolled a **{}**.\n\n{:?}",
-        roll_str, roll_context, total, check, d20, reading,
+        "{} **{}{}** creds{}.\nYou needed a **{}** to pass, you rolled a {}.\n{}\n\n{:?}",
+        roll_str, roll_context, total, debt_note, check, rolled_text, streak_note, reading,
     );
 
+    let rolled = poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title("Daily")
+            .description(&desc)
+            .field("Modifiers", modifiers.breakdown(), false)
+            .asset_thumbnail(&roll_ref)
+            .color(roll_color)
+            .asset_image(&ponder_image)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    );
     reply
         .edit(
             ctx,
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::new()
-                    .title("Daily")
-                    .description(&desc)
-                    .thumbnail(roll_ref.unwrap().to_string())
-                    .color(roll_color)
-                    .image(ponder_image)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(rolled, &[&roll_ref, &ponder_image]).await,
         )
         .await?;
 
-    if d20 == 1 {
-        user_data.sub_creds(total);
-    } else {
-        user_data.add_creds(total);
-    }
-
-    user_data.add_rolls(d20);
+    user_data.record_roll(Game::Daily, d20, d20 + bonus);
     user_data.add_bonus();
+    user_data.set_streak(streak, freezes_used);
     user_data.update_daily();
 
     let levelup = user_data.update_xp(450);
@@ -242,73 +296,90 @@ pub async fn uwu(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 /// claim bonus creds for every three dailies
-#[poise::command(slash_command)]
+#[poise::command(slash_command, guild_only)]
 pub async fn claim_bonus(ctx: Context<'_>) -> Result<(), Error> {
-    // update this to implement a d20 dice roll + bonus from level
-
     let user = ctx.author();
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
     let u = data.get_mut(&user.id).unwrap();
     let mut user_data = u.write().await;
 
     let bonus = user_data.get_bonus();
     if user_data.check_claim() {
-        let d20 = thread_rng().gen_range(1..21);
-        let proficiency = 2 + user_data.get_level() / 8;
-        let base_ref = ctx.data().d20f.get(28);
+        let config = ctx.data().config.get();
+        let roles = match ctx.author_member().await {
+            Some(member) => member.roles.clone(),
+            None => Vec::new(),
+        };
+        let modifiers = modifiers::gather(Game::Bonus, &user_data, 0, &roles, &config);
+
+        let mut rng = ctx.data().rng.draw(&format!(
+            "claim_bonus {} {}",
+            ctx.guild_id().unwrap(),
+            user.id
+        ));
+        let d20 = dice::d20(&mut rng);
+        let proficiency = modifiers.roll();
+        let assets = &ctx.data().assets;
+        let base_ref = assets.get("dice.rolling");
+        let rolling_image = assets.get("bonus.rolling");
 
         // temporary message to roll the dice
         let desc = format!(
             "Rolling for Bonus loot, you get a **+{}** fortune modifier.\n---\n",
             proficiency
         );
+        let rolling = poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Claim Bonus")
+                .description(&desc)
+                .asset_thumbnail(&base_ref)
+                .color(data::EMBED_DEFAULT)
+                .asset_image(&rolling_image)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        );
         let reply = ctx
-            .send(
-                poise::CreateReply::default().embed(
-                    serenity::CreateEmbed::new()
-                        .title("Claim Bonus")
-                        .description(&desc)
-                        .thumbnail(base_ref.unwrap().to_string())
-                        .color(data::EMBED_DEFAULT)
-                        .image("https://cdn.discordapp.com/attachments/1260223476766343188/1262193927386038302/giphy.gif?ex=6695b532&is=669463b2&hm=62e2fb0cc811b9e5b198a44c4351ca8f5d28bcc728c10334c55ba6b2f00ad658&")
-                        .footer(serenity::CreateEmbedFooter::new(
-                            "@~ powered by UwUntu & RustyBamboo",
-                        )),
-                ),
-            )
+            .send(assets::attach(rolling, &[&base_ref, &rolling_image]).await)
             .await?;
 
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        let low = (d20 + proficiency - 1) * 40;
-        let high = (d20 + proficiency) * 40;
-        let fortune = thread_rng().gen_range(low..high);
-        let roll_ref = ctx.data().d20f.get((d20 + proficiency - 1) as usize); // make more dice face
+        // a roll pushed below 1 still pays like a 1
+        let total_roll = (d20 + proficiency).max(1);
+        let low = (total_roll - 1) * 40;
+        let high = total_roll * 40;
+        let fortune = modifiers.payout(rng.gen_range(low..high));
+        let roll_ref = assets.nth("dice.faces", (total_roll - 1) as usize); // make more dice face
+        let claimed_image = assets.get("bonus.claimed");
 
         // final message with updated dice roll and creds
         let desc = format!(
-            "You rolled a **{}** and obtained **+{}** creds.",
-            d20 + proficiency,
-            fortune
+            "You rolled a **{}** ({} {:+}) and obtained **+{}** creds.",
+            total_roll, d20, proficiency, fortune
         );
 
+        let rolled = poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Claim Bonus")
+                .description(&desc)
+                .field("Modifiers", modifiers.breakdown(), false)
+                .asset_thumbnail(&roll_ref)
+                .color(data::EMBED_GOLD)
+                .asset_image(&claimed_image)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        );
         reply
             .edit(
                 ctx,
-                poise::CreateReply::default().embed(
-                    serenity::CreateEmbed::new()
-                        .title("Claim Bonus")
-                        .description(&desc)
-                        .thumbnail(roll_ref.unwrap().to_string())
-                        .color(data::EMBED_GOLD)
-                        .image("https://cdn.discordapp.com/attachments/1260223476766343188/1262191655323308053/19c237178769d1c1fe6cd44b3399afb61d2840b9_hq.gif?ex=6695b315&is=66946195&hm=43de96a5e0aac7f571a537420608f6a3b893831b5ccbc5bcdd3b74c9378bcaa8&")
-                        .footer(serenity::CreateEmbedFooter::new(
-                            "@~ powered by UwUntu & RustyBamboo",
-                        )),
-                ),
+                assets::attach(rolled, &[&roll_ref, &claimed_image]).await,
             )
             .await?;
 
-        user_data.add_creds(fortune);
+        user_data.add_creds(fortune, data::CredReason::Bonus { roll: d20 });
+        user_data.record_roll(Game::Bonus, d20, total_roll);
         user_data.reset_bonus();
 
         let levelup = user_data.update_xp(150);
@@ -346,30 +417,81 @@ pub async fn claim_bonus(ctx: Context<'_>) -> Result<(), Error> {
             }
         };
 
+        let not_ready = ctx.data().assets.get("bonus.not_ready");
+        let reply = poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Claim Bonus")
+                .description(desc)
+                .color(data::EMBED_ERROR)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                ))
+                .asset_thumbnail(&not_ready),
+        );
+        ctx.send(assets::attach(reply, &[&not_ready]).await).await?;
+    }
+    Ok(())
+}
+
+/// check how many creds, wishes, or submits you have
+#[poise::command(slash_command, guild_only)]
+pub async fn wallet(
+    ctx: Context<'_>,
+    #[description = "see how you finished a past season"]
+    #[min = 1]
+    season: Option<usize>,
+) -> Result<(), Error> {
+    let user = ctx.author();
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
+    let u = data.get(&user.id).unwrap();
+    let user_data = u.read().await;
+
+    if let Some(season) = season {
+        let (title, desc, color) = match user_data
+            .get_seasons()
+            .iter()
+            .find(|s| s.season == season)
+        {
+            Some(record) => (
+                format!("Season {} Wallet", season),
+                format!(
+                    "**Level {} **  -  {} xp\n﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋\nFinished........... . . . **#{}** {}\nAverage Luck..... . . . **{}**\nDaily UwUs......... . . . **{}**\n\nTotal Creds: **{}** \u{3000}\u{3000}\u{2000}Tickets: **{}**\nEnded {}",
+                    record.level,
+                    record.xp,
+                    record.rank,
+                    record.badge.as_deref().unwrap_or_default(),
+                    record.luck,
+                    record.daily_count,
+                    record.creds,
+                    record.tickets,
+                    record.ended.format("%m/%d/%Y")
+                ),
+                data::EMBED_GOLD,
+            ),
+            None => (
+                "Wallet".to_string(),
+                format!("You have no record from season {}", season),
+                data::EMBED_ERROR,
+            ),
+        };
+        drop(user_data);
+
         ctx.send(
             poise::CreateReply::default().embed(
                 serenity::CreateEmbed::new()
-                    .title("Claim Bonus")
+                    .title(title)
                     .description(desc)
-                    .color(data::EMBED_ERROR)
+                    .thumbnail(user.avatar_url().unwrap_or_default().to_string())
+                    .color(color)
                     .footer(serenity::CreateEmbedFooter::new(
                         "@~ powered by UwUntu & RustyBamboo",
-                    ))
-                    .thumbnail("https://cdn.discordapp.com/attachments/1260223476766343188/1262191656124284999/f7WPkmj.jpeg?ex=6695b315&is=66946195&hm=552171d50e562072461dc76c8222e9791cd9931f2ee7252975f25ab0dc63b0e5&"),
+                    )),
             ),
         )
         .await?;
+        return Ok(());
     }
-    Ok(())
-}
-
-/// check how many creds, wishes, or submits you have
-#[poise::command(slash_command)]
-pub async fn wallet(ctx: Context<'_>) -> Result<(), Error> {
-    let user = ctx.author();
-    let data = &ctx.data().users;
-    let u = data.get(&user.id).unwrap();
-    let user_data = u.read().await;
 
     // get user info
     let luck: String = if user_data.get_luck() == "" {
@@ -378,10 +500,12 @@ pub async fn wallet(ctx: Context<'_>) -> Result<(), Error> {
         user_data.get_luck()
     };
 
-    let daily: String = if user_data.check_daily() {
+    let config = ctx.data().config.get();
+    let tz = config.time.timezone;
+    let daily: String = if user_data.check_daily(tz) {
         "Available".to_string()
     } else {
-        "Not Available".to_string()
+        format!("Resets in {}", clock::until(clock::next_reset(tz)))
     };
 
     let claim: String = if user_data.check_claim() {
@@ -395,10 +519,33 @@ pub async fn wallet(ctx: Context<'_>) -> Result<(), Error> {
     let next_level = user_data.get_next_level();
     let creds: i32 = user_data.get_creds();
     let tickets: i32 = user_data.get_tickets();
+    let wishes: i32 = user_data.get_wishes();
+    let streak: String = match user_data.get_streak(tz, &config.streaks) {
+        0 => "None".to_string(),
+        days => match config.streak_percent(days) {
+            0 => format!("{} days", days),
+            percent => format!("{} days (+{}%)", days, percent),
+        },
+    };
+    let freezes: u32 = user_data.get_freezes();
+    let debt: String = match user_data.get_debt() {
+        0 => String::new(),
+        owed => format!("Debt: **{}** (see **/loan**)\n", owed),
+    };
+    let badges: Vec<String> = user_data
+        .get_badges()
+        .iter()
+        .map(|(season, badge)| format!("{} S{}", badge, season))
+        .collect();
+    let prestige = match badges.is_empty() {
+        true => String::new(),
+        false => format!("Prestige: {}\n", badges.join(" \u{2022} ")),
+    };
+    let season = guild.seasons().season();
 
     let desc = format!(
-        "**Level {} **  -  {}/{}\n﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋\nDaily UwU........... . . . **{}**\nAverage Luck..... . . . **{}**\nClaim Bonus....... . . . **{}**\n\nTotal Creds: **{}** \u{3000}\u{3000}\u{2000}Tickets: **{}**\n",
-        level, xp, next_level, daily, luck, claim, creds, tickets
+        "**Level {} **  -  {}/{}\n﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋﹋\nDaily UwU........... . . . **{}**\nAverage Luck..... . . . **{}**\nClaim Bonus....... . . . **{}**\nDaily Streak...... . . . **{}**\n\nTotal Creds: **{}** \u{3000}\u{3000}\u{2000}Tickets: **{}**\nWishes: **{}** \u{3000}\u{3000}\u{2000}Streak Freezes: **{}**\n{}\nSeason **{}**\n{}",
+        level, xp, next_level, daily, luck, claim, streak, creds, tickets, wishes, freezes, debt, season, prestige
     );
 
     ctx.send(
@@ -417,43 +564,239 @@ pub async fn wallet(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// see where your creds came from and where they went
+#[poise::command(slash_command, guild_only)]
+pub async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    let user = ctx.author();
+    let avatar = user.avatar_url().unwrap_or_default();
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
+    let u = data.get(&user.id).unwrap();
+    let user_data = u.read().await;
+
+    // newest first, ten entries per page
+    let lines: Vec<String> = user_data
+        .get_ledger()
+        .iter()
+        .rev()
+        .map(|entry| {
+            format!(
+                "`{}` **{:+}** {} \u{2192} {}",
+                entry.date.format("%m/%d %H:%M"),
+                entry.amount,
+                entry.reason.describe(),
+                entry.balance
+            )
+        })
+        .collect();
+    drop(user_data);
+
+    if lines.is_empty() {
+        ctx.send(
+            poise::CreateReply::default().embed(
+                serenity::CreateEmbed::new()
+                    .title("History")
+                    .description("No cred history yet, try **/uwu**!")
+                    .thumbnail(&avatar)
+                    .color(data::EMBED_ERROR)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "@~ powered by UwUntu & RustyBamboo",
+                    )),
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let pages: Vec<String> = lines.chunks(10).map(|page| page.join("\n")).collect();
+    let total_pages = pages.len();
+    let history_embed = move |page: usize| {
+        serenity::CreateEmbed::new()
+            .title("History")
+            .description(pages[page].clone())
+            .thumbnail(&avatar)
+            .color(data::EMBED_GOLD)
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Page {}/{} | @~ powered by UwUntu & RustyBamboo",
+                page + 1,
+                pages.len()
+            )))
+    };
+
+    let buttons = vec![
+        serenity::CreateButton::new("open_modal")
+            .label("<")
+            .custom_id("back".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+        serenity::CreateButton::new("open_modal")
+            .label(">")
+            .custom_id("next".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+    ];
+    let components = if total_pages > 1 {
+        vec![serenity::CreateActionRow::Buttons(buttons)]
+    } else {
+        Vec::new()
+    };
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(history_embed(0))
+                .components(components),
+        )
+        .await?;
+
+    let msg_og = Arc::new(RwLock::new(reply.into_message().await?));
+    let msg = Arc::clone(&msg_og);
+
+    let mut reactions = msg
+        .read()
+        .await
+        .await_component_interactions(ctx)
+        .timeout(Duration::new(60, 0))
+        .author_id(user.id)
+        .stream();
+
+    let ctx = ctx.serenity_context().clone();
+
+    tokio::spawn(async move {
+        let mut current_page: usize = 0;
+        while let Some(reaction) = reactions.next().await {
+            match reaction.data.custom_id.as_str() {
+                "back" if current_page > 0 => {
+                    current_page -= 1;
+                }
+                "next" if current_page < total_pages - 1 => {
+                    current_page += 1;
+                }
+                _ => (),
+            };
+
+            reaction
+                .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await
+                .unwrap();
+
+            msg.write()
+                .await
+                .edit(
+                    &ctx,
+                    EditMessage::default().embed(history_embed(current_page)),
+                )
+                .await
+                .unwrap();
+        }
+    });
+
+    Ok(())
+}
+
 //TODO create buttons for various sorts, creds/pokedex/tickets
 /// show the top wealthiest users in the server
-#[poise::command(slash_command)]
-pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
-    let data = &ctx.data().users;
+#[poise::command(slash_command, guild_only)]
+pub async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "see the final standings of a past season"]
+    #[min = 1]
+    season: Option<usize>,
+) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
 
     let mut all_creds = Vec::new();
     let mut all_fortune = Vec::new();
     let mut all_level = Vec::new();
+    let mut all_streak = Vec::new();
+    let config = ctx.data().config.get();
 
     for x in data.iter() {
         let (id, u) = x.pair();
         let u = u.read().await;
+        let luck_stats = u.get_luck_stats();
+
+        // past seasons rank what everyone had when the season ended
+        let (creds, luck_score, luck, rolls, level, xp, streak) = match season {
+            None => (
+                u.get_creds(),
+                luck_stats.average,
+                luck_stats.label(),
+                luck_stats.rolls,
+                u.get_level(),
+                u.get_xp(),
+                u.get_streak(config.time.timezone, &config.streaks),
+            ),
+            Some(season) => match u.get_seasons().iter().find(|s| s.season == season) {
+                Some(r) => (
+                    r.creds,
+                    r.luck_score,
+                    r.luck.clone(),
+                    r.rolls,
+                    r.level,
+                    r.xp,
+                    r.streak,
+                ),
+                None => continue,
+            },
+        };
 
         let user_name = id.to_user(ctx).await?.name.replace('_', "");
-        all_creds.push((*id, u.get_creds(), String::new(), user_name.clone()));
-        all_fortune.push((*id, u.get_luck_score(), u.get_luck(), user_name.clone()));
+        all_creds.push((*id, creds, String::new(), user_name.clone()));
+        // a handful of lucky rolls shouldn't top the board
+        if config.luck.is_ranked(rolls) {
+            all_fortune.push((
+                *id,
+                luck_score,
+                format!("{:.2} {}", luck_score, luck),
+                user_name.clone(),
+            ));
+        }
 
-        let total_xp = u.get_level() * 80 + u.get_xp();
-        all_level.push((
+        let total_xp = level * 80 + xp;
+        all_level.push((*id, total_xp, format!("Level {}", level), user_name.clone()));
+        all_streak.push((
             *id,
-            total_xp,
-            format!("Level {}", u.get_level()),
+            streak as i32,
+            format!("{} days", streak),
             user_name.clone(),
         ));
     }
 
-    all_creds.sort_by(|a, b| b.1.cmp(&a.1));
-    all_fortune.sort_by(|a, b| b.1.cmp(&a.1));
-    all_level.sort_by(|a, b| b.1.cmp(&a.1));
+    if all_creds.is_empty() {
+        ctx.send(
+            poise::CreateReply::default().embed(
+                serenity::CreateEmbed::new()
+                    .title("Leaderboard")
+                    .description(match season {
+                        Some(season) => format!("Season {} hasn't ended yet", season),
+                        None => "No one has an account yet".to_string(),
+                    })
+                    .color(data::EMBED_ERROR)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "@~ powered by UwUntu & RustyBamboo",
+                    )),
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let title = match season {
+        Some(season) => format!("Season {} Leaderboard", season),
+        None => "Leaderboard".to_string(),
+    };
+
+    all_creds.sort_by_key(|x| std::cmp::Reverse(x.1));
+    all_fortune.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let all_fortune: Vec<(UserId, i32, String, String)> = all_fortune
+        .into_iter()
+        .map(|(id, _, luck, user_name)| (id, 0, luck, user_name))
+        .collect();
+    let min_rolls = config.luck.min_rolls;
+    all_level.sort_by_key(|x| std::cmp::Reverse(x.1));
+    all_streak.sort_by_key(|x| std::cmp::Reverse(x.1));
 
     let total_pages = (&all_creds.len()) / 10 + 1;
-    let buttons = vec![
-        serenity::CreateButton::new("open_modal")
-            .label("<")
-            .custom_id("back".to_string())
-            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+    let sort_buttons = vec![
         serenity::CreateButton::new("open_modal")
             .label("Sort by Creds")
             .custom_id("Creds".to_string())
@@ -466,13 +809,26 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
             .label("Sort by Level")
             .custom_id("Level".to_string())
             .style(poise::serenity_prelude::ButtonStyle::Primary),
+        serenity::CreateButton::new("open_modal")
+            .label("Sort by Streak")
+            .custom_id("Streak".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Primary),
+    ];
+    let page_buttons = vec![
+        serenity::CreateButton::new("open_modal")
+            .label("<")
+            .custom_id("back".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
         serenity::CreateButton::new("open_modal")
             .label(">")
             .custom_id("next".to_string())
             .style(poise::serenity_prelude::ButtonStyle::Secondary),
     ];
 
-    let components = vec![serenity::CreateActionRow::Buttons(buttons)];
+    let components = vec![
+        serenity::CreateActionRow::Buttons(sort_buttons),
+        serenity::CreateActionRow::Buttons(page_buttons),
+    ];
     let first_thumbnail = all_creds[0]
         .0
         .to_user(ctx)
@@ -483,7 +839,7 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
     let leaderboard_text = get_leaderboard(&all_creds, "Creds".to_string(), 0);
 
     let embed = serenity::CreateEmbed::new()
-        .title("Leaderboard")
+        .title(title.clone())
         .color(data::EMBED_CYAN)
         .thumbnail(first_thumbnail.clone())
         .description("Here lists the most accomplished in UwUversity!")
@@ -518,10 +874,8 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
         while let Some(reaction) = reactions.next().await {
             let label = reaction.data.custom_id.as_str();
             match label {
-                "back" => {
-                    if current_page > 0 {
-                        current_page -= 10;
-                    }
+                "back" if current_page > 0 => {
+                    current_page -= 10;
                 }
 
                 "Creds" => {
@@ -542,23 +896,33 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
                     current_page = 0;
                 }
 
-                "next" => {
-                    if current_page < total_pages - 1 {
-                        current_page += 10;
-                    }
+                "Streak" => {
+                    sort = "Streak".to_string();
+                    info = all_streak.clone();
+                    current_page = 0;
+                }
+
+                "next" if current_page < total_pages - 1 => {
+                    current_page += 10;
                 }
                 _ => (),
             };
 
-            let leaderboard_text = get_leaderboard(&info, sort.clone(), current_page);
+            // fortune only ranks members with enough rolls, it can be empty
+            let leaderboard_text = match info.is_empty() {
+                true => format!("No one has rolled **{}** d20s yet", min_rolls),
+                false => get_leaderboard(&info, sort.clone(), current_page),
+            };
 
-            let first_thumbnail = info[0]
-                .0
-                .to_user(&ctx)
-                .await
-                .unwrap()
-                .avatar_url()
-                .unwrap_or_default();
+            let first_thumbnail = match info.first() {
+                Some((id, ..)) => id
+                    .to_user(&ctx)
+                    .await
+                    .unwrap()
+                    .avatar_url()
+                    .unwrap_or_default(),
+                None => first_thumbnail.clone(),
+            };
 
             reaction
                 .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
@@ -566,7 +930,7 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
                 .unwrap();
 
             let embed = serenity::CreateEmbed::new()
-                .title("Leaderboard")
+                .title(title.clone())
                 .color(data::EMBED_CYAN)
                 .thumbnail(first_thumbnail.clone())
                 .description("Here lists the most accomplished in UwUversity!")
@@ -585,11 +949,42 @@ pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// How many tickets `creds` buys for someone who owns `owned`, and their cost
+fn affordable_tickets(config: &Config, owned: i32, creds: i32) -> (i32, i32) {
+    // every ticket costs at least base_price, search below that many for the most that fit
+    let (mut low, mut high) = (0, creds.max(0) / config.tickets.base_price.max(1));
+    while low < high {
+        let mid = ((low as i64 + high as i64 + 1) / 2) as i32;
+        match config.ticket_cost(owned, mid) <= creds {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+    (low, config.ticket_cost(owned, low))
+}
+
 /// buy tickets for the battle pass raffle
-#[poise::command(slash_command)]
+#[poise::command(slash_command, guild_only)]
 pub async fn buy_tickets(ctx: Context<'_>) -> Result<(), Error> {
     let user = ctx.author();
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
+
+    if guild.raffle().is_closed() {
+        ctx.send(
+            poise::CreateReply::default().embed(
+                serenity::CreateEmbed::new()
+                    .title("Buy Tickets")
+                    .description("Ticket sales are closed until the raffle is drawn")
+                    .color(data::EMBED_ERROR)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "@~ powered by UwUntu & RustyBamboo",
+                    )),
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
 
     let u = data.get(&user.id).unwrap();
     let user_data = u.read().await;
@@ -597,26 +992,22 @@ pub async fn buy_tickets(ctx: Context<'_>) -> Result<(), Error> {
     let tickets = user_data.get_tickets();
     let creds = user_data.get_creds();
 
-    let tkcost1 = 2000 + 300 * (tickets);
-    let tkcost2 = (2000 + 300 * (tickets + 1)) + tkcost1;
-    let tkcost3 = (2000 + 300 * (tickets + 2)) + tkcost2;
-
-    let mut tkcostmax = 0;
-    let mut tkcount = 0;
-    let mut tkcreds = creds;
-    while 2000 + 300 * (tickets + tkcount) <= tkcreds {
-        tkcreds -= 2000 + 300 * (tickets + tkcount);
-        tkcostmax += 2000 + 300 * (tickets + tkcount);
-        tkcount += 1;
-    }
-
-    println!("{}", tkcount);
+    let config = ctx.data().config.get();
+    let tkcost1 = config.ticket_cost(tickets, 1);
+    let tkcost2 = config.ticket_cost(tickets, 2);
+    let tkcost3 = config.ticket_cost(tickets, 3);
+    let (tkcount, tkcostmax) = affordable_tickets(&config, tickets, creds);
+    drop(user_data);
 
     let mut desc = format!(
         "Welcome to the Shop, buy tickets here to participate in the Server's Battle Pass Raffle! (Total: {})\n\n",
         creds
     );
 
+    if let Some(open) = guild.raffle().open {
+        desc += format!("This season's prize: **{}**\n\n", open.prize).as_str();
+    }
+
     desc.push_str("```yaml\n");
 
     fn table_helper_print(first: String, second: i32) -> String {
@@ -703,90 +1094,122 @@ pub async fn buy_tickets(ctx: Context<'_>) -> Result<(), Error> {
         .timeout(Duration::new(60, 0))
         .stream();
 
+    let saver = Arc::clone(&ctx.data().saver);
+    let guild_id = ctx.guild_id().unwrap();
+    let cancel_image = ctx.data().assets.get("shop.cancel");
+    let success_image = ctx.data().assets.get("shop.success");
+    let config_handle = ctx.data().config.clone();
     let ctx = ctx.serenity_context().clone();
 
     let user_id = user.id;
 
     let u = Arc::clone(&u);
+    let guild = Arc::clone(&guild);
 
     tokio::spawn(async move {
         while let Some(reaction) = reactions.next().await {
-            let bought_tickets;
-            let purchase_cost;
-
             let react_id = reaction.member.clone().unwrap_or_default().user.id;
             if react_id == user_id {
-                match reaction.data.custom_id.as_str() {
-                    "buy-1" => {
-                        bought_tickets = 1;
-                        purchase_cost = tkcost1;
-                    }
-
-                    "buy-2" => {
-                        bought_tickets = 2;
-                        purchase_cost = tkcost2;
-                    }
-
-                    "buy-3" => {
-                        bought_tickets = 3;
-                        purchase_cost = tkcost3;
-                    }
-
-                    "buy-max" => {
-                        bought_tickets = tkcount;
-                        purchase_cost = tkcostmax;
-                    }
-
+                // sales can close while the buttons are up, that cancels the purchase
+                let choice = match guild.raffle().is_closed() {
+                    true => "buy-none",
+                    false => reaction.data.custom_id.as_str(),
+                };
+                // None buys as many as the creds allow
+                let wanted = match choice {
+                    "buy-1" => Some(1),
+                    "buy-2" => Some(2),
+                    "buy-3" => Some(3),
+                    "buy-max" => None,
                     _ => {
                         msg.write()
                             .await
                             .edit(
                                 &ctx,
-                                EditMessage::default()
-                                    .embed(
-                                        serenity::CreateEmbed::default()
-                                            .title("Buy Tickets".to_string())
-                                            .description(&desc)
-                                            .thumbnail("https://cdn.discordapp.com/attachments/1260223476766343188/1262203993245876244/tumblr_inline_pamkf7AfPf1s2a9fg_500.gif?ex=6695be92&is=66946d12&hm=49948cee0fd647192a40c9e88ad890cbbcb63724c460ee61964c99594c9c3a53&")
-                                            .colour(data::EMBED_ERROR)
-                                            .footer(serenity::CreateEmbedFooter::new(
-                                                "@~ powered by UwUntu & RustyBamboo",
-                                            )),
-                                    )
-                                    .components(Vec::new()),
+                                assets::attach(
+                                    EditMessage::default()
+                                        .embed(
+                                            serenity::CreateEmbed::default()
+                                                .title("Buy Tickets".to_string())
+                                                .description(&desc)
+                                                .asset_thumbnail(&cancel_image)
+                                                .colour(data::EMBED_ERROR)
+                                                .footer(serenity::CreateEmbedFooter::new(
+                                                    "@~ powered by UwUntu & RustyBamboo",
+                                                )),
+                                        )
+                                        .components(Vec::new()),
+                                    &[&cancel_image],
+                                )
+                                .await,
                             )
                             .await
                             .unwrap();
                         return;
                     }
-                }
+                };
+
+                // priced again from the wallet as it is now, another purchase
+                // may have happened since the prompt opened
+                let bought = {
+                    let config = config_handle.get();
+                    let mut user_data = u.write().await;
+                    let owned = user_data.get_tickets();
+                    let creds = user_data.get_creds();
+                    let (count, cost) = match wanted {
+                        Some(count) => (count, config.ticket_cost(owned, count)),
+                        None => affordable_tickets(&config, owned, creds),
+                    };
+                    if count < 1 || cost > creds {
+                        Err(creds)
+                    } else {
+                        user_data.sub_creds(cost, data::CredReason::Tickets { count });
+                        user_data.add_tickets(count);
+                        saver.mark_dirty(guild_id, user_id);
+                        Ok((count, cost))
+                    }
+                };
 
+                let (embed, image) = match bought {
+                    Ok((count, cost)) => (
+                        serenity::CreateEmbed::new()
+                            .title("Buy Tickets".to_string())
+                            .description(format!(
+                                "You purchased **{}** ticket(s)! Ganbatte!! (-{} creds)",
+                                count, cost
+                            ))
+                            .asset_image(&success_image)
+                            .color(data::EMBED_CYAN),
+                        &success_image,
+                    ),
+                    Err(creds) => (
+                        serenity::CreateEmbed::new()
+                            .title("Buy Tickets".to_string())
+                            .description(format!(
+                                "You only have **{}** creds now, not enough for those tickets",
+                                creds
+                            ))
+                            .asset_thumbnail(&cancel_image)
+                            .color(data::EMBED_ERROR),
+                        &cancel_image,
+                    ),
+                };
                 msg.write()
                     .await
                     .edit(
                         &ctx,
-                        EditMessage::default()
-                            .embed(
-                                serenity::CreateEmbed::new()
-                                    .title("Buy Tickets".to_string())
-                                    .description(format!(
-                                        "You purchased **{}** ticket(s)! Ganbatte!! (-{} creds)",
-                                        bought_tickets, purchase_cost
-                                    ))
-                                    .image("https://cdn.discordapp.com/attachments/1260223476766343188/1262202607980777662/tumblr_n8dtwljTrx1tt5tk6o1_500.gif?ex=6695bd48&is=66946bc8&hm=da981bf028647549f958bb60e30c9c2f5d4635b6b597c50fb58f50b1618f7619&")
-                                    .color(data::EMBED_CYAN)
-                                    .footer(serenity::CreateEmbedFooter::new(
-                                        "@~ powered by UwUntu & RustyBamboo",
-                                    )),
-                            )
-                            .components(Vec::new()),
+                        assets::attach(
+                            EditMessage::default()
+                                .embed(embed.footer(serenity::CreateEmbedFooter::new(
+                                    "@~ powered by UwUntu & RustyBamboo",
+                                )))
+                                .components(Vec::new()),
+                            &[image],
+                        )
+                        .await,
                     )
                     .await
                     .unwrap();
-                let mut user_data = u.write().await;
-
-                user_data.sub_creds(purchase_cost);
-                user_data.add_tickets(bought_tickets);
 
                 return;
             }
@@ -815,19 +1238,277 @@ pub async fn buy_tickets(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Why a transfer can't happen right now, checked again when it is confirmed
+fn check_pay(
+    sender: &data::UserData,
+    receiver: &data::UserData,
+    amount: i32,
+    config: &Config,
+) -> Option<String> {
+    let since = Utc::now() - chrono::Duration::hours(24);
+    let pay = &config.pay;
+
+    // creds owed come first, otherwise debt could be dodged by handing creds off
+    if sender.get_debt() > 0 {
+        return Some(format!(
+            "You owe **{}** creds, pay them back with **/repay** before sending any",
+            sender.get_debt()
+        ));
+    }
+
+    if amount > sender.get_creds() {
+        return Some(format!(
+            "You only have **{}** creds, you can't send **{}**",
+            sender.get_creds(),
+            amount
+        ));
+    }
+
+    let sent = sender.paid_since(since);
+    if amount > pay.daily_send_limit - sent {
+        return Some(format!(
+            "You can only send **{}** creds a day, **{}** left for now",
+            pay.daily_send_limit,
+            (pay.daily_send_limit - sent).max(0)
+        ));
+    }
+
+    let received = receiver.received_since(since);
+    let arriving = amount - config.pay_tax(amount);
+    if arriving > pay.daily_receive_limit - received {
+        return Some(format!(
+            "They can only receive **{}** creds a day, **{}** left for now",
+            pay.daily_receive_limit,
+            (pay.daily_receive_limit - received).max(0)
+        ));
+    }
+
+    None
+}
+
+/// send some of your creds to another member
+#[poise::command(slash_command, guild_only)]
+pub async fn pay(
+    ctx: Context<'_>,
+    #[description = "who gets the creds"] user: serenity::User,
+    #[description = "how many creds to send"]
+    #[min = 1]
+    amount: u32,
+) -> Result<(), Error> {
+    let author = ctx.author();
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+    let amount = amount.min(i32::MAX as u32) as i32;
+
+    let fail = |desc: String| {
+        poise::CreateReply::default().ephemeral(true).embed(
+            serenity::CreateEmbed::new()
+                .title("Pay")
+                .description(desc)
+                .color(data::EMBED_ERROR)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        )
+    };
+
+    if config.pay.daily_send_limit == 0 || config.pay.daily_receive_limit == 0 {
+        ctx.send(fail("Transfers are turned off right now".to_string()))
+            .await?;
+        return Ok(());
+    }
+    if user.id == author.id || user.bot {
+        ctx.send(fail("You can only pay other members".to_string()))
+            .await?;
+        return Ok(());
+    }
+
+    let sender = Arc::clone(&guild.users.get(&author.id).unwrap());
+    let receiver = match guild.users.get(&user.id) {
+        Some(u) => Arc::clone(&u),
+        None => {
+            ctx.send(fail(format!(
+                "<@{}> doesn't have an account yet, they need to use professorBot first",
+                user.id
+            )))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // lower id first, like the confirm below, so the two can't deadlock
+    let problem = {
+        let (from, to);
+        if author.id < user.id {
+            from = sender.read().await;
+            to = receiver.read().await;
+        } else {
+            to = receiver.read().await;
+            from = sender.read().await;
+        }
+        check_pay(&from, &to, amount, &config)
+    };
+    if let Some(problem) = problem {
+        ctx.send(fail(problem)).await?;
+        return Ok(());
+    }
+
+    let tax = config.pay_tax(amount);
+    let mut desc = format!("Send **{}** creds to <@{}>?", amount, user.id);
+    if tax > 0 {
+        desc += format!(
+            "\n\nA **{}%** tax applies, they will receive **{}** creds",
+            config.pay.tax_percent,
+            amount - tax
+        )
+        .as_str();
+    }
+
+    let buttons = vec![
+        serenity::CreateButton::new("pay-confirm")
+            .label("Confirm")
+            .style(poise::serenity_prelude::ButtonStyle::Success),
+        serenity::CreateButton::new("pay-cancel")
+            .label("Cancel")
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+    ];
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(
+                    serenity::CreateEmbed::new()
+                        .title("Pay")
+                        .description(&desc)
+                        .thumbnail(user.avatar_url().unwrap_or_default())
+                        .color(data::EMBED_DEFAULT)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                )
+                .components(vec![serenity::CreateActionRow::Buttons(buttons)]),
+        )
+        .await?;
+
+    let msg_og = Arc::new(RwLock::new(reply.into_message().await?));
+
+    let msg = Arc::clone(&msg_og);
+
+    let mut reactions = msg
+        .read()
+        .await
+        .await_component_interactions(ctx)
+        .author_id(author.id)
+        .timeout(Duration::new(60, 0))
+        .stream();
+
+    let saver = Arc::clone(&ctx.data().saver);
+    let config_handle = ctx.data().config.clone();
+    let ctx = ctx.serenity_context().clone();
+    let sender_id = author.id;
+    let receiver_id = user.id;
+
+    tokio::spawn(async move {
+        let (title, desc, color) = match reactions.next().await {
+            Some(reaction) if reaction.data.custom_id == "pay-confirm" => {
+                // always lock the lower id first so two members paying each other can't deadlock
+                let (mut from, mut to);
+                if sender_id < receiver_id {
+                    from = sender.write().await;
+                    to = receiver.write().await;
+                } else {
+                    to = receiver.write().await;
+                    from = sender.write().await;
+                }
+
+                // balances and limits may have moved while the prompt was open
+                let config = config_handle.get();
+                match check_pay(&from, &to, amount, &config) {
+                    Some(problem) => ("Pay", problem, data::EMBED_ERROR),
+                    None => {
+                        let tax = config.pay_tax(amount);
+                        from.sub_creds(
+                            amount - tax,
+                            data::CredReason::PaySent {
+                                to: receiver_id,
+                                tax,
+                            },
+                        );
+                        // the tax leaves the economy, so it gets its own entry
+                        if tax > 0 {
+                            from.sub_creds(tax, data::CredReason::PayTax { to: receiver_id });
+                        }
+                        if amount > tax {
+                            to.add_creds(
+                                amount - tax,
+                                data::CredReason::PayReceived { from: sender_id },
+                            );
+                        }
+                        saver.mark_dirty(guild_id, sender_id);
+                        saver.mark_dirty(guild_id, receiver_id);
+
+                        (
+                            "Payment Sent!",
+                            format!(
+                                "<@{}> sent **{}** creds to <@{}>{}",
+                                sender_id,
+                                amount - tax,
+                                receiver_id,
+                                if tax > 0 {
+                                    format!(" (-{} tax)", tax)
+                                } else {
+                                    String::new()
+                                }
+                            ),
+                            data::EMBED_GOLD,
+                        )
+                    }
+                }
+            }
+            Some(_) => ("Pay", "Payment cancelled".to_string(), data::EMBED_ERROR),
+            None => ("Pay", "Payment timed out".to_string(), data::EMBED_ERROR),
+        };
+
+        msg.write()
+            .await
+            .edit(
+                &ctx,
+                EditMessage::default()
+                    .embed(
+                        serenity::CreateEmbed::new()
+                            .title(title)
+                            .description(desc)
+                            .color(color)
+                            .footer(serenity::CreateEmbedFooter::new(
+                                "@~ powered by UwUntu & RustyBamboo",
+                            )),
+                    )
+                    .components(Vec::new()),
+            )
+            .await
+            .unwrap();
+    });
+
+    Ok(())
+}
+
 /// get the status of voice channels
-#[poise::command(slash_command)]
+#[poise::command(slash_command, guild_only)]
 pub async fn voice_status(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
     let data = &ctx.data().voice_users;
 
     let mut out: Vec<(UserId, VoiceUser)> = Vec::new();
 
     for x in data.iter() {
-        let (id, u) = x.pair();
-        out.push((*id, u.clone()));
+        let ((gid, id), u) = x.pair();
+        if *gid == guild_id {
+            out.push((*id, u.clone()));
+        }
     }
 
-    out.sort_by(|a, b| a.1.joined.cmp(&b.1.joined));
+    out.sort_by_key(|x| x.1.joined);
 
     let now = chrono::Utc::now();
 
@@ -978,15 +1659,11 @@ pub async fn info(ctx: Context<'_>) -> Result<(), Error> {
         while let Some(reaction) = reactions.next().await {
             let label = reaction.data.custom_id.as_str();
             match label {
-                "back" => {
-                    if current_page > 0 {
-                        current_page -= 1;
-                    }
+                "back" if current_page > 0 => {
+                    current_page -= 1;
                 }
-                "next" => {
-                    if current_page < subs.len() - 1 {
-                        current_page += 1;
-                    }
+                "next" if current_page < subs.len() - 1 => {
+                    current_page += 1;
                 }
                 _ => (),
             };
@@ -1016,3 +1693,29 @@ pub async fn info(ctx: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affordable_tickets_buys_as_many_as_fit() {
+        let config = Config::default();
+        let price = |n| config.ticket_price(n);
+        assert_eq!(affordable_tickets(&config, 0, price(0) - 1), (0, 0));
+        assert_eq!(affordable_tickets(&config, 0, price(0)), (1, price(0)));
+        let two = price(4) + price(5);
+        assert_eq!(affordable_tickets(&config, 4, two + price(6) - 1), (2, two));
+    }
+
+    #[test]
+    fn affordable_tickets_stays_quick_on_a_flat_curve() {
+        let mut config = Config::default();
+        config.tickets.base_price = 1;
+        config.tickets.price_step = 0;
+        assert_eq!(
+            affordable_tickets(&config, 0, i32::MAX),
+            (i32::MAX, i32::MAX)
+        );
+    }
+}
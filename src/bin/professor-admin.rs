@@ -0,0 +1,406 @@
+//!---------------------------------------------------------------------!
+//! Offline admin tool for inspecting and repairing professorBot's      !
+//! save data without connecting to Discord                             !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - list                                                      !
+//!     [x] - show                                                      !
+//!     [x] - creds                                                     !
+//!     [x] - tickets                                                   !
+//!     [x] - level                                                     !
+//!     [x] - remove-clip                                               !
+//!     [x] - merge                                                     !
+//!     [x] - export-csv                                                !
+//!---------------------------------------------------------------------!
+
+use dashmap::DashMap;
+use professor_rs::data::{CredReason, GuildSave, SaveData, UserData};
+use professor_rs::helper::parse_user_mention;
+use professor_rs::storage::{self, JsonStore, SqliteStore, UserStore};
+use professor_rs::{serenity, Error};
+use std::fs;
+use std::process::exit;
+
+const USAGE: &str = "\
+usage: professor-admin [--json <file> | --sqlite <file>] [--guild <id>] <command> [args]
+
+Without --json or --sqlite the store is picked like the bot does (STORAGE env var).
+--guild can be left out when the save only has one guild, list and export-csv
+cover every guild without it.
+Stop the bot before making changes, it would overwrite them on its next save.
+
+commands:
+    list                                  list every user
+    show <user>                           show a user's profile and recent cred history
+    creds <user> <+n|-n> [note]           give or take creds, recorded in the ledger
+    tickets <user> <+n|-n>                give or take raffle tickets
+    level <user> <n>                      set a user's level
+    remove-clip <user> <index>            remove one of a user's clips (index from show)
+    merge <other> [--prefer-other]        copy guilds and users from another save file into this one
+    export-csv <out.csv>                  write every user to a csv file
+";
+
+fn main() {
+    dotenv::dotenv().ok();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let store = match open_store(&mut args) {
+        Ok(store) => store,
+        Err(e) => fail(&format!("Failed to open store: {}", e)),
+    };
+
+    let guild = match parse_guild_flag(&mut args) {
+        Ok(guild) => guild,
+        Err(e) => fail(&e.to_string()),
+    };
+
+    if args.is_empty() {
+        print!("{}", USAGE);
+        exit(1);
+    }
+
+    if let Err(e) = run(store.as_ref(), guild, &args) {
+        fail(&e.to_string());
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    exit(1);
+}
+
+fn open_store(args: &mut Vec<String>) -> Result<Box<dyn UserStore>, Error> {
+    for (flag, sqlite) in [("--json", false), ("--sqlite", true)] {
+        if let Some(pos) = args.iter().position(|a| a == flag) {
+            if pos + 1 >= args.len() {
+                return Err(format!("{} needs a file", flag).into());
+            }
+            let path = args.remove(pos + 1);
+            args.remove(pos);
+            return open_path(&path, sqlite);
+        }
+    }
+    storage::open_store()
+}
+
+fn open_path(path: &str, sqlite: bool) -> Result<Box<dyn UserStore>, Error> {
+    if sqlite {
+        Ok(Box::new(SqliteStore::open(path)?))
+    } else {
+        Ok(Box::new(JsonStore::new(path, storage::BACKUP_DIR)))
+    }
+}
+
+fn parse_guild_flag(args: &mut Vec<String>) -> Result<Option<serenity::GuildId>, Error> {
+    let pos = match args.iter().position(|a| a == "--guild") {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    if pos + 1 >= args.len() {
+        return Err("--guild needs a guild id".into());
+    }
+    let id = args.remove(pos + 1);
+    args.remove(pos);
+    match id.parse::<u64>() {
+        Ok(id) if id != 0 => Ok(Some(serenity::GuildId::new(id))),
+        _ => Err(format!("not a guild id: {}", id).into()),
+    }
+}
+
+/// the guild a command works on, the only one in the save if none was given
+fn pick_guild(
+    save: &SaveData,
+    guild: Option<serenity::GuildId>,
+) -> Result<serenity::GuildId, Error> {
+    if let Some(guild) = guild {
+        return match save.guilds.contains_key(&guild) {
+            true => Ok(guild),
+            false => Err(format!("no such guild: {}", guild.get()).into()),
+        };
+    }
+
+    let ids: Vec<_> = save.guilds.iter().map(|g| *g.key()).collect();
+    match ids.as_slice() {
+        [only] => Ok(*only),
+        [] => Err("the save has no guilds".into()),
+        _ => Err(format!(
+            "the save has {} guilds, pick one with --guild: {}",
+            ids.len(),
+            ids.iter()
+                .map(|id| id.get().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into()),
+    }
+}
+
+/// every (guild, user) pair, limited to one guild when --guild was given
+fn all_users(
+    save: &SaveData,
+    guild: Option<serenity::GuildId>,
+) -> Vec<(serenity::GuildId, serenity::UserId, UserData)> {
+    let mut users = Vec::new();
+    for g in save.guilds.iter() {
+        if guild.is_some_and(|guild| guild != *g.key()) {
+            continue;
+        }
+        for x in g.users.iter() {
+            users.push((*g.key(), *x.key(), x.value().clone()));
+        }
+    }
+    users
+}
+
+fn parse_user(arg: &str) -> Result<serenity::UserId, Error> {
+    match parse_user_mention(arg.to_string()) {
+        1 => Err(format!("not a user id or mention: {}", arg).into()),
+        id => Ok(serenity::UserId::new(id)),
+    }
+}
+
+fn parse_num(arg: &str) -> Result<i32, Error> {
+    arg.parse::<i32>()
+        .map_err(|_| format!("not a number: {}", arg).into())
+}
+
+fn arg<'a>(args: &'a [String], idx: usize, name: &str) -> Result<&'a str, Error> {
+    args.get(idx)
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing <{}>\n\n{}", name, USAGE).into())
+}
+
+fn run(
+    store: &dyn UserStore,
+    guild: Option<serenity::GuildId>,
+    args: &[String],
+) -> Result<(), Error> {
+    let save = store.load()?.unwrap_or_default();
+
+    match args[0].as_str() {
+        "list" => {
+            let mut users = all_users(&save, guild);
+            users.sort_by_key(|(gid, _, u)| (*gid, std::cmp::Reverse(u.get_creds())));
+
+            println!(
+                "{:<20} {:<20} {:>6} {:>10} {:>8} {:>6}",
+                "guild", "user", "level", "creds", "tickets", "clips"
+            );
+            for (gid, id, u) in &users {
+                println!(
+                    "{:<20} {:<20} {:>6} {:>10} {:>8} {:>6}",
+                    gid.get(),
+                    id.get(),
+                    u.get_level(),
+                    u.get_creds(),
+                    u.get_tickets(),
+                    u.submits.iter().flatten().count()
+                );
+            }
+            println!("{} users", users.len());
+            Ok(())
+        }
+
+        "show" => {
+            let id = parse_user(arg(args, 1, "user")?)?;
+            let gid = pick_guild(&save, guild)?;
+            let g = save.guilds.get(&gid).unwrap();
+            let u = g.users.get(&id).ok_or("no such user")?;
+            print_user(id, &u);
+            Ok(())
+        }
+
+        "creds" => {
+            let id = parse_user(arg(args, 1, "user")?)?;
+            let amount = parse_num(arg(args, 2, "+n|-n")?)?;
+            let note = if args.len() > 3 {
+                args[3..].join(" ")
+            } else {
+                "professor-admin".to_string()
+            };
+
+            edit_user(store, &save, pick_guild(&save, guild)?, id, |u| {
+                let reason = CredReason::Admin { note };
+                if amount >= 0 {
+                    u.add_creds(amount, reason)
+                } else {
+                    u.sub_creds(-amount, reason)
+                }
+            })
+        }
+
+        "tickets" => {
+            let id = parse_user(arg(args, 1, "user")?)?;
+            let amount = parse_num(arg(args, 2, "+n|-n")?)?;
+
+            edit_user(store, &save, pick_guild(&save, guild)?, id, |u| {
+                if amount >= 0 {
+                    u.add_tickets(amount)
+                } else {
+                    u.remove_tickets(-amount)
+                }
+            })
+        }
+
+        "level" => {
+            let id = parse_user(arg(args, 1, "user")?)?;
+            let level = parse_num(arg(args, 2, "n")?)?;
+            edit_user(store, &save, pick_guild(&save, guild)?, id, |u| {
+                u.set_level(level)
+            })
+        }
+
+        "remove-clip" => {
+            let id = parse_user(arg(args, 1, "user")?)?;
+            let index = parse_num(arg(args, 2, "index")?)?;
+            edit_user(store, &save, pick_guild(&save, guild)?, id, |u| {
+                index >= 0 && u.remove_submit(index as usize)
+            })
+        }
+
+        "merge" => {
+            let other_path = arg(args, 1, "other")?;
+            let prefer_other = args.iter().any(|a| a == "--prefer-other");
+            let other = open_path(other_path, other_path.ends_with(".db"))?
+                .load()?
+                .unwrap_or_default();
+
+            let (mut added, mut replaced, mut kept) = (0, 0, 0);
+            for g in other.guilds.iter() {
+                let gid = *g.key();
+                if guild.is_some_and(|guild| guild != gid) {
+                    continue;
+                }
+                // a guild new to this save brings its config along
+                let ours = save.guilds.entry(gid).or_insert_with(|| GuildSave {
+                    users: DashMap::new(),
+                    ..g.value().clone()
+                });
+
+                for x in g.users.iter() {
+                    let (id, u) = x.pair();
+                    if !ours.users.contains_key(id) {
+                        ours.users.insert(*id, u.clone());
+                        added += 1;
+                    } else if prefer_other {
+                        ours.users.insert(*id, u.clone());
+                        replaced += 1;
+                    } else {
+                        println!("kept existing profile for {} in {}", id.get(), gid.get());
+                        kept += 1;
+                    }
+                }
+            }
+
+            store.replace(&save)?;
+            println!(
+                "merged {}: {} added, {} replaced, {} kept",
+                other_path, added, replaced, kept
+            );
+            Ok(())
+        }
+
+        "export-csv" => {
+            let out = arg(args, 1, "out.csv")?;
+            let mut csv = String::from(
+                "guild_id,user_id,level,xp,creds,tickets,daily_count,bonus_count,clips,last_daily\n",
+            );
+            let users = all_users(&save, guild);
+            for (gid, id, u) in &users {
+                csv += &format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
+                    gid.get(),
+                    id.get(),
+                    u.get_level(),
+                    u.get_xp(),
+                    u.get_creds(),
+                    u.get_tickets(),
+                    u.get_daily_count(),
+                    u.get_bonus(),
+                    u.submits.iter().flatten().count(),
+                    u.get_last_daily().to_rfc3339()
+                );
+            }
+            fs::write(out, csv)?;
+            println!("exported {} users to {}", users.len(), out);
+            Ok(())
+        }
+
+        other => Err(format!("unknown command: {}\n\n{}", other, USAGE).into()),
+    }
+}
+
+/// apply a change through UserData's own methods, and save only if it was accepted
+fn edit_user(
+    store: &dyn UserStore,
+    save: &SaveData,
+    guild_id: serenity::GuildId,
+    id: serenity::UserId,
+    edit: impl FnOnce(&mut UserData) -> bool,
+) -> Result<(), Error> {
+    let guild = save.guilds.get(&guild_id).unwrap();
+    let mut u = guild.users.get_mut(&id).ok_or("no such user")?;
+    if !edit(&mut u) {
+        return Err("change rejected, check the arguments".into());
+    }
+    let updated = u.clone();
+    drop(u);
+
+    // stores that write single users only need the edited one
+    if store.incremental() {
+        let one = GuildSave {
+            users: DashMap::new(),
+            ..guild.clone()
+        };
+        one.users.insert(id, updated.clone());
+        drop(guild);
+
+        let guilds = DashMap::new();
+        guilds.insert(guild_id, one);
+        store.save(&SaveData::new(guilds))?;
+    } else {
+        drop(guild);
+        store.replace(save)?;
+    }
+
+    print_user(id, &updated);
+    Ok(())
+}
+
+fn print_user(id: serenity::UserId, u: &UserData) {
+    println!("user        {}", id.get());
+    println!(
+        "level       {} ({}/{} xp)",
+        u.get_level(),
+        u.get_xp(),
+        u.get_next_level()
+    );
+    println!("creds       {}", u.get_creds());
+    println!("tickets     {}", u.get_tickets());
+    println!(
+        "dailies     {} (last {}), bonus {}/3, luck {}",
+        u.get_daily_count(),
+        u.get_last_daily().format("%Y-%m-%d %H:%M"),
+        u.get_bonus(),
+        u.get_luck()
+    );
+
+    println!("clips");
+    for (idx, clip) in u.submits.iter().enumerate() {
+        if let Some(clip) = clip {
+            let rating = clip.rating.map(|r| r.to_string()).unwrap_or("-".into());
+            println!("  [{}] {} ({}) [{}/5]", idx, clip.title, clip.link, rating);
+        }
+    }
+
+    println!("recent creds");
+    for entry in u.get_ledger().iter().rev().take(10) {
+        println!(
+            "  {} {:+} {} -> {}",
+            entry.date.format("%Y-%m-%d %H:%M"),
+            entry.amount,
+            entry.reason.describe(),
+            entry.balance
+        );
+    }
+}
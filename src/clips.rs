@@ -9,23 +9,28 @@
 //!     [x] - next_clip                                                 !
 //!---------------------------------------------------------------------!
 
+use crate::assets::{self, ShowAsset};
 use crate::data::{self, ClipData};
 use crate::{serenity, Context, Error};
 use dashmap::DashMap;
 use poise::serenity_prelude::futures::StreamExt;
 use poise::serenity_prelude::{EditMessage, ReactionType};
 use rand::seq::IteratorRandom;
-use rand::thread_rng;
 use regex::Regex;
-use std::env;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
 pub async fn check_mod(ctx: Context<'_>) -> Result<bool, Error> {
-    let mod_id = ctx.data().mod_id;
-    let guild_id = ctx.guild_id().unwrap();
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Ok(false),
+    };
+    let mod_id = match ctx.data().guild(guild_id).config().mod_role {
+        Some(mod_id) => mod_id,
+        None => return Ok(false),
+    };
     if let Ok(b) = ctx.author().has_role(ctx, guild_id, mod_id).await {
         if b {
             return Ok(true);
@@ -43,15 +48,16 @@ fn is_youtube_or_medal_url(url: &str) -> bool {
 }
 
 /// submit a youtube or medal clip for clip night!
-#[poise::command(slash_command)]
+#[poise::command(slash_command, guild_only)]
 pub async fn submit_clip(
     ctx: Context<'_>,
     #[description = "the name of your clip"] title: String,
     #[description = "the youtube or medal link of your clip"] link: String,
 ) -> Result<(), Error> {
-    let sub_chat = env::var("SUBMIT").expect("Failed to load SUBMIT channel id");
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let sub_chat = guild.config().submit;
 
-    if ctx.channel_id().get().to_string() != sub_chat {
+    if sub_chat != Some(ctx.channel_id()) {
         ctx.send(
             poise::CreateReply::default().embed(
                 serenity::CreateEmbed::default()
@@ -68,24 +74,29 @@ pub async fn submit_clip(
     }
 
     if !is_youtube_or_medal_url(&link) {
+        let invalid_image = ctx.data().assets.get("clip.invalid_link");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::default()
-                    .title("Submit Clip")
-                    .description("Invalid link - Link must either be youtube or medal")
-                    .thumbnail("https://cdn.discordapp.com/attachments/1196582162057662484/1197004718631833650/tenor.gif?ex=65b9b084&is=65a73b84&hm=0368979e5bdf0c258f6b344ec2b79826459b3ec4c937374e05ec77f131adf37f&")
-                    .color(data::EMBED_ERROR)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::default()
+                        .title("Submit Clip")
+                        .description("Invalid link - Link must either be youtube or medal")
+                        .asset_thumbnail(&invalid_image)
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&invalid_image],
+            )
+            .await,
         )
         .await?;
         return Ok(());
     }
 
     let user = ctx.author();
-    let data = &ctx.data().users;
+    let data = &guild.users;
     let u = data.get_mut(&user.id).unwrap();
     let mut user_data = u.write().await;
 
@@ -98,21 +109,27 @@ pub async fn submit_clip(
 
     let clip = ClipData::new(title, link);
 
-    let check = user_data.add_submit(clip);
+    let max_clips = ctx.data().config.get().clips.max_clips;
+    let check = user_data.add_submit(clip, max_clips);
 
     if !check {
+        let max_image = ctx.data().assets.get("clip.max_reached");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::default()
-                    .title("Submit Clip")
-                    .thumbnail(&avatar)
-                    .description("Max clips reached...")
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354794588307456/tenor_1.gif?ex=65d81121&is=65c59c21&hm=35114062e5a4516b69da081842189520df9b846bce5b8547f83ad39c91c2d1cd&")
-                    .color(data::EMBED_FAIL)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::default()
+                        .title("Submit Clip")
+                        .thumbnail(&avatar)
+                        .description("Max clips reached...")
+                        .asset_image(&max_image)
+                        .color(data::EMBED_FAIL)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&max_image],
+            )
+            .await,
         )
         .await?;
         return Ok(());
@@ -143,7 +160,8 @@ pub async fn server_clips(ctx: Context<'_>) -> Result<(), Error> {
     };
 
     let icon_url = guild.icon_url().unwrap_or_default();
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
 
     let mut all_clips = Vec::new();
 
@@ -151,10 +169,8 @@ pub async fn server_clips(ctx: Context<'_>) -> Result<(), Error> {
     for x in data.iter() {
         let (id, u) = x.pair();
         let u = u.read().await;
-        for c in &u.submits {
-            if let Some(c) = c {
-                all_clips.push((*id, c.clone()));
-            }
+        for c in u.submits.iter().flatten() {
+            all_clips.push((*id, c.clone()));
         }
         let author = id.to_user(ctx).await.unwrap();
         let clips = u.get_submissions(true, false);
@@ -172,17 +188,22 @@ pub async fn server_clips(ctx: Context<'_>) -> Result<(), Error> {
     println!("{:}", &desc);
 
     if all_clips.is_empty() {
+        let empty_image = ctx.data().assets.get("clip.server_empty");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::default()
-                    .title("Server Clips")
-                    .description("Where are the clips...")
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354794156429362/tenor_2.gif?ex=65d81121&is=65c59c21&hm=c402afb9f3a578f018657cd60a4b8ec1cefccc09e26b0830701037593852b65d&")
-                    .color(data::EMBED_ERROR)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::default()
+                        .title("Server Clips")
+                        .description("Where are the clips...")
+                        .asset_image(&empty_image)
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&empty_image],
+            )
+            .await,
         )
         .await?;
         return Ok(());
@@ -247,31 +268,37 @@ pub async fn server_clips(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 /// [!] view and edit your submitted clips
-#[poise::command(slash_command, track_edits)]
+#[poise::command(slash_command, track_edits, guild_only)]
 pub async fn my_clips(ctx: Context<'_>) -> Result<(), Error> {
     let author = ctx.author();
     let id = author.id;
     let avatar = author.avatar_url().unwrap_or_default().to_string();
 
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
     let u = data.get(&id).unwrap();
     let clips = u.read().await;
 
     let desc = clips.get_submissions(false, true).join("\n");
 
     if clips.submits.is_empty() {
+        let empty_image = ctx.data().assets.get("clip.mine_empty");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::default()
-                    .title("My Clips")
-                    .description("You have not submitted a clip yet, submit your first clip with /submit-clip!!")
-                    .thumbnail(&avatar)
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354793753903104/tenor_3.gif?ex=65d81121&is=65c59c21&hm=32b8b0926677e68d225a2085b4a99ac63d0356b5cb4d05d54e13f5013b9a8664&")
-                    .color(data::EMBED_ERROR)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::default()
+                        .title("My Clips")
+                        .description("You have not submitted a clip yet, submit your first clip with /submit-clip!!")
+                        .thumbnail(&avatar)
+                        .asset_image(&empty_image)
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&empty_image],
+            )
+            .await,
         )
         .await?;
         return Ok(());
@@ -321,16 +348,20 @@ pub async fn my_clips(ctx: Context<'_>) -> Result<(), Error> {
         .author_id(author.id);
 
     let u = Arc::clone(&u);
+    let saver = Arc::clone(&ctx.data().saver);
+    let guild_id = ctx.guild_id().unwrap();
+    let removed_image = ctx.data().assets.get("clip.removed");
     let ctx = ctx.serenity_context().clone();
 
     tokio::spawn(async move {
         if let Some(reaction) = reactions.await {
-            let id = reaction.data.custom_id.chars().last().unwrap();
-            let i = id.to_digit(10);
+            let digit = reaction.data.custom_id.chars().last().unwrap();
+            let i = digit.to_digit(10);
             if let Some(i) = i {
                 let mut clip = u.write().await;
 
                 clip.remove_submit(i as usize);
+                saver.mark_dirty(guild_id, id);
             }
             reaction
                 .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
@@ -340,19 +371,23 @@ pub async fn my_clips(ctx: Context<'_>) -> Result<(), Error> {
                 .await
                 .edit(
                     &ctx,
-                    EditMessage::default()
-                        .embed(
-                            serenity::CreateEmbed::new()
-                                .title("My Clips")
-                                .description("Clip Removed!")
-                                .thumbnail(&avatar)
-                                .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354795012071424/tenor.gif?ex=65d81121&is=65c59c21&hm=e283dc1b9ffdeb45b85d8caabfdc68dedbf18faef0bdf84967f7d242749476cd&")
-                                .color(data::EMBED_CYAN)
-                                .footer(serenity::CreateEmbedFooter::new(
-                                    "@~ powered by UwUntu & RustyBamboo",
-                                )),
-                        )
-                        .components(Vec::new()),
+                    assets::attach(
+                        EditMessage::default()
+                            .embed(
+                                serenity::CreateEmbed::new()
+                                    .title("My Clips")
+                                    .description("Clip Removed!")
+                                    .thumbnail(&avatar)
+                                    .asset_image(&removed_image)
+                                    .color(data::EMBED_CYAN)
+                                    .footer(serenity::CreateEmbedFooter::new(
+                                        "@~ powered by UwUntu & RustyBamboo",
+                                    )),
+                            )
+                            .components(Vec::new()),
+                        &[&removed_image],
+                    )
+                    .await,
                 )
                 .await
                 .unwrap();
@@ -392,7 +427,8 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
 
     let icon_url = guild.icon_url().unwrap_or_default();
 
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
 
     let mut all_clips = Vec::new();
 
@@ -410,21 +446,33 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
         }
     }
 
-    let rand_clip = all_clips.iter().choose(&mut thread_rng());
+    // guild members come in no set order, sorting keeps the logged seed replayable
+    all_clips.sort_by_key(|(id, _, idx)| (*id, *idx));
+    let rand_clip = all_clips.iter().choose(
+        &mut ctx
+            .data()
+            .rng
+            .draw(&format!("next_clip {}", ctx.guild_id().unwrap())),
+    );
 
     if rand_clip.is_none() {
+        let none_left_image = ctx.data().assets.get("clip.none_left");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::default()
-                    .title("Next Clip")
-                    .description("No more clips!")
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354793246138408/tenor_4.gif?ex=65d81121&is=65c59c21&hm=752fa8c3dbc4ef91ec632f3988261c0e7628fb6ca54170ffdd6439a5de9a3a9b&")
-                    .thumbnail(&icon_url)
-                    .colour(data::EMBED_FAIL)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::default()
+                        .title("Next Clip")
+                        .description("No more clips!")
+                        .asset_image(&none_left_image)
+                        .thumbnail(&icon_url)
+                        .colour(data::EMBED_FAIL)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&none_left_image],
+            )
+            .await,
         )
         .await?;
         return Ok(());
@@ -488,10 +536,14 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
         .timeout(Duration::new(10 * 60, 0))
         .stream();
 
-    let mod_id = ctx.data().mod_id;
+    let guild_id = ctx.guild_id().unwrap();
+    let mod_id = ctx.data().guild(guild_id).config().mod_role;
+    let saver = Arc::clone(&ctx.data().saver);
+    let rated_image = ctx.data().assets.get("clip.rated");
 
     let ctx = ctx.serenity_context().clone();
 
+    let owner_id = rand_clip.0;
     let user = Arc::clone(&rand_clip.1);
     let index = rand_clip.2;
 
@@ -501,7 +553,7 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
         while let Some(reaction) = reactions.next().await {
             let roles = reaction.member.clone().unwrap_or_default().roles;
             if reaction.data.custom_id == "vote-done" {
-                if !roles.contains(&mod_id) {
+                if !mod_id.is_some_and(|mod_id| roles.contains(&mod_id)) {
                     reaction
                         .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
                         .await
@@ -511,27 +563,32 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
                 let mut user = user.write().await;
                 let clip = user.submits[index].as_mut().unwrap();
                 clip.rating = Some(score.load(Ordering::Relaxed));
+                saver.mark_dirty(guild_id, owner_id);
 
                 msg.write()
                     .await
                     .edit(
                         &ctx,
-                        EditMessage::default()
-                            .embed(
-                                serenity::CreateEmbed::new()
-                                    .title("Next Clip")
-                                    .description(format!(
-                                        "Final Score: **{}**",
-                                        score.load(Ordering::Relaxed)
-                                    ))
-                                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354792621309972/tenor_5.gif?ex=65d81120&is=65c59c20&hm=b7661397c96231060492b909d1d7f2025bcfa91c166618611f612e95551be35a&")
-                                    .thumbnail(&icon_url)
-                                    .colour(data::EMBED_MOD)
-                                    .footer(serenity::CreateEmbedFooter::new(
-                                        "@~ powered by UwUntu & RustyBamboo",
-                                    )),
-                            )
-                            .components(Vec::new()),
+                        assets::attach(
+                            EditMessage::default()
+                                .embed(
+                                    serenity::CreateEmbed::new()
+                                        .title("Next Clip")
+                                        .description(format!(
+                                            "Final Score: **{}**",
+                                            score.load(Ordering::Relaxed)
+                                        ))
+                                        .asset_image(&rated_image)
+                                        .thumbnail(&icon_url)
+                                        .colour(data::EMBED_MOD)
+                                        .footer(serenity::CreateEmbedFooter::new(
+                                            "@~ powered by UwUntu & RustyBamboo",
+                                        )),
+                                )
+                                .components(Vec::new()),
+                            &[&rated_image],
+                        )
+                        .await,
                     )
                     .await
                     .unwrap();
@@ -576,27 +633,32 @@ pub async fn next_clip(ctx: Context<'_>) -> Result<(), Error> {
         let mut user = user.write().await;
         let clip = user.submits[index].as_mut().unwrap();
         clip.rating = Some(score.load(Ordering::Relaxed));
+        saver.mark_dirty(guild_id, owner_id);
 
         msg.write()
             .await
             .edit(
                 &ctx,
-                EditMessage::default()
-                    .embed(
-                        serenity::CreateEmbed::new()
-                            .title("Next Clip")
-                            .description(format!(
-                                "Final Score: **{}**",
-                                score.load(Ordering::Relaxed)
-                            ))
-                            .thumbnail(&icon_url)
-                            .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205354792621309972/tenor_5.gif?ex=65d81120&is=65c59c20&hm=b7661397c96231060492b909d1d7f2025bcfa91c166618611f612e95551be35a&")
-                            .colour(data::EMBED_MOD)
-                            .footer(serenity::CreateEmbedFooter::new(
-                                "@~ powered by UwUntu & RustyBamboo",
-                            )),
-                    )
-                    .components(Vec::new()),
+                assets::attach(
+                    EditMessage::default()
+                        .embed(
+                            serenity::CreateEmbed::new()
+                                .title("Next Clip")
+                                .description(format!(
+                                    "Final Score: **{}**",
+                                    score.load(Ordering::Relaxed)
+                                ))
+                                .thumbnail(&icon_url)
+                                .asset_image(&rated_image)
+                                .colour(data::EMBED_MOD)
+                                .footer(serenity::CreateEmbedFooter::new(
+                                    "@~ powered by UwUntu & RustyBamboo",
+                                )),
+                        )
+                        .components(Vec::new()),
+                    &[&rated_image],
+                )
+                .await,
             )
             .await
             .unwrap();
@@ -0,0 +1,116 @@
+//!---------------------------------------------------------------------!
+//! This file contains the server clock, days start at midnight in the  !
+//! timezone from config.toml, daylight saving time included            !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - today                                                     !
+//!     [x] - day_of                                                    !
+//!     [x] - start_of                                                  !
+//!     [x] - next_reset / next_reset_after                             !
+//!     [x] - until                                                     !
+//!---------------------------------------------------------------------!
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Local calendar day `instant` falls on
+pub fn day_of(tz: Tz, instant: DateTime<Utc>) -> NaiveDate {
+    instant.with_timezone(&tz).date_naive()
+}
+
+/// Local calendar day right now
+pub fn today(tz: Tz) -> NaiveDate {
+    day_of(tz, Utc::now())
+}
+
+/// When `day` starts, usually local midnight
+pub fn start_of(tz: Tz, day: NaiveDate) -> DateTime<Utc> {
+    // a daylight saving jump can skip midnight, then the day starts at the first hour it has
+    (0..24)
+        .find_map(|hour| {
+            tz.from_local_datetime(&day.and_hms_opt(hour, 0, 0)?)
+                .earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| day.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+/// When dailies reset next, the coming local midnight
+pub fn next_reset(tz: Tz) -> DateTime<Utc> {
+    next_reset_after(tz, Utc::now())
+}
+
+/// The first local midnight after `instant`
+pub fn next_reset_after(tz: Tz, instant: DateTime<Utc>) -> DateTime<Utc> {
+    start_of(tz, day_of(tz, instant).succ_opt().unwrap())
+}
+
+/// How long until `instant`, like "5h 12m"
+pub fn until(instant: DateTime<Utc>) -> String {
+    let left = (instant - Utc::now()).max(chrono::Duration::zero());
+    format!("{}h {}m", left.num_hours(), left.num_minutes() % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America, Asia};
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn spring_forward_day_is_short() {
+        let tz = America::New_York;
+        let start = start_of(tz, date(2024, 3, 10));
+        let next = start_of(tz, date(2024, 3, 11));
+        assert_eq!(start, utc("2024-03-10T05:00:00Z"));
+        assert_eq!(next, utc("2024-03-11T04:00:00Z"));
+        assert_eq!((next - start).num_hours(), 23);
+
+        assert_eq!(next_reset_after(tz, utc("2024-03-10T12:00:00Z")), next);
+    }
+
+    #[test]
+    fn fall_back_day_is_long() {
+        let tz = America::New_York;
+        let start = start_of(tz, date(2024, 11, 3));
+        let next = start_of(tz, date(2024, 11, 4));
+        assert_eq!(start, utc("2024-11-03T04:00:00Z"));
+        assert_eq!(next, utc("2024-11-04T05:00:00Z"));
+        assert_eq!((next - start).num_hours(), 25);
+
+        // 1:30 happens twice that night, both are still the 3rd
+        assert_eq!(day_of(tz, utc("2024-11-03T05:30:00Z")), date(2024, 11, 3));
+        assert_eq!(day_of(tz, utc("2024-11-03T06:30:00Z")), date(2024, 11, 3));
+        assert_eq!(next_reset_after(tz, utc("2024-11-03T06:30:00Z")), next);
+    }
+
+    #[test]
+    fn skipped_midnight_starts_at_the_first_hour() {
+        // clocks went from 00:00 straight to 01:00
+        let tz = America::Sao_Paulo;
+        assert_eq!(start_of(tz, date(2018, 11, 4)), utc("2018-11-04T03:00:00Z"));
+    }
+
+    #[test]
+    fn days_turn_over_at_local_midnight() {
+        let tz = Asia::Tokyo;
+        let midnight = utc("2024-06-01T15:00:00Z");
+        assert_eq!(start_of(tz, date(2024, 6, 2)), midnight);
+
+        let before = midnight - chrono::Duration::seconds(1);
+        assert_eq!(day_of(tz, before), date(2024, 6, 1));
+        assert_eq!(day_of(tz, midnight), date(2024, 6, 2));
+        assert_eq!(next_reset_after(tz, before), midnight);
+        assert_eq!(
+            next_reset_after(tz, midnight),
+            midnight + chrono::Duration::days(1)
+        );
+    }
+}
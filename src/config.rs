@@ -0,0 +1,768 @@
+//!---------------------------------------------------------------------!
+//! This file contains professorBot's settings file, the tunables that  !
+//! used to be hardcoded, loaded at startup and reloadable live         !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - load                                                      !
+//!     [x] - validate                                                  !
+//!     [x] - ticket_price                                              !
+//!     [x] - ticket_cost                                               !
+//!     [x] - ticket_refund                                             !
+//!     [x] - pay_tax                                                   !
+//!     [x] - loan_interest                                             !
+//!     [x] - next_bid                                                  !
+//!     [x] - streak_percent                                            !
+//!     [x] - reload                                                    !
+//!---------------------------------------------------------------------!
+//!
+//! Secrets (DISCORD_TOKEN, API_KEY) and where the save and rng audit live
+//! (STORAGE, SQLITE_PATH, RNG_AUDIT_PATH) stay in the environment, channels
+//! and roles are set per guild with /configure.
+
+use crate::Error;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use poise::serenity_prelude::RoleId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+// Highest tickets.base_price and tickets.price_step, keeps the curve far from overflowing
+const MAX_TICKET_PRICE: i32 = 1_000_000;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub voice: VoiceConfig,
+    pub tickets: TicketConfig,
+    pub clips: ClipConfig,
+    pub mods: ModConfig,
+    pub pay: PayConfig,
+    pub debt: DebtConfig,
+    pub auctions: AuctionConfig,
+    pub seasons: SeasonConfig,
+    pub streaks: StreakConfig,
+    pub modifiers: ModifierConfig,
+    pub luck: LuckConfig,
+    pub wishes: WishConfig,
+    pub gpt: GptConfig,
+    pub time: TimeConfig,
+}
+
+/// Rewards for hanging out in voice channels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoiceConfig {
+    /// Minutes in voice between rewards
+    pub reward_minutes: i64,
+    pub reward_creds: i32,
+    pub reward_xp: i32,
+}
+
+/// Raffle tickets cost `base_price + price_step * owned`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TicketConfig {
+    pub base_price: i32,
+    pub price_step: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipConfig {
+    /// Clips a user can have submitted at once
+    pub max_clips: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModConfig {
+    /// Most creds /give_creds and /take_creds move per user at once
+    pub max_cred_change: u32,
+}
+
+/// Limits on /pay, a limit of 0 turns it off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PayConfig {
+    /// Most creds a user can send in 24 hours
+    pub daily_send_limit: i32,
+    /// Most creds a user can receive in 24 hours
+    pub daily_receive_limit: i32,
+    /// Percent of every transfer that is taken out before it arrives
+    pub tax_percent: i32,
+}
+
+/// What happens when a loss is bigger than what a user has
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebtPolicy {
+    /// The balance goes below the floor, like it always used to
+    Allow,
+    /// The balance stops at the floor and the rest is forgiven
+    Clamp,
+    /// The balance stops at the floor and the rest becomes debt
+    Debt,
+}
+
+/// Balance floor and /loan terms, debt is paid back out of daily and voice rewards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DebtConfig {
+    pub policy: DebtPolicy,
+    /// Lowest balance a loss can leave unless the policy is allow
+    pub floor: i32,
+    /// Most a user can owe from loans at once, 0 turns /loan off
+    pub max_loan: i32,
+    /// Interest added to a loan when it is taken out
+    pub interest_percent: i32,
+    /// Percent of every daily and voice reward that goes to paying off debt
+    pub repay_percent: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuctionConfig {
+    /// Longest an auction can run
+    pub max_hours: u32,
+    /// How much a bid has to beat the top bid by, in percent of it
+    pub min_increment_percent: i32,
+    /// Auctions one user can have running at once
+    pub max_listings: usize,
+}
+
+/// What a season rollover can wipe, everything else carries over
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeasonField {
+    Creds,
+    /// Level and xp
+    Levels,
+    /// Rolls and daily count, the average luck starts over
+    Luck,
+    Tickets,
+}
+
+/// Economy seasons ended with /season_end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeasonConfig {
+    /// Fields reset when a moderator doesn't pick any
+    pub reset: Vec<SeasonField>,
+    /// Prestige badges for the richest members of a season, first place first
+    pub badges: Vec<String>,
+}
+
+/// Daily streaks, a daily due but not taken within the grace window breaks it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreakConfig {
+    /// Hours into a new day a missed daily can still be made up
+    pub grace_hours: i64,
+    /// Creds per freeze with /buy_freeze, a freeze covers one missed day
+    pub freeze_price: i32,
+    /// Freezes a user can hold at once
+    pub max_freezes: u32,
+    /// Daily payout bonuses, the highest milestone reached counts
+    pub milestones: Vec<StreakMilestone>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StreakMilestone {
+    pub days: u32,
+    /// Extra creds on a daily, in percent of the roll
+    pub percent: i32,
+}
+
+/// Bonuses on the /uwu and /claim_bonus rolls, `roll` is added to the d20 and
+/// `payout_percent` to the creds won
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModifierConfig {
+    /// +1 to the roll for every this many levels, 0 turns it off
+    pub level_step: i32,
+    /// Flat bonus on every /claim_bonus roll
+    pub bonus_proficiency: i32,
+    pub items: Vec<ItemModifier>,
+    pub roles: Vec<RoleModifier>,
+    pub events: Vec<EventModifier>,
+}
+
+/// Bonus for owning an item, bought in the shop or pulled with /wish
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemModifier {
+    pub item: String,
+    #[serde(default)]
+    pub roll: i32,
+    #[serde(default)]
+    pub payout_percent: i32,
+}
+
+/// Bonus for members with a role, like boosters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleModifier {
+    pub name: String,
+    pub role: RoleId,
+    #[serde(default)]
+    pub roll: i32,
+    #[serde(default)]
+    pub payout_percent: i32,
+}
+
+/// Bonus for everyone while an event runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventModifier {
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default)]
+    pub roll: i32,
+    #[serde(default)]
+    pub payout_percent: i32,
+}
+
+/// Luck stats from the /uwu and /claim_bonus d20s
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LuckConfig {
+    /// Rolls needed before someone is ranked on the fortune leaderboard
+    pub min_rolls: usize,
+}
+
+/// The /wish gacha, tiers are listed rarest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WishConfig {
+    /// Creds per wish with /buy_wishes
+    pub price: i32,
+    pub tiers: Vec<WishTier>,
+    pub banners: Vec<WishBanner>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WishTier {
+    pub name: String,
+    /// Percent chance per wish, the last tier gets whatever is left
+    #[serde(default)]
+    pub chance: f64,
+    /// A wish of this tier is guaranteed after this many wishes without one, 0 for never
+    #[serde(default)]
+    pub pity: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WishBanner {
+    pub name: String,
+    /// Items that can be pulled, by tier name
+    pub items: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GptConfig {
+    pub chat_model: String,
+    pub image_model: String,
+    /// Attempts at a chat completion before giving up
+    pub retries: u32,
+}
+
+/// Where the server's days start and end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeConfig {
+    /// IANA name like "America/New_York", dailies and birthdays go by its midnight
+    pub timezone: Tz,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        VoiceConfig {
+            reward_minutes: 30,
+            reward_creds: 50,
+            reward_xp: 30,
+        }
+    }
+}
+
+impl Default for TicketConfig {
+    fn default() -> Self {
+        TicketConfig {
+            base_price: 2000,
+            price_step: 300,
+        }
+    }
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        ClipConfig { max_clips: 5 }
+    }
+}
+
+impl Default for ModConfig {
+    fn default() -> Self {
+        ModConfig {
+            max_cred_change: 10000,
+        }
+    }
+}
+
+impl Default for PayConfig {
+    fn default() -> Self {
+        PayConfig {
+            daily_send_limit: 5000,
+            daily_receive_limit: 10000,
+            tax_percent: 0,
+        }
+    }
+}
+
+impl Default for DebtConfig {
+    fn default() -> Self {
+        DebtConfig {
+            policy: DebtPolicy::Debt,
+            floor: 0,
+            max_loan: 5000,
+            interest_percent: 10,
+            repay_percent: 50,
+        }
+    }
+}
+
+impl Default for SeasonConfig {
+    fn default() -> Self {
+        SeasonConfig {
+            reset: vec![SeasonField::Creds, SeasonField::Luck, SeasonField::Tickets],
+            badges: vec!["🥇".to_string(), "🥈".to_string(), "🥉".to_string()],
+        }
+    }
+}
+
+impl Default for StreakConfig {
+    fn default() -> Self {
+        let milestone = |days, percent| StreakMilestone { days, percent };
+        StreakConfig {
+            grace_hours: 6,
+            freeze_price: 300,
+            max_freezes: 3,
+            milestones: vec![
+                milestone(3, 10),
+                milestone(7, 25),
+                milestone(14, 50),
+                milestone(30, 100),
+            ],
+        }
+    }
+}
+
+impl Default for ModifierConfig {
+    fn default() -> Self {
+        ModifierConfig {
+            level_step: 8,
+            bonus_proficiency: 2,
+            items: Vec::new(),
+            roles: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl LuckConfig {
+    /// Whether `rolls` d20s are enough for the fortune leaderboard
+    pub fn is_ranked(&self, rolls: usize) -> bool {
+        rolls >= self.min_rolls
+    }
+}
+
+impl Default for LuckConfig {
+    fn default() -> Self {
+        LuckConfig { min_rolls: 10 }
+    }
+}
+
+impl Default for AuctionConfig {
+    fn default() -> Self {
+        AuctionConfig {
+            max_hours: 72,
+            min_increment_percent: 5,
+            max_listings: 3,
+        }
+    }
+}
+
+impl Default for WishConfig {
+    fn default() -> Self {
+        let tier = |name: &str, chance: f64, pity: u32| WishTier {
+            name: name.to_string(),
+            chance,
+            pity,
+        };
+        let items = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+
+        WishConfig {
+            price: 160,
+            tiers: vec![
+                tier("\u{2605}\u{2605}\u{2605}\u{2605}\u{2605}", 1.0, 60),
+                tier("\u{2605}\u{2605}\u{2605}\u{2605}", 10.0, 10),
+                tier("\u{2605}\u{2605}\u{2605}", 0.0, 0),
+            ],
+            banners: vec![WishBanner {
+                name: "Standard".to_string(),
+                items: BTreeMap::from([
+                    (
+                        "\u{2605}\u{2605}\u{2605}\u{2605}\u{2605}".to_string(),
+                        items(&["Golden Bamboo", "Professor's Monocle"]),
+                    ),
+                    (
+                        "\u{2605}\u{2605}\u{2605}\u{2605}".to_string(),
+                        items(&["Lucky D20", "Rusty Crab", "Cat Ears", "Clip of the Week"]),
+                    ),
+                    (
+                        "\u{2605}\u{2605}\u{2605}".to_string(),
+                        items(&[
+                            "Bamboo Shoot",
+                            "Sticky Note",
+                            "Loose Cred",
+                            "Paper Crown",
+                            "Old Meme",
+                        ]),
+                    ),
+                ]),
+            }],
+        }
+    }
+}
+
+impl Default for GptConfig {
+    fn default() -> Self {
+        GptConfig {
+            chat_model: "gpt-4-1106-preview".to_string(),
+            image_model: "dall-e-3".to_string(),
+            retries: 5,
+        }
+    }
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        TimeConfig {
+            timezone: chrono_tz::America::New_York,
+        }
+    }
+}
+
+impl Config {
+    /// Path of the settings file, CONFIG_PATH overrides the default
+    pub fn path() -> String {
+        env::var("CONFIG_PATH").unwrap_or_else(|_| CONFIG_FILE.to_string())
+    }
+
+    /// Read and validate the settings file, a missing file means every default
+    pub fn load(path: &str) -> Result<Config, Error> {
+        if !Path::new(path).exists() {
+            println!("No {} found, using default settings", path);
+            return Ok(Config::default());
+        }
+
+        let text = fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        config.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(config)
+    }
+
+    /// Catch settings that would break commands, all problems are reported at once
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if self.voice.reward_minutes < 1 {
+            problems.push("voice.reward_minutes must be at least 1".to_string());
+        }
+        if self.voice.reward_creds < 0 || self.voice.reward_xp < 0 {
+            problems.push("voice.reward_creds and voice.reward_xp can't be negative".to_string());
+        }
+        if !(1..=MAX_TICKET_PRICE).contains(&self.tickets.base_price) {
+            problems.push(format!(
+                "tickets.base_price must be between 1 and {}",
+                MAX_TICKET_PRICE
+            ));
+        }
+        if !(0..=MAX_TICKET_PRICE).contains(&self.tickets.price_step) {
+            problems.push(format!(
+                "tickets.price_step must be between 0 and {}",
+                MAX_TICKET_PRICE
+            ));
+        }
+        // the clip buttons in /my_clips are told apart by a single digit
+        if !(1..=10).contains(&self.clips.max_clips) {
+            problems.push("clips.max_clips must be between 1 and 10".to_string());
+        }
+        if self.mods.max_cred_change < 1 {
+            problems.push("mods.max_cred_change must be at least 1".to_string());
+        }
+        if self.pay.daily_send_limit < 0 || self.pay.daily_receive_limit < 0 {
+            problems.push(
+                "pay.daily_send_limit and pay.daily_receive_limit can't be negative".to_string(),
+            );
+        }
+        if !(0..100).contains(&self.pay.tax_percent) {
+            problems.push("pay.tax_percent must be between 0 and 99".to_string());
+        }
+        if self.debt.floor > 0 {
+            problems.push("debt.floor can't be above 0".to_string());
+        }
+        if self.debt.max_loan < 0 {
+            problems.push("debt.max_loan can't be negative".to_string());
+        }
+        if !(0..=100).contains(&self.debt.interest_percent) {
+            problems.push("debt.interest_percent must be between 0 and 100".to_string());
+        }
+        if !(1..=100).contains(&self.debt.repay_percent) {
+            problems.push("debt.repay_percent must be between 1 and 100".to_string());
+        }
+        if self.auctions.max_hours < 1 {
+            problems.push("auctions.max_hours must be at least 1".to_string());
+        }
+        if !(0..=100).contains(&self.auctions.min_increment_percent) {
+            problems.push("auctions.min_increment_percent must be between 0 and 100".to_string());
+        }
+        if self.seasons.badges.iter().any(|b| b.trim().is_empty()) {
+            problems.push("seasons.badges can't have empty badges".to_string());
+        }
+        if self.streaks.grace_hours < 0 {
+            problems.push("streaks.grace_hours can't be negative".to_string());
+        }
+        if self.streaks.freeze_price < 1 {
+            problems.push("streaks.freeze_price must be at least 1".to_string());
+        }
+        for milestone in &self.streaks.milestones {
+            if milestone.days < 1 || milestone.percent < 0 {
+                problems.push(format!(
+                    "streaks milestone for {} days needs at least 1 day and a percent of 0 or more",
+                    milestone.days
+                ));
+            }
+        }
+        if self.modifiers.level_step < 0 {
+            problems.push("modifiers.level_step can't be negative".to_string());
+        }
+        for event in &self.modifiers.events {
+            if event.end <= event.start {
+                problems.push(format!(
+                    "modifiers event {} ends before it starts",
+                    event.name
+                ));
+            }
+        }
+        if self.wishes.price < 1 {
+            problems.push("wishes.price must be at least 1".to_string());
+        }
+        self.validate_wishes(&mut problems);
+        if self.gpt.chat_model.is_empty() || self.gpt.image_model.is_empty() {
+            problems.push("gpt.chat_model and gpt.image_model can't be empty".to_string());
+        }
+        if self.gpt.retries < 1 {
+            problems.push("gpt.retries must be at least 1".to_string());
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems.join(", ")),
+        }
+    }
+
+    fn validate_wishes(&self, problems: &mut Vec<String>) {
+        let wishes = &self.wishes;
+        if wishes.tiers.is_empty() {
+            problems.push("wishes.tiers needs at least one tier".to_string());
+        }
+        if wishes.banners.is_empty() {
+            problems.push("wishes.banners needs at least one banner".to_string());
+        }
+
+        let mut chances = 0.0;
+        for (i, tier) in wishes.tiers.iter().enumerate() {
+            if wishes.tiers[..i].iter().any(|t| t.name == tier.name) {
+                problems.push(format!("wishes tier {} is listed twice", tier.name));
+            }
+            if tier.chance < 0.0 {
+                problems.push(format!("wishes tier {} has a negative chance", tier.name));
+            }
+            if i + 1 < wishes.tiers.len() {
+                chances += tier.chance;
+            }
+        }
+        if chances > 100.0 {
+            problems.push("wishes tier chances add up to more than 100".to_string());
+        }
+
+        for (i, banner) in wishes.banners.iter().enumerate() {
+            if wishes.banners[..i].iter().any(|b| b.name == banner.name) {
+                problems.push(format!("wishes banner {} is listed twice", banner.name));
+            }
+            // every tier needs something to pull, or a lucky wish would come up empty
+            for tier in &wishes.tiers {
+                if banner.items.get(&tier.name).is_none_or(|i| i.is_empty()) {
+                    problems.push(format!(
+                        "wishes banner {} has no {} items",
+                        banner.name, tier.name
+                    ));
+                }
+            }
+            for name in banner.items.keys() {
+                if !wishes.tiers.iter().any(|t| &t.name == name) {
+                    problems.push(format!(
+                        "wishes banner {} has items for unknown tier {}",
+                        banner.name, name
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Price of the next ticket for someone who owns `owned` tickets
+    pub fn ticket_price(&self, owned: i32) -> i32 {
+        self.ticket_cost(owned, 1)
+    }
+
+    /// What the next `count` tickets cost for someone who owns `owned`,
+    /// capped at i32::MAX so a long curve can't overflow
+    pub fn ticket_cost(&self, owned: i32, count: i32) -> i32 {
+        let (owned, count) = (owned.max(0) as i128, count.max(0) as i128);
+        let base = self.tickets.base_price as i128;
+        let step = self.tickets.price_step as i128;
+        // base + step * n summed for n in owned..owned + count
+        let cost = count * base + step * (count * owned + count * (count - 1) / 2);
+        cost.clamp(0, i32::MAX as i128) as i32
+    }
+
+    /// What the newest `count` of `owned` tickets cost, refunds walk the price curve back down
+    pub fn ticket_refund(&self, owned: i32, count: i32) -> i32 {
+        let count = count.clamp(0, owned.max(0));
+        self.ticket_cost(owned - count, count)
+    }
+
+    /// Interest owed on a loan of `amount`, rounded up so small loans aren't free
+    pub fn loan_interest(&self, amount: i32) -> i32 {
+        ((amount as i64 * self.debt.interest_percent as i64 + 99) / 100) as i32
+    }
+
+    /// Smallest bid that beats `top`, always at least one cred more
+    pub fn next_bid(&self, top: i32) -> i32 {
+        let step = top as i64 * self.auctions.min_increment_percent as i64 / 100;
+        (top as i64 + step.max(1)).min(i32::MAX as i64) as i32
+    }
+
+    /// Bonus percent of the highest milestone a `streak` day streak has reached
+    pub fn streak_percent(&self, streak: u32) -> i32 {
+        self.streaks
+            .milestones
+            .iter()
+            .filter(|m| m.days <= streak)
+            .map(|m| m.percent)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Creds taken out of a transfer of `amount`, rounded down
+    pub fn pay_tax(&self, amount: i32) -> i32 {
+        (amount as i64 * self.pay.tax_percent as i64 / 100) as i32
+    }
+}
+
+/// Shared handle to the live settings, swapped as a whole on reload so a
+/// command always sees one consistent version
+#[derive(Clone)]
+pub struct ConfigHandle(Arc<RwLock<Arc<Config>>>);
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        ConfigHandle(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.0.read().unwrap())
+    }
+
+    /// Read the settings file again, the old settings stay if it is invalid
+    pub fn reload(&self) -> Result<Arc<Config>, Error> {
+        let config = Arc::new(Config::load(&Config::path())?);
+        *self.0.write().unwrap() = Arc::clone(&config);
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(edit: impl FnOnce(&mut Config)) -> String {
+        let mut config = Config::default();
+        edit(&mut config);
+        config.validate().unwrap_err()
+    }
+
+    #[test]
+    fn example_matches_the_defaults() {
+        let text = fs::read_to_string("config.example.toml").unwrap();
+        let example: Config = toml::from_str(&text).unwrap();
+        assert_eq!(example, Config::default());
+        assert!(example.validate().is_ok());
+    }
+
+    #[test]
+    fn broken_settings_are_rejected() {
+        assert!(rejected(|c| c.tickets.base_price = 0).contains("tickets.base_price"));
+        assert!(rejected(|c| c.tickets.price_step = -1).contains("tickets.price_step"));
+        assert!(rejected(|c| c.tickets.price_step = MAX_TICKET_PRICE + 1)
+            .contains("tickets.price_step"));
+        assert!(rejected(|c| c.pay.tax_percent = 100).contains("pay.tax_percent"));
+        assert!(rejected(|c| c.debt.floor = 1).contains("debt.floor"));
+        assert!(rejected(|c| c.clips.max_clips = 11).contains("clips.max_clips"));
+
+        // every problem is reported, not just the first
+        let problems = rejected(|c| {
+            c.voice.reward_minutes = 0;
+            c.gpt.retries = 0;
+        });
+        assert!(problems.contains("voice.reward_minutes") && problems.contains("gpt.retries"));
+    }
+
+    #[test]
+    fn ticket_prices_follow_the_curve() {
+        let config = Config::default();
+        let (base, step) = (config.tickets.base_price, config.tickets.price_step);
+        assert_eq!(config.ticket_price(0), base);
+        assert_eq!(config.ticket_price(3), base + 3 * step);
+        assert_eq!(config.ticket_cost(2, 0), 0);
+        assert_eq!(
+            config.ticket_cost(2, 3),
+            (2..5).map(|n| config.ticket_price(n)).sum::<i32>()
+        );
+        // a refund walks back down the same prices
+        assert_eq!(config.ticket_refund(5, 3), config.ticket_cost(2, 3));
+        assert_eq!(config.ticket_refund(2, 10), config.ticket_cost(0, 2));
+    }
+
+    #[test]
+    fn ticket_prices_cap_instead_of_overflowing() {
+        let mut config = Config::default();
+        config.tickets.base_price = MAX_TICKET_PRICE;
+        config.tickets.price_step = MAX_TICKET_PRICE;
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config.ticket_price(i32::MAX), i32::MAX);
+        assert_eq!(config.ticket_cost(10_000, i32::MAX), i32::MAX);
+        assert_eq!(config.ticket_refund(i32::MAX, i32::MAX), i32::MAX);
+    }
+}
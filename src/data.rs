@@ -1,11 +1,21 @@
+use crate::assets::{self, AssetCatalog, ShowAsset};
+use crate::clock;
+use crate::config::{Config, ConfigHandle, DebtConfig, DebtPolicy, SeasonField, StreakConfig};
+use crate::migrate;
+use crate::modifiers::Game;
+use crate::rng::{Commitment, RngService};
+use crate::saver::SaveCoordinator;
 use crate::serenity;
+use crate::storage;
 use chrono::prelude::{DateTime, Utc};
+use chrono::TimeDelta;
+use chrono_tz::Tz;
 use dashmap::DashMap;
 use poise::serenity_prelude::RoleId;
 use serde::{Deserialize, Serialize};
 use serenity::Color;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::{env, fs};
 use tokio::sync::RwLock;
 
 // Constants
@@ -51,6 +61,147 @@ impl ClipData {
     }
 }
 
+/// Why a cred balance changed, variants carry whoever caused it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum CredReason {
+    Daily {
+        roll: i32,
+    },
+    Bonus {
+        roll: i32,
+    },
+    Voice,
+    Tickets {
+        count: i32,
+    },
+    Wishes {
+        count: i32,
+    },
+    StreakFreeze {
+        count: i32,
+    },
+    Shop {
+        item: String,
+    },
+    ShopRefund {
+        item: String,
+    },
+    Loan {
+        interest: i32,
+    },
+    LoanRepayment,
+    MovedToDebt,
+    AuctionBid {
+        id: u32,
+    },
+    AuctionRefund {
+        id: u32,
+    },
+    AuctionSale {
+        id: u32,
+    },
+    SeasonReset {
+        season: usize,
+    },
+    TicketRefund {
+        count: i32,
+        by: serenity::UserId,
+        note: String,
+    },
+    ModGive {
+        by: serenity::UserId,
+    },
+    ModTake {
+        by: serenity::UserId,
+    },
+    /// What the receiver got, the tax is its own PayTax entry
+    PaySent {
+        to: serenity::UserId,
+        tax: i32,
+    },
+    PayTax {
+        to: serenity::UserId,
+    },
+    PayReceived {
+        from: serenity::UserId,
+    },
+    Admin {
+        note: String,
+    },
+}
+
+impl CredReason {
+    /// Who caused the change, None when professorBot did it on its own
+    pub fn actor(&self) -> Option<serenity::UserId> {
+        match self {
+            CredReason::ModGive { by }
+            | CredReason::ModTake { by }
+            | CredReason::TicketRefund { by, .. } => Some(*by),
+            CredReason::PayReceived { from } => Some(*from),
+            _ => None,
+        }
+    }
+
+    /// Whether creds entered or left the economy, false when they only moved
+    /// between members or were bookkeeping
+    pub fn changes_supply(&self) -> bool {
+        !matches!(
+            self,
+            CredReason::PaySent { .. }
+                | CredReason::PayReceived { .. }
+                | CredReason::AuctionBid { .. }
+                | CredReason::AuctionRefund { .. }
+                | CredReason::AuctionSale { .. }
+                | CredReason::MovedToDebt
+                | CredReason::SeasonReset { .. }
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            CredReason::Daily { roll: 1 } => "Daily (critical failure)".to_string(),
+            CredReason::Daily { roll: 20 } => "Daily (critical success)".to_string(),
+            CredReason::Daily { roll } => format!("Daily (rolled {})", roll),
+            CredReason::Bonus { roll } => format!("Claim Bonus (rolled {})", roll),
+            CredReason::Voice => "Voice chat reward".to_string(),
+            CredReason::Tickets { count } => format!("Bought {} ticket(s)", count),
+            CredReason::Wishes { count } => format!("Bought {} wish(es)", count),
+            CredReason::StreakFreeze { count } => format!("Bought {} streak freeze(s)", count),
+            CredReason::Shop { item } => format!("Bought {} from the shop", item),
+            CredReason::ShopRefund { item } => format!("Refund for {}", item),
+            CredReason::Loan { interest } => format!("Loan ({} interest owed)", interest),
+            CredReason::LoanRepayment => "Debt repayment".to_string(),
+            CredReason::MovedToDebt => "Negative balance moved to debt".to_string(),
+            CredReason::AuctionBid { id } => format!("Bid on auction #{}", id),
+            CredReason::AuctionRefund { id } => format!("Outbid on auction #{}", id),
+            CredReason::AuctionSale { id } => format!("Sold in auction #{}", id),
+            CredReason::SeasonReset { season } => format!("Season {} ended", season),
+            CredReason::TicketRefund { count, by, note } => {
+                format!("Refunded {} ticket(s) by <@{}> ({})", count, by, note)
+            }
+            CredReason::ModGive { by } => format!("Given by <@{}>", by),
+            CredReason::ModTake { by } => format!("Taken by <@{}>", by),
+            CredReason::PaySent { to, tax: 0 } => format!("Paid <@{}>", to),
+            CredReason::PaySent { to, tax } => format!("Paid <@{}> ({} tax)", to, tax),
+            CredReason::PayTax { to } => format!("Tax on paying <@{}>", to),
+            CredReason::PayReceived { from } => format!("Paid by <@{}>", from),
+            CredReason::Admin { note } => format!("Admin adjustment ({})", note),
+        }
+    }
+}
+
+/// One change to a user's cred balance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub amount: i32,
+    pub reason: CredReason,
+    pub actor: Option<serenity::UserId>,
+    pub date: DateTime<Utc>,
+    /// Balance right after this change
+    pub balance: i32,
+}
+
 // User profile
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct UserData {
@@ -58,13 +209,174 @@ pub struct UserData {
     xp: i32,
 
     creds: i32,
-    rolls: i32,
     daily_count: i32,
     bonus_count: i32,
     last_daily: DateTime<Utc>,
 
     pub submits: Vec<Option<ClipData>>,
     tickets: i32,
+
+    #[serde(default)]
+    ledger: Vec<LedgerEntry>,
+
+    #[serde(default)]
+    wishes: i32,
+    /// Wishes since the last pull of each tier, by banner then tier
+    #[serde(default)]
+    pity: BTreeMap<String, BTreeMap<String, u32>>,
+    /// Every item pulled with /wish and how many of it
+    #[serde(default)]
+    collection: BTreeMap<String, u32>,
+
+    /// Items bought in the shop and how many of each
+    #[serde(default)]
+    inventory: BTreeMap<String, u32>,
+
+    /// Creds owed from loans and losses that went past the floor
+    #[serde(default)]
+    debt: i32,
+
+    /// How every past economy season ended, oldest first
+    #[serde(default)]
+    seasons: Vec<SeasonRecord>,
+
+    /// Dailies in a row, see next_streak for what breaks it
+    #[serde(default)]
+    streak: u32,
+    #[serde(default)]
+    best_streak: u32,
+    /// Each one saves the streak from one missed day
+    #[serde(default)]
+    freezes: u32,
+
+    /// Every /uwu and /claim_bonus d20, oldest first
+    #[serde(default)]
+    roll_history: Vec<RollRecord>,
+    /// Daily d20s from before the history was kept, only their sum is known
+    #[serde(default)]
+    legacy_rolls: Option<LegacyRolls>,
+}
+
+/// Sum and count of d20s rolled before every roll was recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LegacyRolls {
+    pub sum: i64,
+    pub count: usize,
+}
+
+/// One d20 from /uwu or /claim_bonus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollRecord {
+    pub game: Game,
+    /// The die itself, what luck is measured on
+    pub natural: i32,
+    /// After modifiers
+    pub total: i32,
+    pub date: DateTime<Utc>,
+}
+
+/// Luck over a run of rolls, from the natural d20s
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LuckStats {
+    pub rolls: usize,
+    pub average: f64,
+    pub nat20s: usize,
+    pub nat1s: usize,
+    /// How often each face came up, a 1 first
+    pub faces: [usize; 20],
+    /// Most rolls in a row above 10
+    pub hot_streak: usize,
+    /// Most rolls in a row of 10 or less
+    pub cold_streak: usize,
+    /// Rolls only counted in the average, from before faces were kept
+    pub legacy: usize,
+}
+
+impl LuckStats {
+    pub fn of<'a>(rolls: impl IntoIterator<Item = &'a RollRecord>) -> LuckStats {
+        let mut stats = LuckStats::default();
+        let mut sum = 0i64;
+        let (mut hot, mut cold) = (0, 0);
+
+        for roll in rolls {
+            let natural = roll.natural.clamp(1, 20);
+            stats.rolls += 1;
+            sum += natural as i64;
+            stats.faces[(natural - 1) as usize] += 1;
+            match natural {
+                20 => stats.nat20s += 1,
+                1 => stats.nat1s += 1,
+                _ => (),
+            }
+
+            if natural > 10 {
+                hot += 1;
+                cold = 0;
+            } else {
+                cold += 1;
+                hot = 0;
+            }
+            stats.hot_streak = stats.hot_streak.max(hot);
+            stats.cold_streak = stats.cold_streak.max(cold);
+        }
+
+        if stats.rolls > 0 {
+            stats.average = sum as f64 / stats.rolls as f64;
+        }
+        stats
+    }
+
+    /// Fold rolls known only by their sum into the count and average
+    pub fn with_legacy(mut self, legacy: Option<&LegacyRolls>) -> LuckStats {
+        let Some(legacy) = legacy.filter(|l| l.count > 0) else {
+            return self;
+        };
+
+        let sum = self.average * self.rolls as f64 + legacy.sum as f64;
+        self.rolls += legacy.count;
+        self.legacy = legacy.count;
+        self.average = sum / self.rolls as f64;
+        self
+    }
+
+    /// How the average compares to a fair d20's 10.5
+    pub fn label(&self) -> String {
+        if self.rolls == 0 {
+            return "N/A".to_string();
+        }
+
+        match self.average {
+            a if a < 8.0 => "Horrible",
+            a if a < 9.5 => "Below Average",
+            a if a < 11.5 => "Average",
+            a if a < 13.0 => "Above Average",
+            _ => "Blessed",
+        }
+        .to_string()
+    }
+}
+
+/// One user's standing when a season ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonRecord {
+    pub season: usize,
+    pub ended: DateTime<Utc>,
+    pub creds: i32,
+    pub level: i32,
+    pub xp: i32,
+    pub luck: String,
+    /// Average natural d20 of the season
+    pub luck_score: f64,
+    /// d20s the luck score is from
+    #[serde(default)]
+    pub rolls: usize,
+    pub daily_count: i32,
+    #[serde(default)]
+    pub streak: u32,
+    pub tickets: i32,
+    /// Place by creds among everyone in the guild
+    pub rank: usize,
+    pub badge: Option<String>,
 }
 
 impl UserData {
@@ -72,6 +384,17 @@ impl UserData {
         self.level += 1;
     }
 
+    /// Jump straight to a level, xp is capped so it stays below the next level
+    pub fn set_level(&mut self, level: i32) -> bool {
+        if level < 0 {
+            return false;
+        }
+
+        self.level = level;
+        self.xp = self.xp.min(self.get_next_level() - 1);
+        true
+    }
+
     pub fn update_xp(&mut self, xp: i32) -> bool {
         if xp < 0 {
             return false;
@@ -94,18 +417,84 @@ impl UserData {
         self.daily_count += 1;
     }
 
-    pub fn add_rolls(&mut self, roll: i32) -> bool {
-        if roll < 1 {
+    /// Keep a d20 for the luck stats, `natural` is the die and `total` the roll after modifiers
+    pub fn record_roll(&mut self, game: Game, natural: i32, total: i32) -> bool {
+        if !(1..=20).contains(&natural) {
             return false;
         }
 
-        self.rolls += roll;
+        self.roll_history.push(RollRecord {
+            game,
+            natural,
+            total,
+            date: Utc::now(),
+        });
         true
     }
 
-    pub fn check_daily(&self) -> bool {
-        let diff = Utc::now() - self.last_daily;
-        diff.num_hours() >= 24
+    pub fn get_rolls(&self) -> &[RollRecord] {
+        &self.roll_history
+    }
+
+    pub fn get_legacy_rolls(&self) -> Option<&LegacyRolls> {
+        self.legacy_rolls.as_ref()
+    }
+
+    pub fn get_luck_stats(&self) -> LuckStats {
+        LuckStats::of(&self.roll_history).with_legacy(self.legacy_rolls.as_ref())
+    }
+
+    /// Whether the last daily was before today's midnight in the server timezone
+    pub fn check_daily(&self, tz: Tz) -> bool {
+        clock::day_of(tz, self.last_daily) < clock::today(tz)
+    }
+
+    /// Streak a daily taken now would reach and the freezes it uses up. Every
+    /// missed day costs a freeze, early in the grace window the last one is
+    /// forgiven, without enough freezes it starts over
+    pub fn next_streak(&self, tz: Tz, streaks: &StreakConfig) -> (u32, u32) {
+        if self.streak == 0 {
+            return (1, 0);
+        }
+
+        let today = clock::today(tz);
+        let mut missed = ((today - clock::day_of(tz, self.last_daily)).num_days() - 1).max(0);
+        if missed > 0
+            && Utc::now() - clock::start_of(tz, today) < TimeDelta::hours(streaks.grace_hours)
+        {
+            missed -= 1;
+        }
+
+        match missed as u32 <= self.freezes {
+            true => (self.streak + 1, missed as u32),
+            false => (1, 0),
+        }
+    }
+
+    pub fn set_streak(&mut self, streak: u32, freezes_used: u32) {
+        self.streak = streak;
+        self.best_streak = self.best_streak.max(streak);
+        self.freezes = self.freezes.saturating_sub(freezes_used);
+    }
+
+    /// Current streak, 0 once it can't be continued anymore
+    pub fn get_streak(&self, tz: Tz, streaks: &StreakConfig) -> u32 {
+        match self.next_streak(tz, streaks) {
+            (1, _) => 0,
+            _ => self.streak,
+        }
+    }
+
+    pub fn get_best_streak(&self) -> u32 {
+        self.best_streak
+    }
+
+    pub fn add_freezes(&mut self, freezes: u32) {
+        self.freezes += freezes;
+    }
+
+    pub fn get_freezes(&self) -> u32 {
+        self.freezes
     }
 
     pub fn add_bonus(&mut self) {
@@ -124,23 +513,179 @@ impl UserData {
         matches!(self.bonus_count, 3)
     }
 
-    pub fn add_creds(&mut self, creds: i32) -> bool {
+    pub fn add_creds(&mut self, creds: i32, reason: CredReason) -> bool {
         if creds < 0 {
             return false;
         }
 
         self.creds += creds;
+        self.record(creds, reason);
         true
     }
 
-    pub fn sub_creds(&mut self, creds: i32) -> bool {
+    pub fn sub_creds(&mut self, creds: i32, reason: CredReason) -> bool {
         if creds < 0 {
             return false;
         }
         self.creds -= creds;
+        self.record(-creds, reason);
+        true
+    }
+
+    /// Take creds for a loss the user didn't choose, the debt policy decides what
+    /// happens past the floor. Returns how much of it became debt
+    pub fn charge(&mut self, creds: i32, reason: CredReason, debt: &DebtConfig) -> i32 {
+        if creds < 0 {
+            return 0;
+        }
+
+        let payable = match debt.policy {
+            DebtPolicy::Allow => creds,
+            DebtPolicy::Clamp | DebtPolicy::Debt => creds.min((self.creds - debt.floor).max(0)),
+        };
+        self.sub_creds(payable, reason);
+
+        let owed = match debt.policy {
+            DebtPolicy::Debt => creds - payable,
+            _ => 0,
+        };
+        self.debt += owed;
+        owed
+    }
+
+    /// Give a reward, part of it goes straight to paying off debt. Returns how much did
+    pub fn earn(&mut self, creds: i32, reason: CredReason, debt: &DebtConfig) -> i32 {
+        if !self.add_creds(creds, reason) {
+            return 0;
+        }
+
+        let withheld = ((creds as i64 * debt.repay_percent as i64 / 100) as i32).min(self.debt);
+        self.repay(withheld);
+        withheld
+    }
+
+    /// Borrow creds, the interest is owed on top
+    pub fn take_loan(&mut self, creds: i32, interest: i32) -> bool {
+        if creds < 1 || interest < 0 {
+            return false;
+        }
+
+        self.add_creds(creds, CredReason::Loan { interest });
+        self.debt += creds + interest;
+        true
+    }
+
+    /// Pay off up to `creds` of debt from the balance
+    pub fn repay(&mut self, creds: i32) -> bool {
+        if creds < 1 || creds > self.debt || creds > self.creds {
+            return false;
+        }
+
+        self.sub_creds(creds, CredReason::LoanRepayment);
+        self.debt -= creds;
         true
     }
 
+    pub fn get_debt(&self) -> i32 {
+        self.debt
+    }
+
+    /// Archive how this season went and wipe the fields the new one starts without
+    pub fn end_season(
+        &mut self,
+        season: usize,
+        rank: usize,
+        badge: Option<String>,
+        reset: &[SeasonField],
+    ) {
+        self.seasons.push(SeasonRecord {
+            season,
+            ended: Utc::now(),
+            creds: self.creds,
+            level: self.level,
+            xp: self.xp,
+            luck: self.get_luck(),
+            luck_score: self.get_luck_score(),
+            rolls: self.get_luck_stats().rolls,
+            daily_count: self.daily_count,
+            streak: self.streak,
+            tickets: self.tickets,
+            rank,
+            badge,
+        });
+
+        for field in reset {
+            match field {
+                SeasonField::Creds => {
+                    let creds = self.creds;
+                    self.creds = 0;
+                    self.record(-creds, CredReason::SeasonReset { season });
+                }
+                SeasonField::Levels => {
+                    self.level = 0;
+                    self.xp = 0;
+                }
+                SeasonField::Luck => {
+                    self.roll_history.clear();
+                    self.legacy_rolls = None;
+                    self.daily_count = 0;
+                }
+                SeasonField::Tickets => self.tickets = 0,
+            }
+        }
+    }
+
+    pub fn get_seasons(&self) -> &[SeasonRecord] {
+        &self.seasons
+    }
+
+    /// Prestige badges won in past seasons, with the season each is from
+    pub fn get_badges(&self) -> Vec<(usize, &str)> {
+        self.seasons
+            .iter()
+            .filter_map(|s| s.badge.as_deref().map(|b| (s.season, b)))
+            .collect()
+    }
+
+    fn record(&mut self, amount: i32, reason: CredReason) {
+        self.ledger.push(LedgerEntry {
+            amount,
+            actor: reason.actor(),
+            reason,
+            date: Utc::now(),
+            balance: self.creds,
+        });
+    }
+
+    /// Every cred change, oldest first
+    pub fn get_ledger(&self) -> &[LedgerEntry] {
+        &self.ledger
+    }
+
+    /// Creds sent with /pay since `since`, tax included
+    pub fn paid_since(&self, since: DateTime<Utc>) -> i32 {
+        self.ledger
+            .iter()
+            .filter(|e| {
+                e.date >= since
+                    && matches!(
+                        e.reason,
+                        CredReason::PaySent { .. } | CredReason::PayTax { .. }
+                    )
+            })
+            .map(|e| -e.amount)
+            .sum()
+    }
+
+    /// Creds received with /pay since `since`
+    pub fn received_since(&self, since: DateTime<Utc>) -> i32 {
+        self.ledger
+            .iter()
+            .filter(|e| e.date >= since && matches!(e.reason, CredReason::PayReceived { .. }))
+            .map(|e| e.amount)
+            .sum()
+    }
+
     pub fn add_tickets(&mut self, tickets: i32) -> bool {
         if tickets < 1 {
             return false;
@@ -150,6 +695,151 @@ impl UserData {
         true
     }
 
+    /// What the newest `count` tickets were bought for, read from the ledger
+    /// newest purchase first. Tickets an earlier refund already took back are
+    /// skipped, tickets with no purchase left fall back to the price curve
+    pub fn ticket_refund(&self, count: i32, config: &Config) -> i32 {
+        let count = count.clamp(0, self.tickets);
+        let mut left = count;
+        let mut refunded = 0;
+        let mut skip = 0;
+
+        for entry in self.ledger.iter().rev() {
+            if left == 0 {
+                break;
+            }
+            let bought = match entry.reason {
+                CredReason::TicketRefund { count, .. } => {
+                    skip += count;
+                    continue;
+                }
+                CredReason::Tickets { count } if count > 0 => count,
+                _ => continue,
+            };
+
+            // a batch is split evenly, taking all of it returns exactly its price
+            let mut paid = -entry.amount as i64;
+            let mut tickets = bought;
+            let skipped = skip.min(tickets);
+            paid -= paid * skipped as i64 / tickets as i64;
+            tickets -= skipped;
+            skip -= skipped;
+            if tickets == 0 {
+                continue;
+            }
+
+            let take = left.min(tickets);
+            refunded += (paid * take as i64 / tickets as i64) as i32;
+            left -= take;
+        }
+
+        refunded + config.ticket_refund(self.tickets - (count - left), left)
+    }
+
+    pub fn remove_tickets(&mut self, tickets: i32) -> bool {
+        if tickets < 1 || tickets > self.tickets {
+            return false;
+        }
+
+        self.tickets -= tickets;
+        true
+    }
+
+    pub fn add_wishes(&mut self, wishes: i32) -> bool {
+        if wishes < 1 {
+            return false;
+        }
+
+        self.wishes += wishes;
+        true
+    }
+
+    pub fn sub_wishes(&mut self, wishes: i32) -> bool {
+        if wishes < 1 || wishes > self.wishes {
+            return false;
+        }
+
+        self.wishes -= wishes;
+        true
+    }
+
+    pub fn get_wishes(&self) -> i32 {
+        self.wishes
+    }
+
+    /// Wishes on `banner` since the last `tier` item
+    pub fn get_pity(&self, banner: &str, tier: &str) -> u32 {
+        self.pity
+            .get(banner)
+            .and_then(|b| b.get(tier))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn set_pity(&mut self, banner: &str, tier: &str, count: u32) {
+        self.pity
+            .entry(banner.to_string())
+            .or_default()
+            .insert(tier.to_string(), count);
+    }
+
+    pub fn add_to_collection(&mut self, item: &str) {
+        *self.collection.entry(item.to_string()).or_default() += 1;
+    }
+
+    pub fn get_collection(&self) -> &BTreeMap<String, u32> {
+        &self.collection
+    }
+
+    pub fn add_item(&mut self, item: &str, count: u32) -> bool {
+        if count < 1 {
+            return false;
+        }
+
+        *self.inventory.entry(item.to_string()).or_default() += count;
+        true
+    }
+
+    pub fn remove_item(&mut self, item: &str, count: u32) -> bool {
+        let owned = match self.inventory.get_mut(item) {
+            Some(owned) => owned,
+            None => return false,
+        };
+        if count < 1 || count > *owned {
+            return false;
+        }
+
+        *owned -= count;
+        if *owned == 0 {
+            self.inventory.remove(item);
+        }
+        true
+    }
+
+    /// Whether the item is in the inventory or the wish collection
+    pub fn owns(&self, item: &str) -> bool {
+        self.inventory.get(item).is_some_and(|n| *n > 0) || self.collection.contains_key(item)
+    }
+
+    pub fn get_inventory(&self) -> &BTreeMap<String, u32> {
+        &self.inventory
+    }
+
+    /// How many of `item` were ever bought in the shop, even if they are gone now
+    pub fn get_purchases(&self, item: &str) -> u32 {
+        let count = |refund: bool| {
+            self.ledger
+                .iter()
+                .filter(|e| match &e.reason {
+                    CredReason::Shop { item: i } => !refund && i == item,
+                    CredReason::ShopRefund { item: i } => refund && i == item,
+                    _ => false,
+                })
+                .count() as u32
+        };
+        count(false).saturating_sub(count(true))
+    }
+
     pub fn get_creds(&self) -> i32 {
         self.creds
     }
@@ -159,35 +849,26 @@ impl UserData {
     }
 
     pub fn get_luck(&self) -> String {
-        if self.daily_count == 0 {
-            return "N/A".to_string();
-        }
-
-        let average = self.get_luck_score();
-        let luck: String;
-        if average < 6 {
-            luck = "Horrible".to_string();
-        } else if (6..9).contains(&average) {
-            luck = "Below Average".to_string();
-        } else if (9..12).contains(&average) {
-            luck = "Average".to_string();
-        } else if (12..15).contains(&average) {
-            luck = "Above Average".to_string();
-        } else {
-            luck = "Blessed".to_string();
-        }
-
-        luck
+        self.get_luck_stats().label()
     }
 
-    pub fn get_luck_score(&self) -> i32 {
-        self.rolls / (self.daily_count + 1)
+    /// Average natural d20, 0 before the first roll
+    pub fn get_luck_score(&self) -> f64 {
+        self.get_luck_stats().average
     }
 
     pub fn get_bonus(&self) -> i32 {
         self.bonus_count
     }
 
+    pub fn get_daily_count(&self) -> i32 {
+        self.daily_count
+    }
+
+    pub fn get_last_daily(&self) -> DateTime<Utc> {
+        self.last_daily
+    }
+
     pub fn get_level(&self) -> i32 {
         self.level
     }
@@ -200,8 +881,8 @@ impl UserData {
         500 + self.get_level() * 80
     }
 
-    pub fn add_submit(&mut self, new_submit: ClipData) -> bool {
-        for i in 0..5 {
+    pub fn add_submit(&mut self, new_submit: ClipData, max_clips: usize) -> bool {
+        for i in 0..max_clips {
             let s = self.submits.get_mut(i);
             if let Some(s) = s {
                 if s.is_none() {
@@ -217,6 +898,10 @@ impl UserData {
     }
 
     pub fn remove_submit(&mut self, submit_index: usize) -> bool {
+        if submit_index >= self.submits.len() {
+            return false;
+        }
+
         let res = self.submits.remove(submit_index);
         res.is_some()
     }
@@ -257,6 +942,12 @@ pub struct VoiceUser {
     pub deaf: Option<DateTime<Utc>>,
 }
 
+impl Default for VoiceUser {
+    fn default() -> Self {
+        VoiceUser::new()
+    }
+}
+
 impl VoiceUser {
     pub fn new() -> VoiceUser {
         VoiceUser {
@@ -282,127 +973,531 @@ impl VoiceUser {
     }
 }
 
-#[derive(Default, Serialize, Deserialize)]
-pub struct SaveData {
+/// Channels and roles professorBot uses in one guild, set with /configure
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuildConfig {
+    pub general: Option<serenity::ChannelId>,
+    pub bot_cmd: Option<serenity::ChannelId>,
+    pub submit: Option<serenity::ChannelId>,
+    pub mod_chat: Option<serenity::ChannelId>,
+    pub mod_role: Option<RoleId>,
+}
+
+impl GuildConfig {
+    /// Channels professorBot chats in when mentioned
+    pub fn chat_channels(&self) -> Vec<serenity::ChannelId> {
+        [self.general, self.bot_cmd, self.submit]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// A raffle that is taking tickets, opened with /raffle_open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRaffle {
+    pub prize: String,
+    pub opened: DateTime<Utc>,
+    pub by: serenity::UserId,
+    /// Set by /raffle_close, no tickets are sold after it
+    #[serde(default)]
+    pub closed: Option<ClosedRaffle>,
+}
+
+/// Entries frozen by /raffle_close, the draw only uses these
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedRaffle {
+    pub closed: DateTime<Utc>,
+    /// Every member with tickets, sorted by user id
+    pub entries: Vec<(serenity::UserId, i32)>,
+    /// Hex sha256 of the entries, mixed into the draw
+    pub entries_hash: String,
+    /// Hex sha256 of the seed the draw will use, the seed itself is only revealed by the draw
+    pub commitment: String,
+}
+
+/// How a finished raffle went, kept so past seasons can be looked up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaffleResult {
+    pub season: usize,
+    pub prize: String,
+    /// Winners in the order they were drawn, with the tickets they held
+    pub winners: Vec<(serenity::UserId, i32)>,
+    pub entrants: usize,
+    pub tickets: i32,
+    pub drawn: DateTime<Utc>,
+    pub by: serenity::UserId,
+    /// Revealed seed, None for raffles opened before draws were committed
+    #[serde(default)]
+    pub commitment: Option<Commitment>,
+    /// Every entry in the order they were drawn from, enough to replay the draw
+    #[serde(default)]
+    pub entries: Vec<(serenity::UserId, i32)>,
+}
+
+/// Battle pass raffle of one guild, every draw ends a season
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct RaffleState {
+    pub open: Option<OpenRaffle>,
+    pub history: Vec<RaffleResult>,
+}
+
+impl RaffleState {
+    /// Season the tickets being sold right now count towards
+    pub fn season(&self) -> usize {
+        self.history.len() + 1
+    }
+
+    /// Whether /raffle_close stopped ticket sales for the open raffle
+    pub fn is_closed(&self) -> bool {
+        self.open.as_ref().is_some_and(|open| open.closed.is_some())
+    }
+}
+
+/// How a finished economy season went
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonSummary {
+    pub season: usize,
+    pub ended: DateTime<Utc>,
+    pub by: serenity::UserId,
+    pub reset: Vec<SeasonField>,
+    /// Richest members in order, with their creds
+    pub top: Vec<(serenity::UserId, i32)>,
+    pub members: usize,
+}
+
+/// Economy seasons of one guild, ended by moderators with /season_end
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonState {
+    pub history: Vec<SeasonSummary>,
+}
+
+impl SeasonState {
+    /// Season being played right now
+    pub fn season(&self) -> usize {
+        self.history.len() + 1
+    }
+}
+
+/// Something moderators put up for sale with /shop_add
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShopItem {
+    pub name: String,
+    pub category: String,
+    pub description: String,
+    pub price: i32,
+    /// How many are left, None when there is no end to them
+    pub stock: Option<u32>,
+    /// How many one user can ever buy, None for no limit
+    pub per_user: Option<u32>,
+    /// Buying this rents a role instead of adding to the inventory
+    #[serde(default)]
+    pub rental: Option<RentalTerms>,
+}
+
+/// The role a shop item grants and for how long
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RentalTerms {
+    pub role: RoleId,
+    pub hours: u32,
+}
+
+/// A role someone is renting, taken away again once it expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleRental {
+    pub user: serenity::UserId,
+    pub role: RoleId,
+    pub item: String,
+    pub expires: DateTime<Utc>,
+}
+
+/// What is up for auction, it is held by the auction house until it ends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum AuctionLot {
+    Item { name: String, count: u32 },
+    Tickets { count: i32 },
+}
+
+impl AuctionLot {
+    pub fn describe(&self) -> String {
+        match self {
+            AuctionLot::Item { name, count: 1 } => name.clone(),
+            AuctionLot::Item { name, count } => format!("{} x{}", name, count),
+            AuctionLot::Tickets { count } => format!("{} raffle ticket(s)", count),
+        }
+    }
+}
+
+/// The top bid of an auction, its creds are held until someone outbids it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bid {
+    pub user: serenity::UserId,
+    pub amount: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Auction {
+    pub id: u32,
+    pub seller: serenity::UserId,
+    pub lot: AuctionLot,
+    /// Lowest bid that is accepted
+    pub reserve: i32,
+    pub ends: DateTime<Utc>,
+    pub bid: Option<Bid>,
+    /// Where the auction was announced, results go there too
+    pub channel: serenity::ChannelId,
+    pub message: Option<serenity::MessageId>,
+}
+
+/// Auctions of one guild that haven't been settled yet
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionHouse {
+    pub next_id: u32,
+    pub open: Vec<Auction>,
+}
+
+/// Everything saved for one guild
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct GuildSave {
+    #[serde(default)]
+    pub config: GuildConfig,
+    #[serde(default)]
+    pub raffle: RaffleState,
+    #[serde(default)]
+    pub shop: Vec<ShopItem>,
+    #[serde(default)]
+    pub rentals: Vec<RoleRental>,
+    #[serde(default)]
+    pub auctions: AuctionHouse,
+    #[serde(default)]
+    pub seasons: SeasonState,
     pub users: DashMap<serenity::UserId, UserData>,
 }
 
-impl std::ops::Deref for SaveData {
-    type Target = DashMap<serenity::UserId, UserData>;
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    /// Format version, see migrate.rs before changing the saved structures
+    pub version: u32,
+    pub guilds: DashMap<serenity::GuildId, GuildSave>,
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.users
+impl SaveData {
+    pub fn new(guilds: DashMap<serenity::GuildId, GuildSave>) -> Self {
+        SaveData {
+            version: migrate::CURRENT_VERSION,
+            guilds,
+        }
+    }
+
+    pub fn user_count(&self) -> usize {
+        self.guilds.iter().map(|g| g.users.len()).sum()
     }
 }
 
-/// User data, which is stored and accessible in all command invocations
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData::new(DashMap::new())
+    }
+}
+
+pub type Users = DashMap<serenity::UserId, Arc<RwLock<UserData>>>;
+
+/// Live data of one guild, each guild has its own wallets and leaderboards
 #[derive(Default)]
+pub struct GuildData {
+    pub users: Users,
+    config: std::sync::RwLock<GuildConfig>,
+    raffle: std::sync::RwLock<RaffleState>,
+    shop: std::sync::RwLock<Vec<ShopItem>>,
+    rentals: std::sync::RwLock<Vec<RoleRental>>,
+    auctions: std::sync::RwLock<AuctionHouse>,
+    seasons: std::sync::RwLock<SeasonState>,
+}
+
+impl GuildData {
+    pub fn config(&self) -> GuildConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: GuildConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn raffle(&self) -> RaffleState {
+        self.raffle.read().unwrap().clone()
+    }
+
+    pub fn set_raffle(&self, raffle: RaffleState) {
+        *self.raffle.write().unwrap() = raffle;
+    }
+
+    pub fn shop(&self) -> Vec<ShopItem> {
+        self.shop.read().unwrap().clone()
+    }
+
+    /// Change the shop in place, so checking stock and taking one can't race
+    pub fn update_shop<R>(&self, f: impl FnOnce(&mut Vec<ShopItem>) -> R) -> R {
+        f(&mut self.shop.write().unwrap())
+    }
+
+    pub fn rentals(&self) -> Vec<RoleRental> {
+        self.rentals.read().unwrap().clone()
+    }
+
+    pub fn update_rentals<R>(&self, f: impl FnOnce(&mut Vec<RoleRental>) -> R) -> R {
+        f(&mut self.rentals.write().unwrap())
+    }
+
+    pub fn auctions(&self) -> AuctionHouse {
+        self.auctions.read().unwrap().clone()
+    }
+
+    pub fn update_auctions<R>(&self, f: impl FnOnce(&mut AuctionHouse) -> R) -> R {
+        f(&mut self.auctions.write().unwrap())
+    }
+
+    pub fn seasons(&self) -> SeasonState {
+        self.seasons.read().unwrap().clone()
+    }
+
+    pub fn set_seasons(&self, seasons: SeasonState) {
+        *self.seasons.write().unwrap() = seasons;
+    }
+}
+
+/// User data, which is stored and accessible in all command invocations
 pub struct Data {
-    /// Persistent data of users
-    pub users: Arc<DashMap<serenity::UserId, Arc<RwLock<UserData>>>>,
+    /// Persistent data of every guild
+    pub guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
     /// Duration of users in voice channel, updates by events
-    pub voice_users: Arc<DashMap<serenity::UserId, VoiceUser>>,
-    pub meme: Vec<String>,
-    pub ponder: Vec<String>,
-    pub pong: Vec<String>,
-    pub d20f: Vec<String>,
-    pub mod_id: RoleId,
+    pub voice_users: Arc<DashMap<(serenity::GuildId, serenity::UserId), VoiceUser>>,
+    /// Every image professorBot shows, see assets/manifest.toml
+    pub assets: AssetCatalog,
+    /// Tracks changed users and saves them to the store
+    pub saver: Arc<SaveCoordinator>,
+    /// Settings from config.toml, reloadable with /reload_config
+    pub config: ConfigHandle,
+    /// Seeds every random draw and logs it for audits
+    pub rng: RngService,
 }
 
 impl Data {
+    /// Data of a guild, created empty the first time it is used
+    pub fn guild(&self, guild_id: serenity::GuildId) -> Arc<GuildData> {
+        Arc::clone(&self.guilds.entry(guild_id).or_default())
+    }
+
     pub async fn check_or_create_user(ctx: crate::Context<'_>) -> Result<(), crate::Error> {
         let user_id = ctx.author().id;
+        let guild_id = match ctx.guild_id() {
+            Some(guild_id) => guild_id,
+            None => return Ok(()), // Accounts only exist inside a guild
+        };
+        let guild = ctx.data().guild(guild_id);
         {
-            let data = &ctx.data().users;
-            // let data = &mut ctx.data().users;
+            let data = &guild.users;
             if data.contains_key(&user_id) {
                 return Ok(());
             }
 
             data.insert(user_id, Default::default());
+            ctx.data().saver.mark_dirty(guild_id, user_id);
         }
 
-        ctx.send(
-            poise::CreateReply::default()
+        let welcome = ctx.data().assets.get("account.created");
+        let reply = poise::CreateReply::default()
             .content(format!("<@{}>", ctx.author().id))
             .embed(
                 serenity::CreateEmbed::new()
                     .title("Account Created!")
-                    .description(format!("Welcome <@{}>! You are now registered with ProfessorBot, feel free to checkout Professors Commands in https://discord.com/channels/859993171156140061/860013281165967380", ctx.author().id))
-                    .image(
-                        "https://cdn.discordapp.com/attachments/1260223476766343188/1262191655763578881/anime-girl-okay-sign-b5zlye5h8mnjhdg2.gif?ex=6695b315&is=66946195&hm=215e00c0ee066c4a36a8c837f7b24570d2736dae19713e220702114330667f6c&",
-                    )
+                    .description(welcome_text(user_id, &guild.config()))
+                    .asset_image(&welcome)
                     .color(EMBED_DEFAULT),
-            ),
-        )
-        .await?;
+            );
+        ctx.send(assets::attach(reply, &[&welcome]).await).await?;
 
         Ok(())
     }
 
-    /// Attempts to save the data to a file
-    pub async fn save(&self) {
-        let users = Arc::clone(&self.users);
-        let users_save = DashMap::new();
+    /// Attempts to load the Data from the configured store, otherwise return a default
+    pub fn load() -> Data {
+        let config = Config::load(&Config::path())
+            .unwrap_or_else(|e| panic!("Failed to load settings: {}", e));
 
-        for x in users.iter() {
-            let (id, u) = x.pair();
-            let u = u.read().await;
-            users_save.insert(*id, u.clone());
+        let store = storage::open_store().expect("Failed to open storage");
+        let save_data: SaveData = store
+            .load()
+            .unwrap_or_else(|e| panic!("Refusing to start with empty data: {}", e))
+            .unwrap_or_default();
+        println!(
+            "Loaded {} users in {} guilds from {} store",
+            save_data.user_count(),
+            save_data.guilds.len(),
+            store.name()
+        );
+
+        let guilds = Arc::new(DashMap::default());
+        for g in save_data.guilds.iter() {
+            let (guild_id, guild_save) = g.pair();
+            let guild = GuildData::default();
+            guild.set_config(guild_save.config.clone());
+            guild.set_raffle(guild_save.raffle.clone());
+            guild.update_shop(|shop| *shop = guild_save.shop.clone());
+            guild.update_rentals(|rentals| *rentals = guild_save.rentals.clone());
+            guild.update_auctions(|auctions| *auctions = guild_save.auctions.clone());
+            guild.set_seasons(guild_save.seasons.clone());
+            for x in guild_save.users.iter() {
+                let (id, u) = x.pair();
+                guild.users.insert(*id, Arc::new(RwLock::new(u.clone())));
+            }
+            guilds.insert(*guild_id, Arc::new(guild));
         }
 
-        let users_save = SaveData { users: users_save };
+        let assets = assets::load_checked();
 
-        let encoded = serde_json::to_string(&users_save).unwrap();
-        fs::write("data.json", encoded).expect("Failed to write binary save file");
+        Data {
+            guilds: Arc::clone(&guilds),
+            voice_users: Arc::new(DashMap::new()),
+            assets,
+            saver: Arc::new(SaveCoordinator::new(guilds, Arc::from(store))),
+            config: ConfigHandle::new(config),
+            rng: RngService::new(),
+        }
     }
+}
 
-    /// Attempts to load the Data from a file, otherwise return a default
-    pub fn load() -> Data {
-        let data = fs::read_to_string("data.json").ok();
-        let users_data: SaveData = if let Some(file) = data {
-            serde_json::from_str(&file).expect("Old data format?")
-        } else {
-            SaveData::default()
-        };
+/// Greeting for a freshly created account, points at the guild's bot channel if there is one
+pub fn welcome_text(user_id: serenity::UserId, config: &GuildConfig) -> String {
+    match config.bot_cmd {
+        Some(bot_cmd) => format!("Welcome <@{}>! You are now registered with ProfessorBot, feel free to checkout Professors Commands in <#{}>", user_id, bot_cmd),
+        None => format!("Welcome <@{}>! You are now registered with ProfessorBot!", user_id),
+    }
+}
 
-        let users = Arc::new(DashMap::default());
-        for x in users_data.iter() {
-            let (id, u) = x.pair();
-            users.insert(*id, Arc::new(RwLock::new(u.clone())));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LuckConfig;
+
+    fn rolls(naturals: &[i32]) -> Vec<RollRecord> {
+        naturals
+            .iter()
+            .map(|&natural| RollRecord {
+                game: Game::Daily,
+                natural,
+                total: natural,
+                date: Utc::now(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn luck_of_no_rolls() {
+        let stats = LuckStats::of(&rolls(&[]));
+        assert_eq!(stats, LuckStats::default());
+        assert_eq!(stats.label(), "N/A");
+    }
+
+    #[test]
+    fn luck_counts_faces_and_naturals() {
+        let stats = LuckStats::of(&rolls(&[20, 1, 20, 7, 12]));
+        assert_eq!(stats.rolls, 5);
+        assert_eq!(stats.average, 12.0);
+        assert_eq!(stats.nat20s, 2);
+        assert_eq!(stats.nat1s, 1);
+        assert_eq!(stats.faces[19], 2);
+        assert_eq!(stats.faces[6], 1);
+        assert_eq!(stats.faces.iter().sum::<usize>(), 5);
+        assert_eq!(stats.label(), "Above Average");
+    }
+
+    #[test]
+    fn luck_keeps_the_longest_streaks() {
+        let stats = LuckStats::of(&rolls(&[11, 15, 3, 10, 1, 2, 19, 20, 11, 12, 4]));
+        assert_eq!(stats.hot_streak, 4);
+        assert_eq!(stats.cold_streak, 4);
+
+        let stats = LuckStats::of(&rolls(&[5]));
+        assert_eq!((stats.hot_streak, stats.cold_streak), (0, 1));
+    }
+
+    #[test]
+    fn legacy_rolls_count_in_the_average() {
+        let legacy = LegacyRolls { sum: 60, count: 6 };
+        let stats = LuckStats::of(&rolls(&[20, 20])).with_legacy(Some(&legacy));
+        assert_eq!(stats.rolls, 8);
+        assert_eq!(stats.legacy, 6);
+        assert_eq!(stats.average, 12.5);
+        // only the recorded rolls have faces
+        assert_eq!(stats.faces.iter().sum::<usize>(), 2);
+
+        let only_legacy = LuckStats::default().with_legacy(Some(&legacy));
+        assert_eq!(only_legacy.average, 10.0);
+        assert_eq!(LuckStats::default().with_legacy(None), LuckStats::default());
+    }
+
+    fn buy_tickets(user: &mut UserData, count: i32, cost: i32) {
+        user.sub_creds(cost, CredReason::Tickets { count });
+        user.add_tickets(count);
+    }
 
-        let meme = read_lines("reference/meme.txt");
-        let ponder = read_lines("reference/ponder.txt");
-        let pong = read_lines("reference/pong.txt");
-        let d20f = read_lines("reference/d20.txt");
+    #[test]
+    fn refunds_what_the_tickets_cost() {
+        let config = Config::default();
+        let mut user = UserData::default();
+        user.add_creds(1000, CredReason::Voice);
+        buy_tickets(&mut user, 2, 30);
+        // the price went up in between
+        buy_tickets(&mut user, 1, 50);
 
-        let mod_id = RoleId::new(
-            env::var("MOD_ID")
-                .expect("Missing moderator ID")
-                .parse()
-                .unwrap(),
+        assert_eq!(user.ticket_refund(1, &config), 50);
+        assert_eq!(user.ticket_refund(2, &config), 65);
+        assert_eq!(user.ticket_refund(3, &config), 80);
+        // can't refund tickets they don't have
+        assert_eq!(user.ticket_refund(10, &config), 80);
+
+        // tickets already refunded aren't paid out twice
+        user.remove_tickets(2);
+        user.add_creds(
+            65,
+            CredReason::TicketRefund {
+                count: 2,
+                by: serenity::UserId::new(1),
+                note: String::new(),
+            },
         );
+        assert_eq!(user.ticket_refund(1, &config), 15);
+    }
 
-        Data {
-            users,
-            voice_users: Arc::new(DashMap::new()),
-            meme,
-            ponder,
-            pong,
-            d20f,
-            mod_id,
-        }
+    #[test]
+    fn refunds_unbought_tickets_on_the_price_curve() {
+        let config = Config::default();
+        let mut user = UserData::default();
+        user.add_tickets(2);
+        assert_eq!(user.ticket_refund(2, &config), config.ticket_refund(2, 2));
+
+        user.add_creds(1000, CredReason::Voice);
+        buy_tickets(&mut user, 1, 70);
+        assert_eq!(
+            user.ticket_refund(3, &config),
+            70 + config.ticket_refund(2, 2)
+        );
     }
-}
 
-fn read_lines(filename: &str) -> Vec<String> {
-    let lines: Vec<String> = fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(String::from)
-        .collect();
+    #[test]
+    fn fortune_needs_min_rolls() {
+        let config = LuckConfig { min_rolls: 10 };
+        let mut user = UserData::default();
+        for _ in 0..9 {
+            user.record_roll(Game::Daily, 20, 20);
+        }
+        assert!(!config.is_ranked(user.get_luck_stats().rolls));
 
-    // println!("{}: loaded {} lines", filename, lines.len());
-    lines
+        user.record_roll(Game::Bonus, 20, 20);
+        assert!(config.is_ranked(user.get_luck_stats().rolls));
+        // out of range dice aren't kept
+        assert!(!user.record_roll(Game::Daily, 21, 21));
+        assert_eq!(user.get_rolls().len(), 10);
+    }
 }
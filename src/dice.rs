@@ -0,0 +1,561 @@
+//!---------------------------------------------------------------------!
+//! This file contains the dice engine, expressions like 4d6kh3+2,      !
+//! 1d20 adv and 2d10! are parsed once and rolled with any rng          !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - roll                                                      !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - parse                                                     !
+//!     [x] - d20                                                       !
+//!---------------------------------------------------------------------!
+
+use crate::data;
+use crate::{serenity, Context, Error};
+use rand::Rng;
+use regex::Regex;
+use std::fmt;
+
+// Limits so one expression can't flood the embed or spin forever
+const MAX_TERMS: usize = 10;
+const MAX_DICE: usize = 100;
+const MAX_SIDES: i32 = 1000;
+const MAX_EXPLOSIONS: usize = 100;
+
+/// Which dice of a group count towards the total
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    Highest(usize),
+    Lowest(usize),
+    DropHighest(usize),
+    DropLowest(usize),
+}
+
+/// A group of dice like 4d6kh3, `explode` rolls another die every time one lands on its max
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiceTerm {
+    pub count: usize,
+    pub sides: i32,
+    pub explode: bool,
+    pub keep: Option<Keep>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Dice(DiceTerm),
+    Flat(i32),
+}
+
+/// A parsed expression, terms are added or subtracted by their sign
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub terms: Vec<(i32, Term)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Die {
+    pub value: i32,
+    pub kept: bool,
+    /// Landed on its max and rolled another die
+    pub exploded: bool,
+}
+
+/// How one group of dice landed
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupRoll {
+    pub sign: i32,
+    pub term: DiceTerm,
+    pub dice: Vec<Die>,
+}
+
+/// Everything a rolled expression came up with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    pub groups: Vec<GroupRoll>,
+    /// Sum of the plain numbers
+    pub flat: i32,
+}
+
+impl fmt::Display for DiceTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        if self.explode {
+            write!(f, "!")?;
+        }
+        match self.keep {
+            Some(Keep::Highest(n)) => write!(f, "kh{}", n),
+            Some(Keep::Lowest(n)) => write!(f, "kl{}", n),
+            Some(Keep::DropHighest(n)) => write!(f, "dh{}", n),
+            Some(Keep::DropLowest(n)) => write!(f, "dl{}", n),
+            None => Ok(()),
+        }
+    }
+}
+
+impl GroupRoll {
+    pub fn total(&self) -> i32 {
+        self.sign
+            * self
+                .dice
+                .iter()
+                .filter(|d| d.kept)
+                .map(|d| d.value)
+                .sum::<i32>()
+    }
+}
+
+impl Roll {
+    pub fn total(&self) -> i32 {
+        self.groups.iter().map(GroupRoll::total).sum::<i32>() + self.flat
+    }
+
+    /// First kept die, what a natural 1 or 20 is checked against
+    pub fn natural(&self) -> Option<i32> {
+        self.groups
+            .first()?
+            .dice
+            .iter()
+            .find(|d| d.kept)
+            .map(|d| d.value)
+    }
+
+    /// Every group with its dice, dropped dice are struck through
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for group in &self.groups {
+            let dice: Vec<String> = group
+                .dice
+                .iter()
+                .map(|d| match (d.kept, d.exploded) {
+                    (true, true) => format!("{}!", d.value),
+                    (true, false) => d.value.to_string(),
+                    (false, _) => format!("~~{}~~", d.value),
+                })
+                .collect();
+            text += format!(
+                "{} `{}` [{}] = **{}**\n",
+                if group.sign < 0 { "-" } else { "+" },
+                group.term,
+                dice.join(", "),
+                group.total().abs()
+            )
+            .as_str();
+        }
+        if self.flat != 0 {
+            text += format!(
+                "{} **{}**\n",
+                if self.flat < 0 { "-" } else { "+" },
+                self.flat.abs()
+            )
+            .as_str();
+        }
+        text.trim_start_matches("+ ").to_string()
+    }
+}
+
+impl DiceTerm {
+    fn roll(&self, sign: i32, rng: &mut impl Rng) -> GroupRoll {
+        let mut dice = Vec::new();
+        let mut explosions = 0;
+        for _ in 0..self.count {
+            loop {
+                let value = rng.gen_range(1..=self.sides);
+                let exploded = self.explode && value == self.sides && explosions < MAX_EXPLOSIONS;
+                dice.push(Die {
+                    value,
+                    kept: true,
+                    exploded,
+                });
+                if !exploded {
+                    break;
+                }
+                explosions += 1;
+            }
+        }
+
+        if let Some(keep) = self.keep {
+            // indices from the highest die to the lowest
+            let mut order: Vec<usize> = (0..dice.len()).collect();
+            order.sort_by_key(|i| std::cmp::Reverse(dice[*i].value));
+            let n = dice.len();
+            let kept: Vec<usize> = match keep {
+                Keep::Highest(k) => order[..k.min(n)].to_vec(),
+                Keep::Lowest(k) => order[n - k.min(n)..].to_vec(),
+                Keep::DropHighest(k) => order[k.min(n)..].to_vec(),
+                Keep::DropLowest(k) => order[..n - k.min(n)].to_vec(),
+            };
+            for (i, die) in dice.iter_mut().enumerate() {
+                die.kept = kept.contains(&i);
+            }
+        }
+
+        GroupRoll {
+            sign,
+            term: self.clone(),
+            dice,
+        }
+    }
+}
+
+impl Expression {
+    pub fn roll(&self, rng: &mut impl Rng) -> Roll {
+        let mut roll = Roll {
+            groups: Vec::new(),
+            flat: 0,
+        };
+        for (sign, term) in &self.terms {
+            match term {
+                Term::Dice(dice) => roll.groups.push(dice.roll(*sign, rng)),
+                Term::Flat(n) => roll.flat += sign * n,
+            }
+        }
+        roll
+    }
+}
+
+fn parse_term(text: &str) -> Result<Term, String> {
+    if let Ok(n) = text.parse::<i32>() {
+        return Ok(Term::Flat(n));
+    }
+
+    let dice_regex = Regex::new(r"^(\d*)d(\d+)(!?)(?:(kh|kl|dh|dl|k)(\d+))?$").unwrap();
+    let caps = dice_regex
+        .captures(text)
+        .ok_or_else(|| format!("`{}` isn't a number or dice like 2d6", text))?;
+
+    let count = match &caps[1] {
+        "" => 1,
+        n => n
+            .parse::<usize>()
+            .map_err(|_| format!("`{}` is too many dice", n))?,
+    };
+    let sides = caps[2]
+        .parse::<i32>()
+        .map_err(|_| format!("`{}` is too many sides", &caps[2]))?;
+    if !(1..=MAX_DICE).contains(&count) {
+        return Err(format!("You can roll 1 to {} dice at once", MAX_DICE));
+    }
+    if !(2..=MAX_SIDES).contains(&sides) {
+        return Err(format!("Dice need 2 to {} sides", MAX_SIDES));
+    }
+
+    let keep = match caps.get(4) {
+        Some(kind) => {
+            let n = caps[5].parse::<usize>().unwrap_or(usize::MAX);
+            Some(match kind.as_str() {
+                "kh" | "k" => Keep::Highest(n),
+                "kl" => Keep::Lowest(n),
+                "dh" => Keep::DropHighest(n),
+                _ => Keep::DropLowest(n),
+            })
+        }
+        None => None,
+    };
+
+    Ok(Term::Dice(DiceTerm {
+        count,
+        sides,
+        explode: !caps[3].is_empty(),
+        keep,
+    }))
+}
+
+/// Parse an expression like `4d6kh3+2`, a trailing `adv` or `dis` rolls the
+/// first single die twice and keeps the higher or lower one
+pub fn parse(expression: &str) -> Result<Expression, String> {
+    let mut text = expression.trim().to_lowercase();
+    let mut advantage = None;
+    // longest first, "advantage" is also the end of "disadvantage"
+    for (word, keep) in [
+        ("disadvantage", Keep::Lowest(1)),
+        ("advantage", Keep::Highest(1)),
+        ("dis", Keep::Lowest(1)),
+        ("adv", Keep::Highest(1)),
+    ] {
+        if let Some(rest) = text.strip_suffix(word) {
+            text = rest.to_string();
+            advantage = Some(keep);
+            break;
+        }
+    }
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return Err("Nothing to roll, try something like `1d20+5`".to_string());
+    }
+
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut current = String::new();
+    for c in text.chars().chain(std::iter::once('+')) {
+        if c != '+' && c != '-' {
+            current.push(c);
+            continue;
+        }
+        if current.is_empty() {
+            // a sign before the first term, anything else is a doubled operator
+            if !terms.is_empty() {
+                return Err(format!("`{}` has an operator without a term", expression));
+            }
+        } else {
+            terms.push((sign, parse_term(&current)?));
+            current.clear();
+        }
+        sign = if c == '-' { -1 } else { 1 };
+    }
+    if terms.len() > MAX_TERMS {
+        return Err(format!("Expressions can have at most {} terms", MAX_TERMS));
+    }
+
+    // the biggest total the expression could reach has to fit, so rolling it can't overflow
+    let most: i64 = terms
+        .iter()
+        .map(|(_, term)| match term {
+            Term::Dice(dice) => (dice.count + MAX_EXPLOSIONS) as i64 * dice.sides as i64,
+            Term::Flat(n) => *n as i64,
+        })
+        .sum();
+    if most > i32::MAX as i64 {
+        return Err(format!(
+            "`{}` could total more than {}",
+            expression,
+            i32::MAX
+        ));
+    }
+
+    if let Some(keep) = advantage {
+        match terms.iter_mut().find_map(|(_, t)| match t {
+            Term::Dice(dice) => Some(dice),
+            Term::Flat(_) => None,
+        }) {
+            Some(dice) if dice.count == 1 && dice.keep.is_none() => {
+                dice.count = 2;
+                dice.keep = Some(keep);
+            }
+            _ => return Err("Advantage needs a single die, like `1d20 adv`".to_string()),
+        }
+    }
+
+    Ok(Expression { terms })
+}
+
+/// Natural d20 for the daily and bonus games
+pub fn d20(rng: &mut impl Rng) -> i32 {
+    let expression = Expression {
+        terms: vec![(
+            1,
+            Term::Dice(DiceTerm {
+                count: 1,
+                sides: 20,
+                explode: false,
+                keep: None,
+            }),
+        )],
+    };
+    expression.roll(rng).total()
+}
+
+/// roll dice for tabletop nights, like 4d6kh3+2, 1d20 adv or 2d10!
+#[poise::command(slash_command)]
+pub async fn roll(
+    ctx: Context<'_>,
+    #[description = "dice to roll, like 4d6kh3+2, 1d20 adv or 2d10!"] expression: String,
+) -> Result<(), Error> {
+    let embed = match parse(&expression) {
+        Ok(parsed) => {
+            let rolled = parsed.roll(&mut ctx.data().rng.draw(&format!(
+                "roll {} {}",
+                ctx.author().id,
+                expression.trim()
+            )));
+            serenity::CreateEmbed::new()
+                .title(format!("Roll {}", expression.trim()))
+                .description(format!(
+                    "{}\nTotal: **{}**",
+                    rolled.describe(),
+                    rolled.total()
+                ))
+                .color(data::EMBED_CYAN)
+        }
+        Err(e) => serenity::CreateEmbed::new()
+            .title("Roll")
+            .description(e)
+            .color(data::EMBED_ERROR),
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            embed
+                .thumbnail(ctx.author().avatar_url().unwrap_or_default())
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DrawRng;
+    use rand::{RngCore, SeedableRng};
+
+    /// Every d2 lands on a 2, half of u32 is the first value the sampler maps to it
+    struct MaxRng;
+
+    impl RngCore for MaxRng {
+        fn next_u32(&mut self) -> u32 {
+            1 << 31
+        }
+        fn next_u64(&mut self) -> u64 {
+            1 << 63
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0x80);
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            dest.fill(0x80);
+            Ok(())
+        }
+    }
+
+    fn roll(expression: &str, seed: u64) -> Roll {
+        parse(expression)
+            .unwrap()
+            .roll(&mut DrawRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let dice = |count, sides, explode, keep| {
+            Term::Dice(DiceTerm {
+                count,
+                sides,
+                explode,
+                keep,
+            })
+        };
+        let cases = [
+            (
+                "4d6kh3+2",
+                vec![
+                    (1, dice(4, 6, false, Some(Keep::Highest(3)))),
+                    (1, Term::Flat(2)),
+                ],
+            ),
+            (
+                "1d20 adv",
+                vec![(1, dice(2, 20, false, Some(Keep::Highest(1))))],
+            ),
+            (
+                "1d20 dis",
+                vec![(1, dice(2, 20, false, Some(Keep::Lowest(1))))],
+            ),
+            (
+                "1d20 disadvantage",
+                vec![(1, dice(2, 20, false, Some(Keep::Lowest(1))))],
+            ),
+            (
+                "1d20 Advantage",
+                vec![(1, dice(2, 20, false, Some(Keep::Highest(1))))],
+            ),
+            ("2d10!", vec![(1, dice(2, 10, true, None))]),
+            ("d20", vec![(1, dice(1, 20, false, None))]),
+            ("-1d4", vec![(-1, dice(1, 4, false, None))]),
+            (
+                "3d6dl1 - 2",
+                vec![
+                    (1, dice(3, 6, false, Some(Keep::DropLowest(1)))),
+                    (-1, Term::Flat(2)),
+                ],
+            ),
+        ];
+        for (expression, terms) in cases {
+            assert_eq!(
+                parse(expression),
+                Ok(Expression { terms }),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for expression in [
+            "",
+            "   ",
+            "adv",
+            "abc",
+            "1d6++2",
+            "1d6+",
+            "1d1",
+            "0d6",
+            "101d6",
+            "1d1001",
+            "2d20 adv",
+            "2000000000+2000000000",
+            "99999999999",
+            "1+1+1+1+1+1+1+1+1+1+1",
+        ] {
+            assert!(parse(expression).is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn keeps_the_highest_dice() {
+        for seed in 0..20 {
+            let rolled = roll("4d6kh3+2", seed);
+            let dice = &rolled.groups[0].dice;
+            assert_eq!(dice.len(), 4);
+            assert_eq!(dice.iter().filter(|d| d.kept).count(), 3);
+
+            let lowest = dice.iter().map(|d| d.value).min().unwrap();
+            let sum: i32 = dice.iter().map(|d| d.value).sum();
+            assert_eq!(rolled.total(), sum - lowest + 2);
+        }
+    }
+
+    #[test]
+    fn advantage_keeps_one_of_two() {
+        for seed in 0..20 {
+            let adv = roll("1d20 adv", seed);
+            let dis = roll("1d20 dis", seed);
+            let values: Vec<i32> = adv.groups[0].dice.iter().map(|d| d.value).collect();
+
+            // the same seed rolls the same two dice
+            assert_eq!(adv.total(), *values.iter().max().unwrap());
+            assert_eq!(dis.total(), *values.iter().min().unwrap());
+            assert_eq!(adv.natural(), Some(adv.total()));
+        }
+    }
+
+    #[test]
+    fn seeded_rolls_repeat() {
+        for seed in 0..5 {
+            assert_eq!(
+                roll("4d6kh3+2d8!-1d4+3", seed),
+                roll("4d6kh3+2d8!-1d4+3", seed)
+            );
+        }
+        for seed in 0..20 {
+            let total = roll("-1d4", seed).total();
+            assert!((-4..=-1).contains(&total));
+            let total = roll("d20", seed).total();
+            assert!((1..=20).contains(&total));
+            assert!((1..=20).contains(&d20(&mut DrawRng::seed_from_u64(seed))));
+        }
+    }
+
+    #[test]
+    fn explosions_are_capped() {
+        let rolled = parse("2d2!").unwrap().roll(&mut MaxRng);
+        let dice = &rolled.groups[0].dice;
+
+        assert_eq!(dice.len(), 2 + MAX_EXPLOSIONS);
+        assert_eq!(dice.iter().filter(|d| d.exploded).count(), MAX_EXPLOSIONS);
+        assert!(!dice.last().unwrap().exploded);
+        assert_eq!(rolled.total(), 2 * (2 + MAX_EXPLOSIONS as i32));
+    }
+}
@@ -0,0 +1,268 @@
+//!---------------------------------------------------------------------!
+//! This file contains the economy dashboard, how creds are spread      !
+//! across members and how fast they are made and spent                 !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - economy                                                   !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - percentile                                                !
+//!     [x] - gini                                                      !
+//!     [x] - histogram                                                 !
+//!---------------------------------------------------------------------!
+
+use crate::clips::check_mod;
+use crate::data::{self, LedgerEntry};
+use crate::{serenity, Context, Error};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+/// Upper bounds of the histogram buckets, the last bucket has no bound
+const BUCKETS: [i32; 7] = [0, 100, 500, 1000, 5000, 10000, 50000];
+
+// Width of the longest histogram bar
+const BAR_WIDTH: usize = 20;
+
+/// Nearest rank percentile of ascending `sorted`, `p` is 0 to 100
+pub fn percentile(sorted: &[i32], p: usize) -> i32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+/// Gini coefficient of ascending `sorted`, 0 is perfectly equal and 1 is one
+/// member owning everything. Negative balances count as nothing
+pub fn gini(sorted: &[i32]) -> f64 {
+    let n = sorted.len() as f64;
+    let total: f64 = sorted.iter().map(|c| (*c).max(0) as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i + 1) as f64 * (*c).max(0) as f64)
+        .sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+/// Balances bucketed into a text bar chart
+pub fn histogram(balances: &[i32]) -> String {
+    let mut counts = [0usize; BUCKETS.len() + 1];
+    for creds in balances {
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| creds <= bound)
+            .unwrap_or(BUCKETS.len());
+        counts[bucket] += 1;
+    }
+
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    let mut chart = String::new();
+    for (i, count) in counts.iter().enumerate() {
+        let label = match i {
+            0 => "≤ 0".to_string(),
+            i if i == BUCKETS.len() => format!("> {}", BUCKETS[i - 1]),
+            i => format!("{}-{}", BUCKETS[i - 1] + 1, BUCKETS[i]),
+        };
+        chart += format!(
+            "{:<11} {:<width$} {}\n",
+            label,
+            "█".repeat(count * BAR_WIDTH / most),
+            count,
+            width = BAR_WIDTH
+        )
+        .as_str();
+    }
+    chart
+}
+
+/// Creds one ledger brought into and took out of the economy since `since`
+fn flow(ledger: &[LedgerEntry], since: DateTime<Utc>) -> (i64, i64) {
+    let mut minted = 0;
+    let mut spent = 0;
+    for entry in ledger.iter().filter(|e| e.date >= since) {
+        if !entry.reason.changes_supply() {
+            continue;
+        }
+        match entry.amount > 0 {
+            true => minted += entry.amount as i64,
+            false => spent -= entry.amount as i64,
+        }
+    }
+    (minted, spent)
+}
+
+/// [!] MODERATOR - see how creds are spread and how fast they are made and spent
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn economy(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let now = Utc::now();
+
+    let mut balances = Vec::new();
+    let (mut minted_7, mut spent_7) = (0, 0);
+    let (mut minted_30, mut spent_30) = (0, 0);
+    let mut debt: i64 = 0;
+    let mut tickets: i64 = 0;
+    let mut holders = 0;
+    // clone the Arcs out first, a DashMap shard stays locked while a ref is held
+    let users: Vec<_> = guild.users.iter().map(|x| Arc::clone(x.value())).collect();
+    for u in users {
+        let u = u.read().await;
+        balances.push(u.get_creds());
+        debt += u.get_debt() as i64;
+        if u.get_tickets() > 0 {
+            tickets += u.get_tickets() as i64;
+            holders += 1;
+        }
+
+        let (minted, spent) = flow(u.get_ledger(), now - Duration::days(7));
+        minted_7 += minted;
+        spent_7 += spent;
+        let (minted, spent) = flow(u.get_ledger(), now - Duration::days(30));
+        minted_30 += minted;
+        spent_30 += spent;
+    }
+    balances.sort();
+
+    if balances.is_empty() {
+        ctx.send(
+            poise::CreateReply::default().embed(
+                serenity::CreateEmbed::new()
+                    .title("Economy")
+                    .description("No one has an account yet")
+                    .color(data::EMBED_ERROR)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "@~ powered by UwUntu & RustyBamboo",
+                    )),
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let supply: i64 = balances.iter().map(|c| *c as i64).sum();
+
+    let supply_text = format!(
+        "Total: **{}**\nMembers: **{}**\nAverage: **{}**\nDebt owed: **{}**\nGini: **{:.2}**",
+        supply,
+        balances.len(),
+        supply / balances.len() as i64,
+        debt,
+        gini(&balances)
+    );
+    let spread_text = format!(
+        "10th: **{}**\n25th: **{}**\nMedian: **{}**\n75th: **{}**\n90th: **{}**\n99th: **{}**",
+        percentile(&balances, 10),
+        percentile(&balances, 25),
+        percentile(&balances, 50),
+        percentile(&balances, 75),
+        percentile(&balances, 90),
+        percentile(&balances, 99)
+    );
+    let flow_text = format!(
+        "7 days: **+{}** / **-{}** ({:+})\n30 days: **+{}** / **-{}** ({:+})",
+        minted_7,
+        spent_7,
+        minted_7 - spent_7,
+        minted_30,
+        spent_30,
+        minted_30 - spent_30
+    );
+    let ticket_text = format!("**{}** tickets held by **{}** members", tickets, holders);
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Economy")
+                .description(format!("```\n{}```", histogram(&balances)))
+                .field("Supply", supply_text, true)
+                .field("Percentiles", spread_text, true)
+                .field("Minted / Spent", flow_text, false)
+                .field("Raffle", ticket_text, false)
+                .color(data::EMBED_MOD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{CredReason, UserData};
+    use poise::serenity_prelude::UserId;
+
+    #[test]
+    fn only_the_pay_tax_leaves_the_economy() {
+        let to = UserId::new(2);
+        let mut user = UserData::default();
+        user.add_creds(500, CredReason::Voice);
+        user.sub_creds(90, CredReason::PaySent { to, tax: 10 });
+        user.sub_creds(10, CredReason::PayTax { to });
+        user.add_creds(40, CredReason::PayReceived { from: to });
+
+        let since = Utc::now() - Duration::days(1);
+        assert_eq!(flow(user.get_ledger(), since), (500, 10));
+        assert_eq!(user.paid_since(since), 100);
+    }
+
+    #[test]
+    fn percentile_takes_the_nearest_rank() {
+        assert_eq!(percentile(&[], 50), 0);
+        assert_eq!(percentile(&[7], 0), 7);
+        assert_eq!(percentile(&[7], 100), 7);
+
+        let sorted: Vec<i32> = (1..=10).collect();
+        assert_eq!(percentile(&sorted, 0), 1);
+        assert_eq!(percentile(&sorted, 10), 1);
+        // 2.5 rounds up to the third
+        assert_eq!(percentile(&sorted, 25), 3);
+        assert_eq!(percentile(&sorted, 50), 5);
+        assert_eq!(percentile(&sorted, 51), 6);
+        assert_eq!(percentile(&sorted, 100), 10);
+        assert_eq!(percentile(&[1, 2, 3, 4], 50), 2);
+    }
+
+    #[test]
+    fn gini_of_edge_cases() {
+        assert_eq!(gini(&[]), 0.0);
+        assert_eq!(gini(&[0, 0, 0]), 0.0);
+        assert_eq!(gini(&[-50, -10, 0]), 0.0);
+        assert!(gini(&[5, 5, 5]).abs() < 1e-9);
+
+        // one member owning everything is (n - 1) / n
+        assert!((gini(&[0, 0, 0, 100]) - 0.75).abs() < 1e-9);
+        // debt counts as owning nothing
+        assert!((gini(&[-50, 0, 100]) - gini(&[0, 0, 100])).abs() < 1e-9);
+    }
+
+    #[test]
+    fn histogram_buckets_by_upper_bound() {
+        let counts = |balances: &[i32]| -> Vec<usize> {
+            histogram(balances)
+                .lines()
+                .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+                .collect()
+        };
+
+        assert_eq!(counts(&[]), vec![0; BUCKETS.len() + 1]);
+        assert!(!histogram(&[]).contains('█'));
+        assert_eq!(
+            counts(&[-5, 0, 1, 100, 101, 50000, 50001]),
+            vec![2, 2, 1, 0, 0, 0, 1, 1]
+        );
+
+        // the fullest bucket gets the whole bar
+        let chart = histogram(&[0, 0, 200]);
+        let bars: Vec<usize> = chart.lines().map(|l| l.matches('█').count()).collect();
+        assert_eq!(bars[0], BAR_WIDTH);
+        assert_eq!(bars[2], BAR_WIDTH / 2);
+    }
+}
@@ -7,21 +7,20 @@
 //!     [x] - gpt_doodle                                                !
 //!---------------------------------------------------------------------!
 
+use crate::config::GptConfig;
 use openai_api_rs::v1::api::OpenAIClient;
 use openai_api_rs::v1::chat_completion::{self, ChatCompletionRequest};
-use openai_api_rs::v1::common::DALL_E_3;
-use openai_api_rs::v1::common::GPT4_1106_PREVIEW;
 use openai_api_rs::v1::error::APIError;
 use openai_api_rs::v1::image::ImageGenerationRequest;
 use rand::{thread_rng, Rng};
 use std::env;
 
 /// use gpt-3.5-turbo to generate fun responses to user prompts
-pub async fn gpt_string(api_key: String, prompt: String) -> Result<String, APIError> {
+pub async fn gpt_string(api_key: String, model: &str, prompt: String) -> Result<String, APIError> {
     let client = OpenAIClient::new(api_key.to_string());
 
     let req = ChatCompletionRequest::new(
-        GPT4_1106_PREVIEW.to_string(),
+        model.to_string(),
         vec![chat_completion::ChatCompletionMessage {
             role: chat_completion::MessageRole::user,
             content: chat_completion::Content::Text(prompt),
@@ -46,16 +45,20 @@ pub async fn gpt_string(api_key: String, prompt: String) -> Result<String, APIEr
 }
 
 // use dalli-e-3 to generate fun doodles in various styles
-pub async fn gpt_doodle(api_key: String, prompt: String) -> Result<String, APIError> {
+pub async fn gpt_doodle(api_key: String, model: &str, prompt: String) -> Result<String, APIError> {
     let client = OpenAIClient::new(api_key.to_string());
 
-    let req = ImageGenerationRequest::new(prompt).model(DALL_E_3.to_string());
+    let req = ImageGenerationRequest::new(prompt).model(model.to_string());
     let result = client.image_generation(req).await?;
 
     Ok(result.data.first().unwrap().url.to_string())
 }
 
-pub async fn generate_doodle(messages: &mut [String], randomstyle: bool) -> String {
+pub async fn generate_doodle(
+    messages: &mut [String],
+    randomstyle: bool,
+    config: &GptConfig,
+) -> Option<String> {
     let gpt_key: String = env::var("API_KEY").expect("missing GPT API_KEY");
 
     messages.reverse();
@@ -74,13 +77,16 @@ pub async fn generate_doodle(messages: &mut [String], randomstyle: bool) -> Stri
         full_message_history
     };
 
-    match gpt_doodle(gpt_key, prompt.clone()).await {
-        Ok(doodle_url) => doodle_url,
-        Err(_) => "https://cdn.discordapp.com/attachments/1260223476766343188/1278857715891830875/collapsed-stair-structure-of-wooden-cubes-with-upward-pointing-arrows-business-risk-due-to.webp?ex=66d2548f&is=66d1030f&hm=8265ac5a5caf779f6415b8ae2e71843b78ff80d73ef1870df4150ae99991d785&".to_string(),
+    match gpt_doodle(gpt_key, &config.image_model, prompt.clone()).await {
+        Ok(doodle_url) => Some(doodle_url),
+        Err(e) => {
+            println!("Failed to draw doodle: {:?}", e);
+            None
+        }
     }
 }
 
-pub async fn generate_text(messages: &mut [String]) -> String {
+pub async fn generate_text(messages: &mut [String], config: &GptConfig) -> String {
     let gpt_key: String = env::var("API_KEY").expect("missing GPT API_KEY");
 
     messages.reverse();
@@ -94,7 +100,7 @@ pub async fn generate_text(messages: &mut [String]) -> String {
     let mut tries = 0;
     let mut reading;
     loop {
-        match gpt_string(gpt_key.clone(), prompt.clone()).await {
+        match gpt_string(gpt_key.clone(), &config.chat_model, prompt.clone()).await {
             Ok(result) => {
                 reading = result;
                 break;
@@ -102,7 +108,7 @@ pub async fn generate_text(messages: &mut [String]) -> String {
             Err(e) => {
                 println!("An error occurred: {:?}, retrying...", e);
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                if tries > 3 {
+                if tries + 1 >= config.retries {
                     return "Professor failed chat completion... please try again later."
                         .to_string();
                 }
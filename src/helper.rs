@@ -6,7 +6,9 @@
 //!     [ ] - parse_user_mention                                        !
 //!---------------------------------------------------------------------!
 
-use chrono::{Datelike, NaiveDate, TimeDelta, Utc};
+use crate::clock;
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use chrono_tz::Tz;
 use poise::serenity_prelude::UserId;
 
 pub fn parse_user_mention(user_mention: String) -> u64 {
@@ -16,8 +18,8 @@ pub fn parse_user_mention(user_mention: String) -> u64 {
         .unwrap_or(1)
 }
 
-pub fn get_current_date() -> String {
-    let today = Utc::now() - TimeDelta::hours(4);
+pub fn get_current_date(tz: Tz) -> String {
+    let today = clock::today(tz);
     format!("{}-{}-{}", today.year(), today.month(), today.day())
 }
 
@@ -32,9 +34,8 @@ pub fn get_reminder_date(date_str: &str) -> String {
     )
 }
 
-pub fn get_current_year() -> String {
-    let today = Utc::now() - TimeDelta::hours(4);
-    format!("{}", today.year())
+pub fn get_current_year(tz: Tz) -> String {
+    format!("{}", clock::today(tz).year())
 }
 
 pub fn get_leaderboard(
@@ -48,6 +49,7 @@ pub fn get_leaderboard(
     let creds: String = "Creds".to_string();
     let fortune: String = "Fortune".to_string();
     let level: String = "Level".to_string();
+    let streak: String = "Streak".to_string();
 
     if sort == creds {
         leaderboard_text.push_str("```\n"); // Start code block for monospaced font
@@ -79,7 +81,7 @@ pub fn get_leaderboard(
         leaderboard_text.push_str("```\n"); // End code block
     }
 
-    if sort == level {
+    if sort == level || sort == streak {
         leaderboard_text.push_str("```\n"); // Start code block for monospaced font
 
         for (index, (_id, _, level, user_name)) in info.iter().enumerate().skip(start).take(10) {
@@ -0,0 +1,31 @@
+pub mod assets;
+pub mod auctions;
+pub mod basic;
+pub mod clips;
+pub mod clock;
+pub mod config;
+pub mod data;
+pub mod dice;
+pub mod economy;
+pub mod gpt;
+pub mod helper;
+pub mod loans;
+pub mod luck;
+pub mod migrate;
+pub mod modifiers;
+pub mod mods;
+pub mod raffle;
+pub mod reminder;
+pub mod rentals;
+pub mod rng;
+pub mod saver;
+pub mod seasons;
+pub mod shop;
+pub mod storage;
+pub mod streaks;
+pub mod wishes;
+
+pub use poise::serenity_prelude as serenity;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Context<'a> = poise::Context<'a, data::Data, Error>;
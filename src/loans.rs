@@ -0,0 +1,161 @@
+//!---------------------------------------------------------------------!
+//! This file contains loans, borrowed creds and losses past the floor  !
+//! are owed as debt and paid back out of daily and voice rewards       !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - loan                                                      !
+//!     [x] - repay                                                     !
+//!---------------------------------------------------------------------!
+
+use crate::data;
+use crate::{serenity, Context, Error};
+
+fn loan_embed(title: &str, desc: String, color: serenity::Color) -> poise::CreateReply {
+    poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title(title)
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    )
+}
+
+/// borrow creds, or see what you owe when no amount is given
+#[poise::command(slash_command, guild_only)]
+pub async fn loan(
+    ctx: Context<'_>,
+    #[description = "how many creds to borrow"]
+    #[min = 1]
+    amount: Option<u32>,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+    let debt = &config.debt;
+
+    let u = guild.users.get(&user_id).unwrap();
+    let mut user_data = u.write().await;
+    let owed = user_data.get_debt();
+
+    let amount = match amount {
+        Some(amount) => amount.min(i32::MAX as u32) as i32,
+        None => {
+            let desc = match owed {
+                0 => format!(
+                    "You don't owe anything. You can borrow up to **{}** creds with **{}%** interest",
+                    debt.max_loan, debt.interest_percent
+                ),
+                _ => format!(
+                    "You owe **{}** creds.\n\n**{}%** of every daily and voice reward goes to paying it off until it is gone, or pay it yourself with **/repay**",
+                    owed, debt.repay_percent
+                ),
+            };
+            drop(user_data);
+            ctx.send(loan_embed("Loan", desc, data::EMBED_GOLD)).await?;
+            return Ok(());
+        }
+    };
+
+    let interest = config.loan_interest(amount);
+    let room = debt.max_loan - owed;
+    if debt.max_loan == 0 {
+        drop(user_data);
+        ctx.send(loan_embed(
+            "Loan",
+            "Loans are turned off right now".to_string(),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+    if amount.saturating_add(interest) > room {
+        let desc = format!(
+            "You can owe at most **{}** creds, you already owe **{}**. Borrowing **{}** would add **{}** with interest",
+            debt.max_loan,
+            owed,
+            amount,
+            amount.saturating_add(interest)
+        );
+        drop(user_data);
+        ctx.send(loan_embed("Loan", desc, data::EMBED_ERROR))
+            .await?;
+        return Ok(());
+    }
+
+    user_data.take_loan(amount, interest);
+    ctx.data().saver.mark_dirty(guild_id, user_id);
+
+    let desc = format!(
+        "You borrowed **{}** creds! With **{}%** interest you now owe **{}**.\n\n**{}%** of every daily and voice reward goes to paying it off",
+        amount,
+        debt.interest_percent,
+        user_data.get_debt(),
+        debt.repay_percent
+    );
+    drop(user_data);
+    ctx.send(loan_embed("Loan", desc, data::EMBED_GOLD)).await?;
+    Ok(())
+}
+
+/// pay off your debt with creds you have
+#[poise::command(slash_command, guild_only)]
+pub async fn repay(
+    ctx: Context<'_>,
+    #[description = "how many creds to pay back (default as much as you can)"]
+    #[min = 1]
+    amount: Option<u32>,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+
+    let u = guild.users.get(&user_id).unwrap();
+    let mut user_data = u.write().await;
+
+    let owed = user_data.get_debt();
+    let most = owed.min(user_data.get_creds().max(0));
+    let amount = amount.map_or(most, |a| a.min(i32::MAX as u32) as i32);
+
+    let (desc, color) = if owed == 0 {
+        ("You don't owe anything!".to_string(), data::EMBED_ERROR)
+    } else if user_data.get_creds() <= 0 {
+        (
+            format!(
+                "You have no creds to repay with, you still owe **{}**",
+                owed
+            ),
+            data::EMBED_ERROR,
+        )
+    } else if amount > owed {
+        (
+            format!("You only owe **{}** creds", owed),
+            data::EMBED_ERROR,
+        )
+    } else if !user_data.repay(amount) {
+        (
+            format!(
+                "You have **{}** creds, not enough to pay back **{}**",
+                user_data.get_creds(),
+                amount
+            ),
+            data::EMBED_ERROR,
+        )
+    } else {
+        ctx.data().saver.mark_dirty(guild_id, user_id);
+        let left = match user_data.get_debt() {
+            0 => "You are debt free!".to_string(),
+            left => format!("You still owe **{}**", left),
+        };
+        (
+            format!("You paid back **{}** creds. {}", amount, left),
+            data::EMBED_SUCCESS,
+        )
+    };
+    drop(user_data);
+
+    ctx.send(loan_embed("Repay", desc, color)).await?;
+    Ok(())
+}
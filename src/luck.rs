@@ -0,0 +1,127 @@
+//!---------------------------------------------------------------------!
+//! This file contains the luck stats, taken from every d20 rolled for  !
+//! /uwu and /claim_bonus                                               !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - luck                                                      !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - face_chart                                                !
+//!---------------------------------------------------------------------!
+
+use crate::data::{self, LuckStats};
+use crate::modifiers::Game;
+use crate::{serenity, Context, Error};
+
+// Width of the longest bar in the face chart
+const BAR_WIDTH: usize = 16;
+
+/// One line per d20 face with a bar for how often it came up
+pub fn face_chart(stats: &LuckStats) -> String {
+    let most = stats.faces.iter().copied().max().unwrap_or(0).max(1);
+    let mut chart = String::new();
+    for (i, count) in stats.faces.iter().enumerate() {
+        chart += format!(
+            "{:>2} {:<width$} {}\n",
+            i + 1,
+            "█".repeat(count * BAR_WIDTH / most),
+            count,
+            width = BAR_WIDTH
+        )
+        .as_str();
+    }
+    chart
+}
+
+/// see how lucky your daily and bonus d20s have been
+#[poise::command(slash_command, guild_only)]
+pub async fn luck(
+    ctx: Context<'_>,
+    #[description = "whose luck to see (default you)"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let user = user.as_ref().unwrap_or(ctx.author());
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let (rolls, legacy) = match guild.users.get(&user.id) {
+        Some(u) => {
+            let u = u.read().await;
+            (u.get_rolls().to_vec(), u.get_legacy_rolls().copied())
+        }
+        None => {
+            ctx.send(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::new()
+                        .title("Luck")
+                        .description(format!("<@{}> doesn't have an account yet", user.id))
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let stats = LuckStats::of(&rolls).with_legacy(legacy.as_ref());
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("{}'s Luck", user.name))
+        .thumbnail(user.avatar_url().unwrap_or_default())
+        .footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ));
+
+    let embed = if stats.rolls == 0 {
+        embed
+            .description("No d20s rolled yet, try **/uwu**")
+            .color(data::EMBED_ERROR)
+    } else {
+        // rolls from before the history were all dailies
+        let daily = LuckStats::of(rolls.iter().filter(|r| r.game == Game::Daily))
+            .with_legacy(legacy.as_ref());
+        let bonus = LuckStats::of(rolls.iter().filter(|r| r.game == Game::Bonus));
+        let config = ctx.data().config.get();
+        let mut notes = match config.luck.is_ranked(stats.rolls) {
+            false => format!(
+                "\n*{} more roll(s) until the fortune leaderboard*",
+                config.luck.min_rolls - stats.rolls
+            ),
+            true => String::new(),
+        };
+        if stats.legacy > 0 {
+            notes += &format!(
+                "\n*{} roll(s) are from before faces were kept, they only count in the average*",
+                stats.legacy
+            );
+        }
+
+        embed
+            .description(format!(
+                "Average Roll....... . . . **{:.2}** ({})\nRolls................. . . . **{}**\nNatural 20s......... . . . **{}**\nNatural 1s........... . . . **{}**\nHot Streak.......... . . . **{}** above 10\nCold Streak......... . . . **{}** at 10 or under\n{}",
+                stats.average,
+                stats.label(),
+                stats.rolls,
+                stats.nat20s,
+                stats.nat1s,
+                stats.hot_streak,
+                stats.cold_streak,
+                notes
+            ))
+            .field(
+                "Daily",
+                format!("**{}** rolls, **{:.2}** average", daily.rolls, daily.average),
+                true,
+            )
+            .field(
+                "Bonus",
+                format!("**{}** rolls, **{:.2}** average", bonus.rolls, bonus.average),
+                true,
+            )
+            .field("Faces", format!("```\n{}```", face_chart(&stats)), false)
+            .color(data::EMBED_CYAN)
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -1,20 +1,13 @@
-mod basic;
-mod clips;
-mod data;
-mod gpt;
-mod helper;
-mod mods;
-mod reminder;
-
 use dashmap::DashMap;
-use data::{UserData, VoiceUser};
+use professor_rs::config::ConfigHandle;
+use professor_rs::data::{self, GuildData, VoiceUser};
+use professor_rs::saver::SaveCoordinator;
+use professor_rs::{
+    assets, auctions, basic, clips, dice, economy, gpt, loans, luck, mods, raffle, rentals,
+    seasons, shop, streaks, wishes,
+};
+use professor_rs::{serenity, Context, Error};
 use std::{env, sync::Arc};
-use tokio::sync::RwLock;
-
-pub use poise::serenity_prelude as serenity;
-
-type Error = Box<dyn std::error::Error + Send + Sync>;
-type Context<'a> = poise::Context<'a, data::Data, Error>;
 
 #[poise::command(prefix_command)]
 async fn register(ctx: Context<'_>) -> Result<(), Error> {
@@ -27,6 +20,8 @@ async fn main() {
     dotenv::dotenv().expect("Failed to read .env file");
     let token = env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
     let data = data::Data::load();
+    let saver = Arc::clone(&data.saver);
+    Arc::clone(&saver).spawn();
 
     let intents = serenity::GatewayIntents::GUILD_MESSAGES
         | serenity::GatewayIntents::DIRECT_MESSAGES
@@ -43,10 +38,12 @@ async fn main() {
                     data::Data::check_or_create_user(ctx).await.unwrap();
                 })
             },
-            // Save all data after running a command
+            // Flag the author for the next background save after running a command
             post_command: |ctx: Context<'_>| {
                 Box::pin(async move {
-                    ctx.data().save().await;
+                    if let Some(guild_id) = ctx.guild_id() {
+                        ctx.data().saver.mark_dirty(guild_id, ctx.author().id);
+                    }
                 })
             },
             commands: vec![
@@ -54,17 +51,50 @@ async fn main() {
                 basic::ping(),
                 basic::uwu(),
                 basic::wallet(),
+                basic::history(),
                 basic::claim_bonus(),
                 basic::voice_status(),
                 basic::info(),
                 basic::leaderboard(),
                 basic::buy_tickets(),
+                basic::pay(),
+                streaks::buy_freeze(),
+                luck::luck(),
+                dice::roll(),
+                loans::loan(),
+                loans::repay(),
                 clips::submit_clip(),
                 clips::server_clips(),
                 clips::my_clips(),
                 clips::next_clip(),
                 mods::give_creds(),
                 mods::take_creds(),
+                mods::give_wishes(),
+                mods::refund_tickets(),
+                mods::configure(),
+                mods::reload_config(),
+                economy::economy(),
+                raffle::raffle_open(),
+                raffle::raffle_close(),
+                raffle::raffle_draw(),
+                raffle::raffle_history(),
+                raffle::raffle_verify(),
+                seasons::season_end(),
+                wishes::buy_wishes(),
+                wishes::wish(),
+                wishes::collection(),
+                shop::shop(),
+                shop::inventory(),
+                shop::shop_add(),
+                shop::shop_remove(),
+                rentals::rentals(),
+                auctions::auction_item(),
+                auctions::auction_tickets(),
+                auctions::bid(),
+                auctions::auctions(),
+                assets::assets(),
+                assets::asset_set(),
+                assets::asset_add(),
             ],
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("~".into()),
@@ -75,11 +105,15 @@ async fn main() {
             },
             ..Default::default()
         })
-        .setup(|_ctx, _ready, _framework| {
+        .setup(|ctx, _ready, _framework| {
             Box::pin(async move {
-                let users = data.users.clone();
+                let guilds = data.guilds.clone();
                 let voice_users = data.voice_users.clone();
-                background_task(users, voice_users);
+                let saver = data.saver.clone();
+                let config = data.config.clone();
+                rentals::spawn(guilds.clone(), saver.clone(), ctx.http.clone());
+                auctions::spawn(guilds.clone(), saver.clone(), ctx.http.clone());
+                background_task(guilds, voice_users, saver, config);
                 Ok(data)
             })
         })
@@ -96,7 +130,34 @@ async fn main() {
         .framework(framework)
         .await;
 
-    client.unwrap().start().await.unwrap();
+    let mut client = client.unwrap();
+
+    // Stop the shards on ctrl-c or SIGTERM so the final flush below runs
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        println!("Shutting down...");
+        shard_manager.shutdown_all().await;
+    });
+
+    client.start().await.unwrap();
+    saver.flush().await;
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
 
 async fn event_handler(
@@ -105,23 +166,22 @@ async fn event_handler(
     _framework: poise::FrameworkContext<'_, data::Data, Error>,
     data: &data::Data,
 ) -> Result<(), Error> {
-    let gen_chat = env::var("GENERAL").expect("Failed to load GENERAL channel id");
-    let bot_chat = env::var("BOT_CMD").expect("Failed to load BOT_CMD channel id");
-    let sub_chat = env::var("SUBMIT").expect("Failed to load SUBMIT channel id");
-    let prof_bid = env::var("PROFESSOR").expect("Failed to load PROFESSOR bot id");
-
     match event {
         serenity::FullEvent::Ready { data_about_bot, .. } => {
             println!("Logged in as {}\n\n", data_about_bot.user.name);
         }
 
         serenity::FullEvent::Message { new_message } => {
-            if new_message.author.id.to_string() == prof_bid {
+            if new_message.author.id == ctx.cache.current_user().id {
                 return Ok(());
             }
 
-            let channel_id = new_message.channel_id.get().to_string();
-            if channel_id != gen_chat && channel_id != bot_chat && channel_id != sub_chat {
+            let guild_id = match new_message.guild_id {
+                Some(guild_id) => guild_id,
+                None => return Ok(()),
+            };
+            let config = data.guild(guild_id).config();
+            if !config.chat_channels().contains(&new_message.channel_id) {
                 return Ok(());
             }
 
@@ -146,27 +206,58 @@ async fn event_handler(
             let randomstyle = messages[0].to_lowercase().contains("style");
 
             if do_gpt && doodle {
-                let doodle_url: String = gpt::generate_doodle(&mut messages, randomstyle).await;
-                new_message.reply(&ctx.http, doodle_url).await?;
+                let doodle =
+                    gpt::generate_doodle(&mut messages, randomstyle, &data.config.get().gpt).await;
+                match doodle {
+                    Some(doodle_url) => {
+                        new_message.reply(&ctx.http, doodle_url).await?;
+                    }
+                    None => {
+                        // links unfurl on their own, local files get uploaded instead
+                        let failed = data.assets.get("doodle.failed");
+                        let reply = serenity::CreateMessage::new().reference_message(new_message);
+                        let reply = match (failed.is_set(), failed.link()) {
+                            (false, _) => reply.content("The doodle didn't work out, try again"),
+                            (true, Some(link)) => reply.content(link),
+                            (true, None) => assets::attach(reply, &[&failed]).await,
+                        };
+                        new_message
+                            .channel_id
+                            .send_message(&ctx.http, reply)
+                            .await?;
+                    }
+                }
             }
 
             if do_gpt && !doodle {
-                let reading: String = gpt::generate_text(&mut messages).await;
+                let reading: String =
+                    gpt::generate_text(&mut messages, &data.config.get().gpt).await;
                 new_message.reply(&ctx.http, reading).await?;
             }
         }
 
+        // bid buttons outlive the command that posted them, so they are handled here
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(component),
+        } if component.data.custom_id.starts_with(auctions::BID_BUTTON) => {
+            auctions::bid_button(ctx, component, data).await?;
+        }
+
         serenity::FullEvent::VoiceStateUpdate { old: _, new } => {
             let voice_users = &data.voice_users;
+            let guild_id = match new.guild_id {
+                Some(guild_id) => guild_id,
+                None => return Ok(()),
+            };
 
             // Someone left the channel
             if new.channel_id.is_none() {
-                voice_users.remove(&new.user_id);
+                voice_users.remove(&(guild_id, new.user_id));
                 return Ok(());
             }
 
             let mut user = voice_users
-                .entry(new.user_id)
+                .entry((guild_id, new.user_id))
                 .or_insert(data::VoiceUser::new());
             user.update_mute(new.self_mute || new.mute);
             user.update_deaf(new.self_deaf || new.deaf);
@@ -177,48 +268,53 @@ async fn event_handler(
 }
 
 fn background_task(
-    users: Arc<DashMap<serenity::UserId, Arc<RwLock<UserData>>>>,
-    voice_users: Arc<DashMap<serenity::UserId, VoiceUser>>,
+    guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
+    voice_users: Arc<DashMap<(serenity::GuildId, serenity::UserId), VoiceUser>>,
+    saver: Arc<SaveCoordinator>,
+    config: ConfigHandle,
 ) {
     tokio::spawn(async move {
         loop {
             {
-                // How long should someone be in voice for creds
-                const CRED_TIME: i64 = 30;
-                // How much creds to award
-                const REWARD_CREDITS: i32 = 50;
-                // How much xp to award
-                const REWARD_XP: i32 = 30;
+                // How long someone should be in voice for creds, and how much they get
+                let voice = config.get().voice.clone();
+                let debt = config.get().debt.clone();
+                let cred_time = voice.reward_minutes;
 
                 // Check time
                 let now = chrono::Utc::now();
 
                 for mut x in voice_users.iter_mut() {
-                    let (id, vu) = x.pair_mut();
+                    let (&(guild_id, id), vu) = x.pair_mut();
                     let joined = vu.joined;
 
-                    let user_data = users.get_mut(id);
-                    if user_data.is_none() {
-                        return;
-                    }
-                    let user_data = user_data.unwrap();
+                    let guild = match guilds.get(&guild_id) {
+                        Some(guild) => Arc::clone(&guild),
+                        None => continue,
+                    };
+                    let user_data = match guild.users.get(&id) {
+                        Some(user_data) => Arc::clone(&user_data),
+                        None => continue,
+                    };
 
                     if let Some(last) = vu.last_reward {
-                        if (now - last).num_minutes() >= CRED_TIME {
+                        if (now - last).num_minutes() >= cred_time {
                             // Give user credits
                             let mut user_data = user_data.write().await;
-                            user_data.add_creds(REWARD_CREDITS);
-                            user_data.update_xp(REWARD_XP);
+                            user_data.earn(voice.reward_creds, data::CredReason::Voice, &debt);
+                            user_data.update_xp(voice.reward_xp);
                             vu.last_reward = Some(now);
+                            saver.mark_dirty(guild_id, id);
                         }
                     }
 
-                    if (now - joined).num_minutes() >= CRED_TIME {
+                    if (now - joined).num_minutes() >= cred_time {
                         // Give user credits
                         let mut user_data = user_data.write().await;
-                        user_data.add_creds(REWARD_CREDITS);
-                        user_data.update_xp(REWARD_XP);
+                        user_data.earn(voice.reward_creds, data::CredReason::Voice, &debt);
+                        user_data.update_xp(voice.reward_xp);
                         vu.last_reward = Some(now);
+                        saver.mark_dirty(guild_id, id);
                     }
                 }
             }
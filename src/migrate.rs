@@ -0,0 +1,397 @@
+//!---------------------------------------------------------------------!
+//! This file contains the migrations that upgrade older save files     !
+//! to the current SaveData format, one version at a time               !
+//!                                                                     !
+//! Migrations:                                                         !
+//!     [x] - v0 -> v1 (add version, fill missing profile fields)       !
+//!     [x] - v1 -> v2 (move users under their guild, seed its config)  !
+//!     [x] - v2 -> v3 (negative balances become debt)                  !
+//!     [x] - v3 -> v4 (roll sums carried into the luck history)        !
+//!     [x] - v4 -> v5 (pay tax split into its own ledger entry)        !
+//!---------------------------------------------------------------------!
+
+use crate::data::SaveData;
+use serde_json::{json, Map, Value};
+use std::env;
+
+/// Version written by this build, bump it together with a new migration
+pub const CURRENT_VERSION: u32 = 5;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// MIGRATIONS[n] upgrades a version n save to version n + 1
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] =
+    [v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5];
+
+/// version of a raw save, files from before versioning have none and count as 0
+pub fn version_of(save: &Value) -> u32 {
+    save.get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Runs every migration needed to bring `save` up to date and parses it
+pub fn upgrade(mut save: Value) -> Result<SaveData, String> {
+    let mut version = version_of(&save);
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "save is version {} but this build only knows up to {}",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    while version < CURRENT_VERSION {
+        save = MIGRATIONS[version as usize](save)
+            .map_err(|e| format!("migration from v{} failed: {}", version, e))?;
+        version += 1;
+        save["version"] = json!(version);
+    }
+
+    serde_json::from_value(save).map_err(|e| e.to_string())
+}
+
+fn users_mut(save: &mut Value) -> Result<&mut Map<String, Value>, String> {
+    save.get_mut("users")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "missing users map".to_string())
+}
+
+/// v0: unversioned, early profiles can be missing the bonus, submits and ticket fields
+fn v0_to_v1(mut save: Value) -> Result<Value, String> {
+    let defaults = json!({
+        "level": 0,
+        "xp": 0,
+        "creds": 0,
+        "rolls": 0,
+        "daily_count": 0,
+        "bonus_count": 0,
+        "last_daily": "1970-01-01T00:00:00Z",
+        "submits": [],
+        "tickets": 0,
+    });
+    let defaults = defaults.as_object().unwrap();
+
+    for (id, user) in users_mut(&mut save)?.iter_mut() {
+        let user = user
+            .as_object_mut()
+            .ok_or_else(|| format!("user {} is not an object", id))?;
+        for (field, value) in defaults {
+            if user.get(field).is_none_or(Value::is_null) {
+                user.insert(field.clone(), value.clone());
+            }
+        }
+    }
+    Ok(save)
+}
+
+/// v1: a single guild's users at the top level, channels and roles came from env vars
+fn v1_to_v2(mut save: Value) -> Result<Value, String> {
+    let users = std::mem::take(users_mut(&mut save)?);
+
+    let guild_id = match env::var("GUILD_ID")
+        .ok()
+        .and_then(|id| id.parse::<u64>().ok())
+    {
+        Some(id) => id,
+        None if users.is_empty() => {
+            save.as_object_mut().unwrap().remove("users");
+            save["guilds"] = json!({});
+            return Ok(save);
+        }
+        None => return Err("GUILD_ID must be set to know which guild the users belong to".into()),
+    };
+
+    let mut config = Map::new();
+    for (field, var) in [
+        ("general", "GENERAL"),
+        ("bot_cmd", "BOT_CMD"),
+        ("submit", "SUBMIT"),
+        ("mod_chat", "MOD_CHAT"),
+        ("mod_role", "MOD_ID"),
+    ] {
+        if let Some(id) = env::var(var).ok().and_then(|id| id.parse::<u64>().ok()) {
+            config.insert(field.to_string(), json!(id.to_string()));
+        }
+    }
+
+    save.as_object_mut().unwrap().remove("users");
+    save["guilds"] = json!({ guild_id.to_string(): { "config": config, "users": users } });
+    Ok(save)
+}
+
+/// v2: losses could leave balances below zero, that is now owed as debt instead
+fn v2_to_v3(mut save: Value) -> Result<Value, String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let guilds = save
+        .get_mut("guilds")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "missing guilds map".to_string())?;
+
+    for (guild_id, guild) in guilds.iter_mut() {
+        let users = guild
+            .get_mut("users")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| format!("guild {} is missing its users", guild_id))?;
+        for (id, user) in users.iter_mut() {
+            let creds = user["creds"]
+                .as_i64()
+                .ok_or_else(|| format!("user {} has no creds", id))?;
+            if creds >= 0 {
+                continue;
+            }
+
+            user["creds"] = json!(0);
+            user["debt"] = json!(user["debt"].as_i64().unwrap_or(0) - creds);
+            // keep the ledger adding up to the balance
+            let entry = json!({
+                "amount": -creds,
+                "reason": { "kind": "MovedToDebt" },
+                "actor": null,
+                "date": now,
+                "balance": 0,
+            });
+            match user["ledger"].as_array_mut() {
+                Some(ledger) => ledger.push(entry),
+                None => user["ledger"] = json!([entry]),
+            }
+        }
+    }
+    Ok(save)
+}
+
+/// v3: luck was a running sum of daily d20s, keep it as the seed of the roll
+/// history. Season records from then had no roll count, every daily was one
+fn v3_to_v4(mut save: Value) -> Result<Value, String> {
+    let guilds = save
+        .get_mut("guilds")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "missing guilds map".to_string())?;
+
+    for (guild_id, guild) in guilds.iter_mut() {
+        let users = guild
+            .get_mut("users")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| format!("guild {} is missing its users", guild_id))?;
+        for (id, user) in users.iter_mut() {
+            let user = user
+                .as_object_mut()
+                .ok_or_else(|| format!("user {} is not an object", id))?;
+
+            let sum = user.remove("rolls").and_then(|r| r.as_i64()).unwrap_or(0);
+            let count = user["daily_count"].as_i64().unwrap_or(0);
+            if sum > 0 && count > 0 {
+                user.insert(
+                    "legacy_rolls".to_string(),
+                    json!({ "sum": sum, "count": count }),
+                );
+            }
+
+            let Some(seasons) = user.get_mut("seasons").and_then(Value::as_array_mut) else {
+                continue;
+            };
+            for record in seasons {
+                if record.get("rolls").is_none() {
+                    record["rolls"] = json!(record["daily_count"].as_i64().unwrap_or(0).max(0));
+                }
+            }
+        }
+    }
+    Ok(save)
+}
+
+/// v4: a payment's tax was part of its PaySent amount, now it is a PayTax entry of its own
+fn v4_to_v5(mut save: Value) -> Result<Value, String> {
+    let guilds = save
+        .get_mut("guilds")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "missing guilds map".to_string())?;
+
+    for (guild_id, guild) in guilds.iter_mut() {
+        let users = guild
+            .get_mut("users")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| format!("guild {} is missing its users", guild_id))?;
+        for (_, user) in users.iter_mut() {
+            let Some(ledger) = user.get_mut("ledger").and_then(Value::as_array_mut) else {
+                continue;
+            };
+
+            let mut split = Vec::with_capacity(ledger.len());
+            for mut entry in ledger.drain(..) {
+                let tax = match entry["reason"]["kind"].as_str() {
+                    Some("PaySent") => entry["reason"]["tax"].as_i64().unwrap_or(0),
+                    _ => 0,
+                };
+                if tax <= 0 {
+                    split.push(entry);
+                    continue;
+                }
+
+                // the balance after the tax is the one the old entry had
+                let mut paid_tax = entry.clone();
+                paid_tax["amount"] = json!(-tax);
+                paid_tax["reason"] = json!({ "kind": "PayTax", "to": entry["reason"]["to"] });
+                entry["amount"] = json!(entry["amount"].as_i64().unwrap_or(0) + tax);
+                entry["balance"] = json!(entry["balance"].as_i64().unwrap_or(0) + tax);
+                split.push(entry);
+                split.push(paid_tax);
+            }
+            *ledger = split;
+        }
+    }
+    Ok(save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poise::serenity_prelude::{GuildId, UserId};
+
+    const GUILD: u64 = 1194668798830194850;
+
+    fn load_fixture(fixture: &str) -> SaveData {
+        env::set_var("GUILD_ID", GUILD.to_string());
+        upgrade(serde_json::from_str(fixture).unwrap()).unwrap()
+    }
+
+    fn users(save: &SaveData) -> dashmap::mapref::one::Ref<'_, GuildId, crate::data::GuildSave> {
+        save.guilds.get(&GuildId::new(GUILD)).unwrap()
+    }
+
+    #[test]
+    fn upgrades_unversioned_save() {
+        let save = load_fixture(include_str!("../tests/fixtures/save_v0.json"));
+        assert_eq!(save.version, CURRENT_VERSION);
+
+        let guild = users(&save);
+        let user = guild.users.get(&UserId::new(204362014128111616)).unwrap();
+        assert_eq!(user.get_creds(), 12450);
+        assert_eq!(user.get_tickets(), 3);
+        assert_eq!(user.get_level(), 7);
+        assert_eq!(user.submits.len(), 2);
+    }
+
+    #[test]
+    fn upgrades_profiles_missing_fields() {
+        let save = load_fixture(include_str!("../tests/fixtures/save_v0_early.json"));
+
+        let guild = users(&save);
+        let user = guild.users.get(&UserId::new(318491258230849536)).unwrap();
+        assert_eq!(user.get_creds(), 800);
+        assert_eq!(user.get_tickets(), 0);
+        assert_eq!(user.get_bonus(), 0);
+        assert!(user.submits.is_empty());
+    }
+
+    #[test]
+    fn current_save_round_trips() {
+        let save = load_fixture(include_str!("../tests/fixtures/save_v0.json"));
+        let encoded = serde_json::to_value(&save).unwrap();
+        assert_eq!(version_of(&encoded), CURRENT_VERSION);
+
+        let again = upgrade(encoded).unwrap();
+        assert_eq!(again.user_count(), save.user_count());
+    }
+
+    #[test]
+    fn moves_negative_balances_to_debt() {
+        let save = json!({
+            "version": 2,
+            "guilds": { GUILD.to_string(): { "users": {
+                "1": { "level": 0, "xp": 0, "creds": -350, "rolls": 0, "daily_count": 0,
+                       "bonus_count": 0, "last_daily": "1970-01-01T00:00:00Z",
+                       "submits": [], "tickets": 0 },
+                "2": { "level": 0, "xp": 0, "creds": 90, "rolls": 0, "daily_count": 0,
+                       "bonus_count": 0, "last_daily": "1970-01-01T00:00:00Z",
+                       "submits": [], "tickets": 0 },
+            }}},
+        });
+        let save = upgrade(save).unwrap();
+
+        let guild = users(&save);
+        let broke = guild.users.get(&UserId::new(1)).unwrap();
+        assert_eq!(broke.get_creds(), 0);
+        assert_eq!(broke.get_debt(), 350);
+        assert_eq!(broke.get_ledger().last().unwrap().amount, 350);
+
+        let fine = guild.users.get(&UserId::new(2)).unwrap();
+        assert_eq!(fine.get_creds(), 90);
+        assert_eq!(fine.get_debt(), 0);
+        assert!(fine.get_ledger().is_empty());
+    }
+
+    #[test]
+    fn carries_roll_sums_into_luck() {
+        let record = json!({ "season": 1, "ended": "2024-01-01T00:00:00Z", "creds": 10,
+                             "level": 1, "xp": 0, "luck": "Average", "luck_score": 10,
+                             "daily_count": 12, "tickets": 0, "rank": 1, "badge": null });
+        let save = json!({
+            "version": 3,
+            "guilds": { GUILD.to_string(): { "users": {
+                "1": { "level": 0, "xp": 0, "creds": 0, "rolls": 130, "daily_count": 10,
+                       "bonus_count": 0, "last_daily": "1970-01-01T00:00:00Z",
+                       "submits": [], "tickets": 0, "seasons": [record] },
+                "2": { "level": 0, "xp": 0, "creds": 0, "rolls": 0, "daily_count": 0,
+                       "bonus_count": 0, "last_daily": "1970-01-01T00:00:00Z",
+                       "submits": [], "tickets": 0 },
+            }}},
+        });
+        let save = upgrade(save).unwrap();
+
+        let guild = users(&save);
+        let rolled = guild.users.get(&UserId::new(1)).unwrap();
+        let stats = rolled.get_luck_stats();
+        assert_eq!(stats.rolls, 10);
+        assert_eq!(stats.average, 13.0);
+        assert_eq!(rolled.get_seasons()[0].rolls, 12);
+
+        let fresh = guild.users.get(&UserId::new(2)).unwrap();
+        assert_eq!(fresh.get_luck_stats().rolls, 0);
+    }
+
+    #[test]
+    fn splits_pay_tax_out_of_payments() {
+        let entry = |amount: i32, reason: Value, balance: i32| {
+            json!({ "amount": amount, "reason": reason, "actor": null,
+                    "date": "2024-01-01T00:00:00Z", "balance": balance })
+        };
+        let save = json!({
+            "version": 4,
+            "guilds": { GUILD.to_string(): { "users": {
+                "1": { "level": 0, "xp": 0, "creds": 800, "daily_count": 0,
+                       "bonus_count": 0, "last_daily": "1970-01-01T00:00:00Z",
+                       "submits": [], "tickets": 0, "ledger": [
+                           entry(1000, json!({ "kind": "Voice" }), 1000),
+                           entry(-100, json!({ "kind": "PaySent", "to": "2", "tax": 10 }), 900),
+                           entry(-100, json!({ "kind": "PaySent", "to": "2", "tax": 0 }), 800),
+                       ] },
+            }}},
+        });
+        let save = upgrade(save).unwrap();
+
+        let guild = users(&save);
+        let user = guild.users.get(&UserId::new(1)).unwrap();
+        let ledger: Vec<(i32, i32)> = user
+            .get_ledger()
+            .iter()
+            .map(|e| (e.amount, e.balance))
+            .collect();
+        assert_eq!(
+            ledger,
+            vec![(1000, 1000), (-90, 910), (-10, 900), (-100, 800)]
+        );
+        assert_eq!(
+            user.get_ledger()[2].reason,
+            crate::data::CredReason::PayTax { to: UserId::new(2) }
+        );
+        // the tax still counts toward what was sent
+        assert_eq!(user.paid_since(chrono::DateTime::UNIX_EPOCH), 200);
+    }
+
+    #[test]
+    fn rejects_newer_saves() {
+        let save = json!({ "version": CURRENT_VERSION + 1, "users": {} });
+        assert!(upgrade(save).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//!---------------------------------------------------------------------!
+//! This file contains the roll modifiers, named bonuses from levels,   !
+//! streaks, items, roles and events added to /uwu and /claim_bonus     !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - gather                                                    !
+//!     [x] - roll                                                      !
+//!     [x] - payout                                                    !
+//!     [x] - breakdown                                                 !
+//!---------------------------------------------------------------------!
+
+use crate::config::Config;
+use crate::data::UserData;
+use chrono::Utc;
+use poise::serenity_prelude::RoleId;
+use serde::{Deserialize, Serialize};
+
+/// Which roll the modifiers are for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Game {
+    Daily,
+    Bonus,
+}
+
+/// One named bonus, `roll` is added to the d20 and `payout_percent` to the creds won
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modifier {
+    pub source: String,
+    pub roll: i32,
+    pub payout_percent: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+impl Modifiers {
+    fn push(&mut self, source: String, roll: i32, payout_percent: i32) {
+        if roll != 0 || payout_percent != 0 {
+            self.0.push(Modifier {
+                source,
+                roll,
+                payout_percent,
+            });
+        }
+    }
+
+    /// Everything added to the d20
+    pub fn roll(&self) -> i32 {
+        self.0.iter().map(|m| m.roll).sum()
+    }
+
+    /// Creds won after the payout bonuses, never below nothing
+    pub fn payout(&self, creds: i32) -> i32 {
+        let percent: i64 = self.0.iter().map(|m| m.payout_percent as i64).sum();
+        let creds = creds as i64 * (100 + percent).max(0) / 100;
+        creds.min(i32::MAX as i64) as i32
+    }
+
+    /// One line per modifier for the result embed
+    pub fn breakdown(&self) -> String {
+        if self.0.is_empty() {
+            return "None".to_string();
+        }
+
+        let mut text = String::new();
+        for m in &self.0 {
+            let mut parts = Vec::new();
+            if m.roll != 0 {
+                parts.push(format!("**{:+}** roll", m.roll));
+            }
+            if m.payout_percent != 0 {
+                parts.push(format!("**{:+}%** creds", m.payout_percent));
+            }
+            text += format!("{}: {}\n", m.source, parts.join(", ")).as_str();
+        }
+        text
+    }
+}
+
+/// Every modifier that applies to `user`, `streak` is the streak the roll counts towards
+pub fn gather(
+    game: Game,
+    user: &UserData,
+    streak: u32,
+    roles: &[RoleId],
+    config: &Config,
+) -> Modifiers {
+    let settings = &config.modifiers;
+    let mut modifiers = Modifiers::default();
+
+    if game == Game::Bonus {
+        modifiers.push("Proficiency".to_string(), settings.bonus_proficiency, 0);
+    }
+    if settings.level_step > 0 {
+        modifiers.push(
+            format!("Level {}", user.get_level()),
+            user.get_level() / settings.level_step,
+            0,
+        );
+    }
+    if game == Game::Daily {
+        modifiers.push(
+            format!("{} day streak", streak),
+            0,
+            config.streak_percent(streak),
+        );
+    }
+
+    for item in settings.items.iter().filter(|i| user.owns(&i.item)) {
+        modifiers.push(item.item.clone(), item.roll, item.payout_percent);
+    }
+    for role in settings.roles.iter().filter(|r| roles.contains(&r.role)) {
+        modifiers.push(role.name.clone(), role.roll, role.payout_percent);
+    }
+
+    let now = Utc::now();
+    for event in settings
+        .events
+        .iter()
+        .filter(|e| e.start <= now && now < e.end)
+    {
+        modifiers.push(event.name.clone(), event.roll, event.payout_percent);
+    }
+    modifiers
+}
@@ -5,10 +5,13 @@
 //! Commands:                                                           !
 //!     [x] - give_creds                                                !
 //!     [x] - take_creds                                                !
-//!     [ ] - give_wishes                                               !
-//!     [ ] - refund_tickets                                            !
+//!     [x] - give_wishes                                               !
+//!     [x] - refund_tickets                                            !
+//!     [x] - configure                                                 !
+//!     [x] - reload_config                                             !
 //!---------------------------------------------------------------------!
 
+use crate::assets::{self, ShowAsset};
 use crate::clips::check_mod;
 use crate::data;
 use crate::helper::parse_user_mention;
@@ -20,20 +23,26 @@ use poise::serenity_prelude::UserId;
 pub async fn give_creds(
     ctx: Context<'_>,
     #[description = "@username | example: @UwUntu @Rustybamboo"] mentioned: String,
-    #[description = "amount of creds to give (max: 10000)"] give_amount: u32,
+    #[description = "amount of creds to give"] give_amount: u32,
 ) -> Result<(), Error> {
-    if give_amount > 10000 {
+    let max_cred_change = ctx.data().config.get().mods.max_cred_change;
+    if give_amount > max_cred_change {
+        let over_limit = ctx.data().assets.get("mod.over_limit");
         ctx.send(
-            poise::CreateReply::default().embed(
-                serenity::CreateEmbed::new()
-                    .title("Give Creds")
-                    .description("The max amount allowed is 10000.")
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205685838877433866/tenor_2.gif?ex=65d94570&is=65c6d070&hm=be06433cb7dd2c592468560dfffbc5ce6c294582db38f177028ba80a46f67a43&")
-                    .color(data::EMBED_ERROR)
-                    .footer(serenity::CreateEmbedFooter::new(
-                        "@~ powered by UwUntu & RustyBamboo",
-                    )),
-            ),
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::new()
+                        .title("Give Creds")
+                        .description(format!("The max amount allowed is {}.", max_cred_change))
+                        .asset_image(&over_limit)
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&over_limit],
+            )
+            .await,
         )
         .await?;
         return Ok(());
@@ -53,7 +62,8 @@ pub async fn give_creds(
         }
     }
 
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
     let mentioned_list: Vec<&str> = mentioned.split(' ').collect();
     let mentioned_size = mentioned_list.len();
 
@@ -72,16 +82,19 @@ pub async fn give_creds(
             ctx.send(poise::CreateReply::default().content(format!("<@{}>", user_id)))
                 .await?;
 
+            let welcome = ctx.data().assets.get("account.created");
             ctx.send(
-                poise::CreateReply::default().embed(
-                    serenity::CreateEmbed::new()
-                        .title("Account Created!")
-                        .description(format!("Welcome <@{}>! You are now registered with ProfessorBot, feel free to checkout Professors Commands in https://discord.com/channels/1194668798830194850/1194700756306108437", user_id))
-                        .image(
-                            "https://gifdb.com/images/high/anime-girl-okay-sign-b5zlye5h8mnjhdg2.gif",
-                        )
-                        .color(data::EMBED_DEFAULT),
-                ),
+                assets::attach(
+                    poise::CreateReply::default().embed(
+                        serenity::CreateEmbed::new()
+                            .title("Account Created!")
+                            .description(data::welcome_text(user_id, &guild.config()))
+                            .asset_image(&welcome)
+                            .color(data::EMBED_DEFAULT),
+                    ),
+                    &[&welcome],
+                )
+                .await,
             )
             .await?;
         }
@@ -89,7 +102,15 @@ pub async fn give_creds(
         let u = data.get(&user_id).unwrap();
         let mut user_data = u.write().await;
 
-        user_data.add_creds(give_amount as i32);
+        user_data.add_creds(
+            give_amount as i32,
+            data::CredReason::ModGive {
+                by: ctx.author().id,
+            },
+        );
+        ctx.data()
+            .saver
+            .mark_dirty(ctx.guild_id().unwrap(), user_id);
         processed_list.push(parsed_id);
     }
 
@@ -111,19 +132,22 @@ pub async fn give_creds(
         }
     }
 
+    let give_image = ctx.data().assets.get("mod.give");
     ctx.send(
-        poise::CreateReply::default()
-        .content(pre_text)
-        .embed(
-            serenity::CreateEmbed::new()
-                .title("Give Creds")
-                .description(desc)
-                .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205685388157653022/zVdLFbp.gif?ex=65d94505&is=65c6d005&hm=690faecbed4018602cc94a5f7a9db1ff6527d4202a71ba80f27d912d36de3c7e&")
-                .color(data::EMBED_MOD)
-                .footer(serenity::CreateEmbedFooter::new(
-                    "@~ powered by UwUntu & RustyBamboo",
-                )),
-        ),
+        assets::attach(
+            poise::CreateReply::default().content(pre_text).embed(
+                serenity::CreateEmbed::new()
+                    .title("Give Creds")
+                    .description(desc)
+                    .asset_image(&give_image)
+                    .color(data::EMBED_MOD)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "@~ powered by UwUntu & RustyBamboo",
+                    )),
+            ),
+            &[&give_image],
+        )
+        .await,
     )
     .await?;
 
@@ -143,25 +167,167 @@ pub async fn give_creds(
 pub async fn take_creds(
     ctx: Context<'_>,
     #[description = "@username | example: @UwUntu @Rustybamboo"] mentioned: String,
-    #[description = "amount of creds to take (max: 10000)"] take_amount: u32,
+    #[description = "amount of creds to take"] take_amount: u32,
 ) -> Result<(), Error> {
-    if take_amount > 10000 {
+    let max_cred_change = ctx.data().config.get().mods.max_cred_change;
+    if take_amount > max_cred_change {
+        let over_limit = ctx.data().assets.get("mod.over_limit");
         ctx.send(
-            poise::CreateReply::default().embed(
+            assets::attach(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::new()
+                        .title("Take Creds")
+                        .description(format!("The max amount allowed is {}.", max_cred_change))
+                        .asset_image(&over_limit)
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+                &[&over_limit],
+            )
+            .await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    //get list of userIDs that exist in server
+    let guild_members = ctx
+        .guild_id()
+        .unwrap()
+        .members(ctx.http(), None, None)
+        .await;
+
+    let mut guild_ids: Vec<UserId> = Vec::new();
+    for member in guild_members.iter() {
+        for profile in member {
+            guild_ids.push(profile.user.id);
+        }
+    }
+
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
+    let mentioned_list: Vec<&str> = mentioned.split(' ').collect();
+    let mentioned_size = mentioned_list.len();
+
+    let debt = ctx.data().config.get().debt.clone();
+    let mut in_debt: Vec<(u64, i32)> = Vec::new();
+    let mut processed_list: Vec<u64> = Vec::new();
+    for mentioned_user in mentioned_list {
+        let parsed_id: u64 = parse_user_mention(mentioned_user.to_string());
+        let user_id = UserId::from(parsed_id);
+
+        if !guild_ids.contains(&user_id) {
+            continue;
+        }
+
+        if !data.contains_key(&user_id) {
+            data.insert(user_id, Default::default());
+
+            let welcome = ctx.data().assets.get("account.created");
+            ctx.send(
+                assets::attach(
+                    poise::CreateReply::default()
+                        .content(format!("<@{}>", user_id))
+                        .embed(
+                            serenity::CreateEmbed::new()
+                                .title("Account Created!")
+                                .description(data::welcome_text(user_id, &guild.config()))
+                                .asset_image(&welcome)
+                                .color(data::EMBED_DEFAULT),
+                        ),
+                    &[&welcome],
+                )
+                .await,
+            )
+            .await?;
+        }
+
+        let u = data.get(&user_id).unwrap();
+        let mut user_data = u.write().await;
+
+        let owed = user_data.charge(
+            take_amount as i32,
+            data::CredReason::ModTake {
+                by: ctx.author().id,
+            },
+            &debt,
+        );
+        if owed > 0 {
+            in_debt.push((parsed_id, owed));
+        }
+        ctx.data()
+            .saver
+            .mark_dirty(ctx.guild_id().unwrap(), user_id);
+        processed_list.push(parsed_id);
+    }
+
+    let process_size = processed_list.len();
+    let mut pre_text = String::new();
+    let mut desc = String::new();
+    if processed_list.is_empty() {
+        desc += "No one lost creds...";
+    } else {
+        desc += format!(
+            "Moderator <@{}> took {} creds from ",
+            ctx.author().id,
+            take_amount
+        )
+        .as_str();
+        for id in processed_list {
+            pre_text += format!("<@{}> ", id).as_str();
+            desc += format!("<@{}> ", id).as_str();
+        }
+        for (id, owed) in in_debt {
+            desc += format!(
+                "\n<@{}> couldn't cover **{}** of it, that is now debt",
+                id, owed
+            )
+            .as_str();
+        }
+    }
+
+    let take_image = ctx.data().assets.get("mod.take");
+    ctx.send(
+        assets::attach(
+            poise::CreateReply::default().content(pre_text).embed(
                 serenity::CreateEmbed::new()
                     .title("Take Creds")
-                    .description("The max amount allowed is 10000.")
-                    .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205685838877433866/tenor_2.gif?ex=65d94570&is=65c6d070&hm=be06433cb7dd2c592468560dfffbc5ce6c294582db38f177028ba80a46f67a43&")
-                    .color(data::EMBED_ERROR)
+                    .description(desc)
+                    .asset_image(&take_image)
+                    .color(data::EMBED_MOD)
                     .footer(serenity::CreateEmbedFooter::new(
                         "@~ powered by UwUntu & RustyBamboo",
                     )),
             ),
+            &[&take_image],
         )
+        .await,
+    )
+    .await?;
+
+    if process_size != mentioned_size {
+        ctx.send(poise::CreateReply::default().content(format!(
+            "**(!) NOTE** <@{}>\n     **{}** @Mentions did not get processed... double check who did \n     not lose creds.\n",
+            ctx.author().id, mentioned_size - process_size
+        )))
         .await?;
-        return Ok(());
     }
 
+    Ok(())
+}
+
+/// [!] MODERATOR - reward a user with wishes
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn give_wishes(
+    ctx: Context<'_>,
+    #[description = "@username | example: @UwUntu @Rustybamboo"] mentioned: String,
+    #[description = "amount of wishes to give"]
+    #[min = 1]
+    #[max = 1000]
+    give_amount: u32,
+) -> Result<(), Error> {
     //get list of userIDs that exist in server
     let guild_members = ctx
         .guild_id()
@@ -176,7 +342,8 @@ pub async fn take_creds(
         }
     }
 
-    let data = &ctx.data().users;
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let data = &guild.users;
     let mentioned_list: Vec<&str> = mentioned.split(' ').collect();
     let mentioned_size = mentioned_list.len();
 
@@ -192,18 +359,21 @@ pub async fn take_creds(
         if !data.contains_key(&user_id) {
             data.insert(user_id, Default::default());
 
+            let welcome = ctx.data().assets.get("account.created");
             ctx.send(
-                poise::CreateReply::default()
-                .content(format!("<@{}>", user_id))
-                .embed(
-                    serenity::CreateEmbed::new()
-                        .title("Account Created!")
-                        .description(format!("Welcome <@{}>! You are now registered with ProfessorBot, feel free to checkout Professors Commands in https://discord.com/channels/1194668798830194850/1194700756306108437", user_id))
-                        .image(
-                            "https://gifdb.com/images/high/anime-girl-okay-sign-b5zlye5h8mnjhdg2.gif",
-                        )
-                        .color(data::EMBED_DEFAULT),
-                ),
+                assets::attach(
+                    poise::CreateReply::default()
+                        .content(format!("<@{}>", user_id))
+                        .embed(
+                            serenity::CreateEmbed::new()
+                                .title("Account Created!")
+                                .description(data::welcome_text(user_id, &guild.config()))
+                                .asset_image(&welcome)
+                                .color(data::EMBED_DEFAULT),
+                        ),
+                    &[&welcome],
+                )
+                .await,
             )
             .await?;
         }
@@ -211,7 +381,10 @@ pub async fn take_creds(
         let u = data.get(&user_id).unwrap();
         let mut user_data = u.write().await;
 
-        user_data.sub_creds(take_amount as i32);
+        user_data.add_wishes(give_amount as i32);
+        ctx.data()
+            .saver
+            .mark_dirty(ctx.guild_id().unwrap(), user_id);
         processed_list.push(parsed_id);
     }
 
@@ -219,12 +392,12 @@ pub async fn take_creds(
     let mut pre_text = String::new();
     let mut desc = String::new();
     if processed_list.is_empty() {
-        desc += "No one lost creds...";
+        desc += "No one got wishes...";
     } else {
         desc += format!(
-            "Moderator <@{}> took {} creds from ",
+            "Moderator <@{}> gave {} wish(es) to ",
             ctx.author().id,
-            take_amount
+            give_amount
         )
         .as_str();
         for id in processed_list {
@@ -234,13 +407,10 @@ pub async fn take_creds(
     }
 
     ctx.send(
-        poise::CreateReply::default()
-        .content(pre_text)
-        .embed(
+        poise::CreateReply::default().content(pre_text).embed(
             serenity::CreateEmbed::new()
-                .title("Take Creds")
+                .title("Give Wishes")
                 .description(desc)
-                .image("https://cdn.discordapp.com/attachments/1196582162057662484/1205689656268824596/7Z7b-ezgif.com-video-to-gif-converter.gif?ex=65d948fe&is=65c6d3fe&hm=d3ac81f31552010a87cb5bb894ebef6af6f2e3fc73c223abff1b19ab712c0ae8&")
                 .color(data::EMBED_MOD)
                 .footer(serenity::CreateEmbedFooter::new(
                     "@~ powered by UwUntu & RustyBamboo",
@@ -251,7 +421,121 @@ pub async fn take_creds(
 
     if process_size != mentioned_size {
         ctx.send(poise::CreateReply::default().content(format!(
-            "**(!) NOTE** <@{}>\n     **{}** @Mentions did not get processed... double check who did \n     not lose creds.\n",
+            "**(!) NOTE** <@{}>\n     **{}** @Mentions did not get processed... double check who did \n     not get wishes.\n",
+            ctx.author().id, mentioned_size - process_size
+        )))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// [!] MODERATOR - take back tickets and return the creds paid for them
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn refund_tickets(
+    ctx: Context<'_>,
+    #[description = "@username | example: @UwUntu @Rustybamboo"] mentioned: String,
+    #[description = "tickets to refund per user, newest first"]
+    #[min = 1]
+    tickets: u32,
+    #[description = "why the tickets are refunded"] reason: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let data = &guild.users;
+    let config = ctx.data().config.get();
+    let mentioned_list: Vec<&str> = mentioned.split(' ').collect();
+    let mentioned_size = mentioned_list.len();
+
+    // users without an account or tickets have nothing to refund
+    let mut processed_list: Vec<(u64, i32, i32)> = Vec::new();
+    for mentioned_user in mentioned_list {
+        let parsed_id: u64 = parse_user_mention(mentioned_user.to_string());
+        let user_id = UserId::from(parsed_id);
+
+        let u = match data.get(&user_id) {
+            Some(u) => u,
+            None => continue,
+        };
+        let mut user_data = u.write().await;
+
+        let owned = user_data.get_tickets();
+        let count = owned.min(tickets.min(i32::MAX as u32) as i32);
+        if count < 1 {
+            continue;
+        }
+
+        let refund = user_data.ticket_refund(count, &config);
+        user_data.remove_tickets(count);
+        user_data.add_creds(
+            refund,
+            data::CredReason::TicketRefund {
+                count,
+                by: ctx.author().id,
+                note: reason.clone(),
+            },
+        );
+        ctx.data().saver.mark_dirty(guild_id, user_id);
+        processed_list.push((parsed_id, count, refund));
+    }
+
+    // let everyone know where their tickets went, closed dms just miss out
+    for (id, count, refund) in &processed_list {
+        let dm = serenity::CreateMessage::new().embed(
+            serenity::CreateEmbed::new()
+                .title("Tickets Refunded")
+                .description(format!(
+                    "A moderator refunded {} of your ticket(s) for **{}** creds\n\nReason: *{}*",
+                    count, refund, reason
+                ))
+                .color(data::EMBED_MOD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        );
+        if let Err(e) = UserId::new(*id).direct_message(ctx, dm).await {
+            println!("Failed to tell {} about their refund: {}", id, e);
+        }
+    }
+
+    let process_size = processed_list.len();
+    let mut pre_text = String::new();
+    let mut desc = String::new();
+    if processed_list.is_empty() {
+        desc += "No one got a refund...";
+    } else {
+        desc += format!(
+            "Moderator <@{}> refunded tickets for: *{}*\n\n",
+            ctx.author().id,
+            reason
+        )
+        .as_str();
+        for (id, count, refund) in processed_list {
+            pre_text += format!("<@{}> ", id).as_str();
+            desc += format!(
+                "<@{}> \u{2192} {} ticket(s), **+{}** creds\n",
+                id, count, refund
+            )
+            .as_str();
+        }
+    }
+
+    ctx.send(
+        poise::CreateReply::default().content(pre_text).embed(
+            serenity::CreateEmbed::new()
+                .title("Refund Tickets")
+                .description(desc)
+                .color(data::EMBED_MOD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+
+    if process_size != mentioned_size {
+        ctx.send(poise::CreateReply::default().content(format!(
+            "**(!) NOTE** <@{}>\n     **{}** @Mentions did not get processed... they have no tickets \n     or no account.\n",
             ctx.author().id, mentioned_size - process_size
         )))
         .await?;
@@ -259,3 +543,116 @@ pub async fn take_creds(
 
     Ok(())
 }
+
+/// [!] ADMIN - set the channels and moderator role professorBot uses in this server
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn configure(
+    ctx: Context<'_>,
+    #[description = "general chat, birthday wishes go here"] general: Option<
+        serenity::GuildChannel,
+    >,
+    #[description = "bot commands channel"] bot_cmd: Option<serenity::GuildChannel>,
+    #[description = "clip submission channel"] submit: Option<serenity::GuildChannel>,
+    #[description = "moderator chat, reminders go here"] mod_chat: Option<serenity::GuildChannel>,
+    #[description = "moderator role"] mod_role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let mut config = guild.config();
+
+    let changed = general.is_some()
+        || bot_cmd.is_some()
+        || submit.is_some()
+        || mod_chat.is_some()
+        || mod_role.is_some();
+
+    if let Some(channel) = general {
+        config.general = Some(channel.id);
+    }
+    if let Some(channel) = bot_cmd {
+        config.bot_cmd = Some(channel.id);
+    }
+    if let Some(channel) = submit {
+        config.submit = Some(channel.id);
+    }
+    if let Some(channel) = mod_chat {
+        config.mod_chat = Some(channel.id);
+    }
+    if let Some(role) = mod_role {
+        config.mod_role = Some(role.id);
+    }
+
+    if changed {
+        guild.set_config(config.clone());
+        ctx.data().saver.mark_guild_dirty(guild_id);
+    }
+
+    let channel = |c: Option<serenity::ChannelId>| match c {
+        Some(c) => format!("<#{}>", c),
+        None => "not set".to_string(),
+    };
+    let role = match config.mod_role {
+        Some(r) => format!("<@&{}>", r),
+        None => "not set".to_string(),
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Configure")
+                .description(if changed {
+                    "Server settings updated"
+                } else {
+                    "Current server settings"
+                })
+                .field("General", channel(config.general), true)
+                .field("Bot Commands", channel(config.bot_cmd), true)
+                .field("Submit", channel(config.submit), true)
+                .field("Mod Chat", channel(config.mod_chat), true)
+                .field("Mod Role", role, true)
+                .color(data::EMBED_MOD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - apply changes to config.toml without restarting professorBot
+#[poise::command(slash_command, check = "check_mod")]
+pub async fn reload_config(ctx: Context<'_>) -> Result<(), Error> {
+    let old = ctx.data().config.get();
+
+    let embed = match ctx.data().config.reload() {
+        Ok(new) => {
+            let desc = match *old == *new {
+                true => "Settings reloaded, nothing changed".to_string(),
+                false => "Settings reloaded and applied".to_string(),
+            };
+            serenity::CreateEmbed::new()
+                .title("Reload Config")
+                .description(desc)
+                .color(data::EMBED_MOD)
+        }
+        Err(e) => {
+            println!("Failed to reload settings: {}", e);
+            serenity::CreateEmbed::new()
+                .title("Reload Config")
+                .description(format!(
+                    "Kept the current settings, the file has problems:\n```{}```",
+                    e
+                ))
+                .color(data::EMBED_ERROR)
+        }
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(embed.footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ))),
+    )
+    .await?;
+    Ok(())
+}
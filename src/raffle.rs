@@ -0,0 +1,472 @@
+//!---------------------------------------------------------------------!
+//! This file contains the battle pass raffle, tickets bought with      !
+//! /buy_tickets are entries and every draw ends a season               !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - raffle_open                                               !
+//!     [x] - raffle_close                                              !
+//!     [x] - raffle_draw                                               !
+//!     [x] - raffle_history                                            !
+//!     [x] - raffle_verify                                             !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - draw_winners                                              !
+//!     [x] - entries_hash                                              !
+//!---------------------------------------------------------------------!
+
+use crate::clips::check_mod;
+use crate::data::{self, ClosedRaffle, OpenRaffle, RaffleResult};
+use crate::rng;
+use crate::{serenity, Context, Error};
+use chrono::prelude::Utc;
+use poise::serenity_prelude::UserId;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Hex sha256 of the entries, published when sales close so they can't change before the draw
+pub fn entries_hash(entries: &[(UserId, i32)]) -> String {
+    let text: String = entries
+        .iter()
+        .map(|(id, tickets)| format!("{}:{}\n", id, tickets))
+        .collect();
+    rng::sha256_hex(text.as_bytes())
+}
+
+/// Name the committed seed of a raffle is kept under
+fn purpose(guild_id: serenity::GuildId, season: usize) -> String {
+    format!("raffle {} season {}", guild_id, season)
+}
+
+fn raffle_embed(title: String, desc: String, color: serenity::Color) -> poise::CreateReply {
+    poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title(title)
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    )
+}
+
+/// Pick up to `count` different winners, every ticket is one chance to win
+pub fn draw_winners(
+    mut entries: Vec<(UserId, i32)>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<(UserId, i32)> {
+    entries.retain(|(_, tickets)| *tickets > 0);
+
+    let mut winners = Vec::new();
+    while winners.len() < count && !entries.is_empty() {
+        let total: i64 = entries.iter().map(|(_, t)| *t as i64).sum();
+        let mut pick = rng.gen_range(0..total);
+        let index = entries
+            .iter()
+            .position(|(_, t)| {
+                pick -= *t as i64;
+                pick < 0
+            })
+            .unwrap();
+        winners.push(entries.swap_remove(index));
+    }
+    winners
+}
+
+/// [!] MODERATOR - open the battle pass raffle for a prize
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn raffle_open(
+    ctx: Context<'_>,
+    #[description = "what the winners get"] prize: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let mut raffle = guild.raffle();
+
+    let reply = match &raffle.open {
+        Some(open) => raffle_embed(
+            "Raffle".to_string(),
+            format!(
+                "The season {} raffle for **{}** is already open, draw it with **/raffle_draw** first",
+                raffle.season(),
+                open.prize
+            ),
+            data::EMBED_ERROR,
+        ),
+        None => {
+            raffle.open = Some(OpenRaffle {
+                prize: prize.clone(),
+                opened: Utc::now(),
+                by: ctx.author().id,
+                closed: None,
+            });
+            let season = raffle.season();
+            guild.set_raffle(raffle);
+            ctx.data().saver.mark_guild_dirty(guild_id);
+
+            raffle_embed(
+                format!("Season {} Raffle Open!", season),
+                format!(
+                    "Moderator <@{}> opened the raffle for **{}**!\n\nGet your tickets with **/buy_tickets**, every ticket is another chance to win",
+                    ctx.author().id,
+                    prize
+                ),
+                data::EMBED_MOD,
+            )
+        }
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - stop ticket sales and commit to the seed the draw will use
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn raffle_close(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let mut raffle = guild.raffle();
+    let season = raffle.season();
+    let purpose = purpose(guild_id, season);
+
+    let open = match raffle.open.as_mut() {
+        Some(open) => open,
+        None => {
+            ctx.send(raffle_embed(
+                "Raffle".to_string(),
+                "There is no open raffle, start one with **/raffle_open**".to_string(),
+                data::EMBED_ERROR,
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // closing again only makes sense if the seed was lost to a restart
+    if open.closed.is_some() && ctx.data().rng.is_committed(&purpose) {
+        ctx.send(raffle_embed(
+            "Raffle".to_string(),
+            "Ticket sales are already closed, draw the winners with **/raffle_draw**".to_string(),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let entries = match &open.closed {
+        Some(closed) => closed.entries.clone(),
+        None => {
+            // clone the Arcs out first, a DashMap shard stays locked while a ref is held
+            let users: Vec<_> = guild
+                .users
+                .iter()
+                .map(|x| (*x.key(), Arc::clone(x.value())))
+                .collect();
+            let mut entries = Vec::new();
+            for (id, u) in users {
+                let tickets = u.read().await.get_tickets();
+                if tickets > 0 {
+                    entries.push((id, tickets));
+                }
+            }
+            // a fixed order, so the revealed seed replays to the same winners
+            entries.sort();
+            entries
+        }
+    };
+    if entries.is_empty() {
+        ctx.send(raffle_embed(
+            "Raffle".to_string(),
+            format!("No one has bought a ticket for **{}** yet", open.prize),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let recommit = open.closed.is_some();
+    let entries_hash = entries_hash(&entries);
+    let commitment = ctx.data().rng.commit(&purpose);
+    let entrants = entries.len();
+    let tickets: i32 = entries.iter().map(|(_, t)| t).sum();
+    open.closed = Some(ClosedRaffle {
+        closed: Utc::now(),
+        entries,
+        entries_hash: entries_hash.clone(),
+        commitment: commitment.clone(),
+    });
+    let prize = open.prize.clone();
+    guild.set_raffle(raffle);
+    ctx.data().saver.mark_guild_dirty(guild_id);
+
+    let note = match recommit {
+        true => "\n\nThe bot restarted since sales closed and the old seed was never revealed, this commitment replaces it",
+        false => "",
+    };
+    ctx.send(raffle_embed(
+        format!("Season {} Raffle Closed!", season),
+        format!(
+            "Ticket sales for **{}** are closed, {} members entered with {} tickets.\n\nEntries: `{}`\nDraw commitment: `{}`\nThe seed behind it is revealed with the winners{}",
+            prize, entrants, tickets, entries_hash, commitment, note
+        ),
+        data::EMBED_MOD,
+    ))
+    .await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - draw the raffle winners and start the next season
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn raffle_draw(
+    ctx: Context<'_>,
+    #[description = "how many winners to draw (default 1)"]
+    #[min = 1]
+    #[max = 25]
+    winners: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let mut raffle = guild.raffle();
+    let season = raffle.season();
+
+    let (open, closed) = match &raffle.open {
+        Some(open) => match &open.closed {
+            Some(closed) => (open.clone(), closed.clone()),
+            None => {
+                ctx.send(raffle_embed(
+                    "Raffle".to_string(),
+                    "Ticket sales are still open, close them with **/raffle_close** first"
+                        .to_string(),
+                    data::EMBED_ERROR,
+                ))
+                .await?;
+                return Ok(());
+            }
+        },
+        None => {
+            ctx.send(raffle_embed(
+                "Raffle".to_string(),
+                "There is no open raffle, start one with **/raffle_open**".to_string(),
+                data::EMBED_ERROR,
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // the published hash was a promise, there is no drawing without its seed
+    let (mut draw_rng, commitment) = match ctx
+        .data()
+        .rng
+        .reveal(&purpose(guild_id, season), &closed.entries_hash)
+    {
+        Some(revealed) if revealed.1.hash == closed.commitment => revealed,
+        _ => {
+            println!(
+                "Raffle season {} in guild {} has no seed for commitment {}",
+                season, guild_id, closed.commitment
+            );
+            ctx.send(raffle_embed(
+                "Raffle".to_string(),
+                "The seed committed when sales closed is gone, most likely the bot restarted. Nothing was drawn, run **/raffle_close** again to publish a new commitment".to_string(),
+                data::EMBED_ERROR,
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let entries = closed.entries;
+    let entrants = entries.len();
+    let tickets: i32 = entries.iter().map(|(_, t)| t).sum();
+    let drawn = draw_winners(
+        entries.clone(),
+        winners.unwrap_or(1) as usize,
+        &mut draw_rng,
+    );
+
+    // the season is over, everyone starts the next one without tickets
+    for (id, owned) in &entries {
+        if let Some(u) = guild.users.get(id) {
+            let mut user_data = u.write().await;
+            let owned = (*owned).min(user_data.get_tickets());
+            user_data.remove_tickets(owned);
+            ctx.data().saver.mark_dirty(guild_id, *id);
+        }
+    }
+
+    raffle.open = None;
+    raffle.history.push(RaffleResult {
+        season,
+        prize: open.prize.clone(),
+        winners: drawn.clone(),
+        entrants,
+        tickets,
+        drawn: Utc::now(),
+        by: ctx.author().id,
+        commitment: Some(commitment.clone()),
+        entries,
+    });
+    guild.set_raffle(raffle);
+    ctx.data().saver.mark_guild_dirty(guild_id);
+
+    let mut pre_text = String::new();
+    let mut desc = format!("The winners of **{}** are...\n\n", open.prize);
+    for (place, (id, owned)) in drawn.iter().enumerate() {
+        pre_text += format!("<@{}> ", id).as_str();
+        desc += format!(
+            "**{}.** <@{}> with {} ticket(s) ({:.1}% chance)\n",
+            place + 1,
+            id,
+            owned,
+            *owned as f64 / tickets as f64 * 100.0
+        )
+        .as_str();
+    }
+    desc += format!(
+        "\n{} members entered with {} tickets. Tickets have been reset, season {} starts now!\n\nSeed: `{}`\nCommitment: `{}`\nCheck it with **/raffle_verify {}**",
+        entrants,
+        tickets,
+        season + 1,
+        commitment.seed,
+        commitment.hash,
+        season
+    )
+    .as_str();
+
+    ctx.send(
+        raffle_embed(
+            format!("Season {} Raffle Results!", season),
+            desc,
+            data::EMBED_SUCCESS,
+        )
+        .content(pre_text),
+    )
+    .await?;
+    Ok(())
+}
+
+/// see the prizes and winners of past raffles
+#[poise::command(slash_command, guild_only)]
+pub async fn raffle_history(ctx: Context<'_>) -> Result<(), Error> {
+    let raffle = ctx.data().guild(ctx.guild_id().unwrap()).raffle();
+
+    let mut desc = match &raffle.open {
+        Some(open) => match &open.closed {
+            Some(closed) => format!(
+                "Season {} for **{}** closed sales on {}, draw commitment `{}`\n\n",
+                raffle.season(),
+                open.prize,
+                closed.closed.format("%m/%d/%Y"),
+                closed.commitment
+            ),
+            None => format!(
+                "Season {} is open for **{}** since {}\n\n",
+                raffle.season(),
+                open.prize,
+                open.opened.format("%m/%d/%Y")
+            ),
+        },
+        None => format!("Season {} hasn't opened yet\n\n", raffle.season()),
+    };
+
+    if raffle.history.is_empty() {
+        desc += "No raffle has been drawn yet";
+    }
+    // newest first, the embed only has room for the last few seasons
+    for result in raffle.history.iter().rev().take(10) {
+        let winners: Vec<String> = result
+            .winners
+            .iter()
+            .map(|(id, _)| format!("<@{}>", id))
+            .collect();
+        desc += format!(
+            "**Season {}** - {} ({})\n{} \u{2022} {} tickets from {} members\n\n",
+            result.season,
+            result.prize,
+            result.drawn.format("%m/%d/%Y"),
+            winners.join(", "),
+            result.tickets,
+            result.entrants
+        )
+        .as_str();
+    }
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Raffle History")
+                .description(desc)
+                .color(data::EMBED_GOLD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// check that a past raffle was drawn from the seed it committed to
+#[poise::command(slash_command, guild_only)]
+pub async fn raffle_verify(
+    ctx: Context<'_>,
+    #[description = "season of the raffle to check"]
+    #[min = 1]
+    season: usize,
+) -> Result<(), Error> {
+    let raffle = ctx.data().guild(ctx.guild_id().unwrap()).raffle();
+
+    let (desc, color) = match raffle.history.iter().find(|r| r.season == season) {
+        None => (
+            format!("The season {} raffle hasn't been drawn", season),
+            data::EMBED_ERROR,
+        ),
+        Some(result) => match &result.commitment {
+            None => (
+                format!(
+                    "The season {} raffle was drawn before commitments, it can't be checked",
+                    season
+                ),
+                data::EMBED_ERROR,
+            ),
+            Some(commitment) => {
+                let hash = entries_hash(&result.entries);
+                let matches = rng::verify(&commitment.seed, &commitment.hash);
+                let replayed = commitment.replay(&hash).map(|mut draw_rng| {
+                    draw_winners(result.entries.clone(), result.winners.len(), &mut draw_rng)
+                });
+                let same = replayed.as_ref() == Some(&result.winners);
+
+                (
+                    format!(
+                        "Seed: `{}`\nCommitment: `{}`\nEntries: `{}`\n\nSeed matches commitment: **{}**\nReplayed winners match: **{}**\n\n{}",
+                        commitment.seed,
+                        commitment.hash,
+                        hash,
+                        if matches { "yes" } else { "no" },
+                        if same { "yes" } else { "no" },
+                        if matches && same {
+                            "The draw was fair, the winners follow from the entries and the seed promised when sales closed"
+                        } else {
+                            "The draw doesn't match its commitment!"
+                        }
+                    ),
+                    if matches && same {
+                        data::EMBED_SUCCESS
+                    } else {
+                        data::EMBED_FAIL
+                    },
+                )
+            }
+        },
+    };
+
+    ctx.send(raffle_embed(
+        format!("Season {} Raffle Check", season),
+        desc,
+        color,
+    ))
+    .await?;
+    Ok(())
+}
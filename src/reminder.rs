@@ -1,12 +1,29 @@
 use crate::data;
 use crate::helper::{get_current_date, get_current_year, get_reminder_date};
 use crate::{serenity, Context};
-use poise::serenity_prelude::{ChannelId, CreateMessage};
+use poise::serenity_prelude::{CreateMessage, GuildId};
 use std::env;
 use std::fs::{write, File};
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 
-const EVENT_FILE: &str = ".eventdb";
+// Each guild keeps its birthdays in its own file
+const EVENT_DIR: &str = "events";
+// Where the home guild (GUILD_ID) kept them before multi-guild support
+const LEGACY_EVENT_FILE: &str = ".eventdb";
+
+fn event_file(guild_id: GuildId) -> Option<String> {
+    let path = format!("{}/{}.eventdb", EVENT_DIR, guild_id);
+    if Path::new(&path).exists() {
+        return Some(path);
+    }
+
+    let home = env::var("GUILD_ID").ok();
+    if home == Some(guild_id.to_string()) && Path::new(LEGACY_EVENT_FILE).exists() {
+        return Some(LEGACY_EVENT_FILE.to_string());
+    }
+    None
+}
 
 fn import_from_file(filename: &str) -> Vec<Vec<String>> {
     let mut file_descriptor = BufReader::new(File::open(filename).unwrap());
@@ -41,40 +58,46 @@ fn export_to_file(filename: &str, database: Vec<Vec<String>>) {
 }
 
 pub async fn check_birthday(ctx: Context<'_>) {
-    let mut database: Vec<Vec<String>> = import_from_file(EVENT_FILE);
-    let length = database.len();
-    let today: String = get_current_date();
-    let year: String = get_current_year();
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    // guilds without a birthday list have nothing to remind
+    let event_file = match event_file(guild_id) {
+        Some(event_file) => event_file,
+        None => return,
+    };
+    let config = ctx.data().guild(guild_id).config();
+
+    let mut database: Vec<Vec<String>> = import_from_file(&event_file);
+    let tz = ctx.data().config.get().time.timezone;
+    let today: String = get_current_date(tz);
+    let year: String = get_current_year(tz);
 
     //TODO: finish this shit
-    for i in 0..length {
-        let date: String = format!("{}-{}", year, database[i][0]);
-        let name: String = database[i][1].clone();
-        let user_id: String = database[i][2].clone();
-        let reminded: bool = matches!(database[i][3].as_str(), "1");
-        let pinged: bool = matches!(database[i][4].as_str(), "1");
+    for row in database.iter_mut() {
+        let date: String = format!("{}-{}", year, row[0]);
+        let name: String = row[1].clone();
+        let user_id: String = row[2].clone();
+        let reminded: bool = matches!(row[3].as_str(), "1");
+        let pinged: bool = matches!(row[4].as_str(), "1");
 
         let reminder: String = get_reminder_date(&date);
 
-        if today == reminder && !reminded {
-            let mod_chat: u64 = env::var("MOD_CHAT")
-                .expect("Failed to load MODERATOR chat id")
-                .parse()
-                .unwrap();
-
-            let mod_id: u64 = env::var("MOD_ID")
-                .expect("Failed to load MODERATOR ping id")
-                .parse()
-                .unwrap();
-
+        if let (true, false, Some(mod_chat), Some(mod_id)) = (
+            today == reminder,
+            reminded,
+            config.mod_chat,
+            config.mod_role,
+        ) {
             let desc = format!(
                 "Hey <@&{}>, {}'s Birthday is coming up in 2 weeks! ({})",
                 mod_id, name, date
             );
 
-            database[i][3] = "1".to_string();
+            row[3] = "1".to_string();
 
-            ChannelId::new(mod_chat)
+            mod_chat
                 .send_message(
                     ctx.http(),
                     CreateMessage::default().embed(
@@ -91,20 +114,15 @@ pub async fn check_birthday(ctx: Context<'_>) {
                 .unwrap();
         }
 
-        if today == date && !pinged {
+        if let (true, false, Some(gen_chat)) = (today == date, pinged, config.general) {
             let desc = format!(
                 "Heyyyyyyy, its someone's special day!! It's {}'s (<@{}>) Birthday!!!",
                 name, user_id
             );
 
-            let gen_chat: u64 = env::var("GENERAL")
-                .expect("Failed to load GENERAL chat id")
-                .parse()
-                .unwrap();
-
-            database[i][4] = "1".to_string();
+            row[4] = "1".to_string();
 
-            ChannelId::new(gen_chat)
+            gen_chat
                 .send_message(
                     ctx.http(),
                     CreateMessage::default().embed(
@@ -122,5 +140,5 @@ pub async fn check_birthday(ctx: Context<'_>) {
         }
     }
 
-    export_to_file(EVENT_FILE, database);
+    export_to_file(&event_file, database);
 }
@@ -0,0 +1,174 @@
+//!---------------------------------------------------------------------!
+//! This file contains role rentals, roles bought in the shop that are  !
+//! taken away again once their time runs out                           !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - rentals                                                   !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - start_rental                                              !
+//!     [x] - spawn                                                     !
+//!---------------------------------------------------------------------!
+
+use crate::data::{self, GuildData, RentalTerms, RoleRental, ShopItem};
+use crate::saver::SaveCoordinator;
+use crate::{serenity, Context, Error};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+// How many seconds between checks for expired rentals
+const RENTAL_CHECK: u64 = 60;
+
+/// Give a rented role, renting a role someone already has adds to their time
+pub async fn start_rental(
+    http: &serenity::Http,
+    guild_id: serenity::GuildId,
+    guild: &GuildData,
+    user_id: serenity::UserId,
+    item: &ShopItem,
+    terms: &RentalTerms,
+) -> Result<DateTime<Utc>, Error> {
+    http.add_member_role(guild_id, user_id, terms.role, Some("Rented from the shop"))
+        .await?;
+
+    let now = Utc::now();
+    let length = chrono::Duration::hours(terms.hours as i64);
+    let expires = guild.update_rentals(|rentals| {
+        match rentals
+            .iter_mut()
+            .find(|r| r.user == user_id && r.role == terms.role)
+        {
+            Some(rental) => {
+                rental.expires = rental.expires.max(now) + length;
+                rental.item = item.name.clone();
+                rental.expires
+            }
+            None => {
+                rentals.push(RoleRental {
+                    user: user_id,
+                    role: terms.role,
+                    item: item.name.clone(),
+                    expires: now + length,
+                });
+                now + length
+            }
+        }
+    });
+    Ok(expires)
+}
+
+/// Whether discord says the member or role is gone, so there is nothing left to take away
+fn is_gone(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response)) => {
+            response.status_code == serenity::StatusCode::NOT_FOUND
+        }
+        _ => false,
+    }
+}
+
+/// Take away expired roles in the background, rentals are saved so a restart
+/// picks up where it left off
+pub fn spawn(
+    guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
+    saver: Arc<SaveCoordinator>,
+    http: Arc<serenity::Http>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let list: Vec<(serenity::GuildId, Arc<GuildData>)> = guilds
+                .iter()
+                .map(|g| (*g.key(), Arc::clone(g.value())))
+                .collect();
+
+            for (guild_id, guild) in list {
+                let expired: Vec<RoleRental> = guild
+                    .rentals()
+                    .into_iter()
+                    .filter(|r| r.expires <= now)
+                    .collect();
+
+                for rental in expired {
+                    let removed = http
+                        .remove_member_role(
+                            guild_id,
+                            rental.user,
+                            rental.role,
+                            Some("Role rental expired"),
+                        )
+                        .await;
+                    if let Err(e) = removed {
+                        println!(
+                            "Failed to take rented role {} from {}: {}",
+                            rental.role, rental.user, e
+                        );
+                        // try again next time unless the member or role is gone
+                        if !is_gone(&e) {
+                            continue;
+                        }
+                    }
+
+                    guild.update_rentals(|rentals| rentals.retain(|r| r != &rental));
+                    saver.mark_guild_dirty(guild_id);
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(RENTAL_CHECK)).await;
+        }
+    });
+}
+
+/// see the roles you are renting and how long they last
+#[poise::command(slash_command, guild_only)]
+pub async fn rentals(ctx: Context<'_>) -> Result<(), Error> {
+    let user = ctx.author();
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let now = Utc::now();
+
+    let mut rentals: Vec<RoleRental> = guild
+        .rentals()
+        .into_iter()
+        .filter(|r| r.user == user.id)
+        .collect();
+    rentals.sort_by_key(|r| r.expires);
+
+    let mut desc = String::new();
+    for rental in &rentals {
+        let left = (rental.expires - now).max(chrono::Duration::zero());
+        desc += format!(
+            "<@&{}> from **{}**\n{}h {}m left, expires <t:{}:f>\n\n",
+            rental.role,
+            rental.item,
+            left.num_hours(),
+            left.num_minutes() % 60,
+            rental.expires.timestamp()
+        )
+        .as_str();
+    }
+
+    let embed = match rentals.is_empty() {
+        true => serenity::CreateEmbed::new()
+            .title("Rentals")
+            .description("You aren't renting any roles, take a look at the **/shop**!")
+            .color(data::EMBED_ERROR),
+        false => serenity::CreateEmbed::new()
+            .title("Rentals")
+            .description(desc)
+            .color(data::EMBED_GOLD),
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            embed
+                .thumbnail(user.avatar_url().unwrap_or_default())
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
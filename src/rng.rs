@@ -0,0 +1,317 @@
+//!---------------------------------------------------------------------!
+//! This file contains the rng service, every draw gets its own seed    !
+//! written to an audit file so rolls can be replayed, and big draws    !
+//! commit to a seed hash up front and reveal the seed afterwards       !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - draw                                                      !
+//!     [x] - commit                                                    !
+//!     [x] - reveal                                                    !
+//!     [x] - replay                                                    !
+//!     [x] - verify                                                    !
+//!---------------------------------------------------------------------!
+
+use chrono::{DateTime, Utc};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const AUDIT_FILE: &str = "rng_audit.jsonl";
+
+/// Rng of a single draw, ChaCha20 so a seed replays the same on every machine
+pub type DrawRng = ChaCha20Rng;
+
+/// Hands out a freshly seeded rng for every draw
+pub struct RngService {
+    master: Mutex<ChaCha20Rng>,
+    /// Committed seeds waiting for their draw, by purpose. Only ever kept in
+    /// memory, so nobody can read a seed out of the save before it is revealed
+    pending: Mutex<HashMap<String, [u8; 32]>>,
+    /// Append-only file every seed is written to, None in tests
+    audit: Option<PathBuf>,
+}
+
+/// A revealed seed and the hash that was published before the draw
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Commitment {
+    /// Hex seed
+    pub seed: String,
+    /// Hex sha256 of the seed bytes
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEvent {
+    Draw,
+    Commit,
+    Reveal,
+}
+
+/// One line of the audit file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub date: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub purpose: String,
+    /// Hex seed, None for a commitment that isn't revealed yet
+    pub seed: Option<String>,
+    /// Hex sha256 of the seed for commitments and reveals
+    pub hash: Option<String>,
+}
+
+impl Default for RngService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RngService {
+    /// Seeds come from the operating system and are audited to RNG_AUDIT_PATH
+    pub fn new() -> Self {
+        let path = env::var("RNG_AUDIT_PATH").unwrap_or_else(|_| AUDIT_FILE.to_string());
+        RngService {
+            master: Mutex::new(ChaCha20Rng::from_entropy()),
+            pending: Mutex::new(HashMap::new()),
+            audit: Some(PathBuf::from(path)),
+        }
+    }
+
+    /// Every draw follows from `seed`, for tests and replays, nothing is audited
+    pub fn seeded(seed: u64) -> Self {
+        RngService {
+            master: Mutex::new(ChaCha20Rng::seed_from_u64(seed)),
+            pending: Mutex::new(HashMap::new()),
+            audit: None,
+        }
+    }
+
+    /// Write the audit records to `path` instead
+    pub fn audit_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit = Some(path.into());
+        self
+    }
+
+    fn next_seed(&self) -> [u8; 32] {
+        let mut seed = [0u8; 32];
+        self.master.lock().unwrap().fill_bytes(&mut seed);
+        seed
+    }
+
+    fn record(&self, event: AuditEvent, purpose: &str, seed: Option<String>, hash: Option<String>) {
+        let Some(path) = &self.audit else {
+            return;
+        };
+        let record = AuditRecord {
+            date: Utc::now(),
+            event,
+            purpose: purpose.to_string(),
+            seed,
+            hash,
+        };
+
+        let line = serde_json::to_string(&record).unwrap() + "\n";
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            println!("Failed to write rng audit to {}: {}", path.display(), e);
+            print!("rng audit: {}", line);
+        }
+    }
+
+    /// Rng for one draw, the seed is audited under `purpose`
+    pub fn draw(&self, purpose: &str) -> DrawRng {
+        let seed = self.next_seed();
+        self.record(AuditEvent::Draw, purpose, Some(to_hex(&seed)), None);
+        ChaCha20Rng::from_seed(seed)
+    }
+
+    /// Pick a seed for a later draw and return its hash to publish. The seed
+    /// stays in memory until `reveal`, committing again replaces it
+    pub fn commit(&self, purpose: &str) -> String {
+        let seed = self.next_seed();
+        let hash = sha256_hex(&seed);
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(purpose.to_string(), seed);
+        self.record(AuditEvent::Commit, purpose, None, Some(hash.clone()));
+        hash
+    }
+
+    /// Whether a seed committed for `purpose` is waiting to be revealed
+    pub fn is_committed(&self, purpose: &str) -> bool {
+        self.pending.lock().unwrap().contains_key(purpose)
+    }
+
+    /// Rng from the seed committed for `purpose` mixed with `salt`, None if
+    /// nothing was committed, like after a restart
+    pub fn reveal(&self, purpose: &str, salt: &str) -> Option<(DrawRng, Commitment)> {
+        let seed = self.pending.lock().unwrap().remove(purpose)?;
+        let commitment = Commitment {
+            seed: to_hex(&seed),
+            hash: sha256_hex(&seed),
+        };
+        self.record(
+            AuditEvent::Reveal,
+            purpose,
+            Some(commitment.seed.clone()),
+            Some(commitment.hash.clone()),
+        );
+        Some((derive(&seed, salt), commitment))
+    }
+}
+
+impl Commitment {
+    /// The rng the draw used, None if the seed doesn't match the hash
+    pub fn replay(&self, salt: &str) -> Option<DrawRng> {
+        if !verify(&self.seed, &self.hash) {
+            return None;
+        }
+        let seed: [u8; 32] = from_hex(&self.seed)?.try_into().ok()?;
+        Some(derive(&seed, salt))
+    }
+}
+
+/// The draw depends on the seed and on `salt`, what it was drawn from
+fn derive(seed: &[u8; 32], salt: &str) -> DrawRng {
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update(salt.as_bytes());
+    ChaCha20Rng::from_seed(hasher.finalize().into())
+}
+
+/// True if `seed` hashes to `hash`, anyone can check a revealed draw with this
+pub fn verify(seed: &str, hash: &str) -> bool {
+    match from_hex(seed) {
+        Some(bytes) if bytes.len() == 32 => sha256_hex(&bytes) == hash.to_lowercase(),
+        _ => false,
+    }
+}
+
+/// Hex sha256 of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    // an odd length leaves half a byte, `get` returns None for it
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::fs;
+
+    #[test]
+    fn seeded_service_replays_draws() {
+        let a = RngService::seeded(42);
+        let b = RngService::seeded(42);
+        for _ in 0..5 {
+            let x: Vec<i32> = (0..10).map(|_| a.draw("test").gen_range(1..21)).collect();
+            let y: Vec<i32> = (0..10).map(|_| b.draw("test").gen_range(1..21)).collect();
+            assert_eq!(x, y);
+        }
+    }
+
+    #[test]
+    fn draws_get_different_seeds() {
+        let service = RngService::seeded(7);
+        let x: u64 = service.draw("test").gen();
+        let y: u64 = service.draw("test").gen();
+        assert_ne!(x, y);
+    }
+
+    #[test]
+    fn revealed_commitment_verifies_and_replays() {
+        let service = RngService::seeded(1);
+        let hash = service.commit("raffle");
+        assert!(service.is_committed("raffle"));
+
+        let (mut drawn, commitment) = service.reveal("raffle", "entries").unwrap();
+        assert_eq!(commitment.hash, hash);
+        assert!(verify(&commitment.seed, &hash));
+        assert!(!service.is_committed("raffle"));
+
+        let x: u64 = drawn.gen();
+        let y: u64 = commitment.replay("entries").unwrap().gen();
+        let other: u64 = commitment.replay("other entries").unwrap().gen();
+        assert_eq!(x, y);
+        assert_ne!(x, other);
+    }
+
+    #[test]
+    fn reveal_needs_a_commitment() {
+        let service = RngService::seeded(1);
+        assert!(service.reveal("raffle", "entries").is_none());
+
+        service.commit("raffle");
+        assert!(service.reveal("raffle", "entries").is_some());
+        // a seed is only revealed once
+        assert!(service.reveal("raffle", "entries").is_none());
+    }
+
+    #[test]
+    fn tampered_seed_fails_verify() {
+        let service = RngService::seeded(1);
+        let hash = service.commit("a");
+        service.commit("b");
+        let (_, other) = service.reveal("b", "").unwrap();
+        assert!(!verify(&other.seed, &hash));
+        assert!(!verify("not hex", &hash));
+
+        let tampered = Commitment {
+            seed: other.seed,
+            hash,
+        };
+        assert!(tampered.replay("").is_none());
+    }
+
+    #[test]
+    fn audit_keeps_every_seed() {
+        let path = env::temp_dir().join(format!("rng_audit_test_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let service = RngService::seeded(3).audit_to(&path);
+
+        service.draw("uwu");
+        let hash = service.commit("raffle");
+        let (_, commitment) = service.reveal("raffle", "entries").unwrap();
+
+        let records: Vec<AuditRecord> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].event, AuditEvent::Draw);
+        assert_eq!(records[0].purpose, "uwu");
+        assert!(records[0].seed.is_some());
+        // the seed stays out of the audit until the reveal
+        assert_eq!(records[1].event, AuditEvent::Commit);
+        assert_eq!(records[1].seed, None);
+        assert_eq!(records[1].hash, Some(hash));
+        assert_eq!(records[2].event, AuditEvent::Reveal);
+        assert_eq!(records[2].seed, Some(commitment.seed));
+    }
+}
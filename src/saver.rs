@@ -0,0 +1,296 @@
+//!---------------------------------------------------------------------!
+//! This file contains the save coordinator, which tracks which users   !
+//! changed and flushes them to the store in the background             !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - mark_dirty                                                !
+//!     [x] - mark_guild_dirty                                          !
+//!     [x] - flush                                                     !
+//!     [x] - spawn                                                     !
+//!---------------------------------------------------------------------!
+
+use crate::data::{GuildData, GuildSave, SaveData};
+use crate::serenity;
+use crate::storage::UserStore;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// How long it has to be quiet before dirty users get flushed
+const SAVE_DEBOUNCE: Duration = Duration::from_secs(5);
+// How long a change may wait at most, even if users keep changing
+const SAVE_MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct DirtySet {
+    users: HashSet<(serenity::GuildId, serenity::UserId)>,
+    guilds: HashSet<serenity::GuildId>,
+    first_mark: Option<Instant>,
+    last_mark: Option<Instant>,
+}
+
+pub struct SaveCoordinator {
+    guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
+    store: Arc<dyn UserStore>,
+    dirty: Mutex<DirtySet>,
+    /// Keeps a manual flush and the background flush from overlapping
+    flush_lock: tokio::sync::Mutex<()>,
+}
+
+impl SaveCoordinator {
+    pub fn new(
+        guilds: Arc<DashMap<serenity::GuildId, Arc<GuildData>>>,
+        store: Arc<dyn UserStore>,
+    ) -> Self {
+        SaveCoordinator {
+            guilds,
+            store,
+            dirty: Mutex::new(DirtySet::default()),
+            flush_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Flag a user as changed so the next flush writes them
+    pub fn mark_dirty(&self, guild_id: serenity::GuildId, id: serenity::UserId) {
+        let mut dirty = self.dirty.lock().unwrap();
+        dirty.users.insert((guild_id, id));
+        Self::touch(&mut dirty);
+    }
+
+    /// Flag a guild's own data (like its config) as changed
+    pub fn mark_guild_dirty(&self, guild_id: serenity::GuildId) {
+        let mut dirty = self.dirty.lock().unwrap();
+        dirty.guilds.insert(guild_id);
+        Self::touch(&mut dirty);
+    }
+
+    fn touch(dirty: &mut DirtySet) {
+        let now = Instant::now();
+        dirty.first_mark.get_or_insert(now);
+        dirty.last_mark = Some(now);
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        let dirty = self.dirty.lock().unwrap();
+        match (dirty.first_mark, dirty.last_mark) {
+            (Some(first), Some(last)) => {
+                now - last >= SAVE_DEBOUNCE || now - first >= SAVE_MAX_DELAY
+            }
+            _ => false,
+        }
+    }
+
+    /// Write every dirty user to the store, stores that can't write single
+    /// users get everyone. Users stay dirty if the write fails.
+    pub async fn flush(&self) {
+        let _guard = self.flush_lock.lock().await;
+
+        // marks made while this flush runs start their own debounce
+        let (dirty, dirty_guilds, first_mark) = {
+            let mut dirty = self.dirty.lock().unwrap();
+            dirty.last_mark = None;
+            (
+                std::mem::take(&mut dirty.users),
+                std::mem::take(&mut dirty.guilds),
+                dirty.first_mark.take(),
+            )
+        };
+        if dirty.is_empty() && dirty_guilds.is_empty() {
+            return;
+        }
+
+        let incremental = self.store.incremental();
+        // clone the Arcs out first, a DashMap shard stays locked while a ref is held
+        let guilds: Vec<_> = self
+            .guilds
+            .iter()
+            .map(|g| (*g.key(), Arc::clone(g.value())))
+            .collect();
+
+        let guilds_save = DashMap::new();
+        for (guild_id, guild) in guilds {
+            let touched =
+                dirty_guilds.contains(&guild_id) || dirty.iter().any(|(gid, _)| *gid == guild_id);
+            if incremental && !touched {
+                continue;
+            }
+
+            let users: Vec<_> = guild
+                .users
+                .iter()
+                .filter(|x| !incremental || dirty.contains(&(guild_id, *x.key())))
+                .map(|x| (*x.key(), Arc::clone(x.value())))
+                .collect();
+            let users_save = DashMap::new();
+            for (id, u) in users {
+                let u = u.read().await;
+                users_save.insert(id, u.clone());
+            }
+
+            let guild_save = GuildSave {
+                config: guild.config(),
+                raffle: guild.raffle(),
+                shop: guild.shop(),
+                rentals: guild.rentals(),
+                auctions: guild.auctions(),
+                seasons: guild.seasons(),
+                users: users_save,
+            };
+            guilds_save.insert(guild_id, guild_save);
+        }
+
+        // sqlite and the json file both block, keep them off the runtime threads
+        let save = SaveData::new(guilds_save);
+        let store = Arc::clone(&self.store);
+        let saved = tokio::task::spawn_blocking(move || match incremental {
+            true => store.save(&save),
+            false => store.replace(&save),
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.into()));
+        if let Err(e) = saved {
+            println!("Failed to save to {} store: {}", self.store.name(), e);
+            for (guild_id, id) in dirty {
+                self.mark_dirty(guild_id, id);
+            }
+            for guild_id in dirty_guilds {
+                self.mark_guild_dirty(guild_id);
+            }
+            // the max delay still counts from the oldest change that wasn't written
+            let mut left = self.dirty.lock().unwrap();
+            left.first_mark = left.first_mark.min(first_mark).or(first_mark);
+        }
+    }
+
+    /// Flush in the background whenever the dirty set has settled
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                if self.is_due(Instant::now()) {
+                    self.flush().await;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::UserData;
+    use crate::Error;
+    use serenity::{GuildId, UserId};
+
+    const GUILD: GuildId = GuildId::new(1);
+
+    /// Counts saves and how many users each one held
+    #[derive(Default)]
+    struct CountingStore {
+        saves: Mutex<Vec<usize>>,
+        /// Marks user 2 once while the next save is being written
+        mark_during_save: Mutex<Option<std::sync::Weak<SaveCoordinator>>>,
+    }
+
+    impl UserStore for CountingStore {
+        fn load(&self) -> Result<Option<SaveData>, Error> {
+            Ok(None)
+        }
+
+        fn save(&self, data: &SaveData) -> Result<(), Error> {
+            self.saves.lock().unwrap().push(data.user_count());
+            let saver = self.mark_during_save.lock().unwrap().take();
+            if let Some(saver) = saver.and_then(|s| s.upgrade()) {
+                saver.mark_dirty(GUILD, UserId::new(2));
+            }
+            Ok(())
+        }
+
+        fn incremental(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    fn coordinator() -> (Arc<SaveCoordinator>, Arc<CountingStore>) {
+        let guild = GuildData::default();
+        for id in 1..=3 {
+            guild.users.insert(
+                UserId::new(id),
+                Arc::new(tokio::sync::RwLock::new(UserData::default())),
+            );
+        }
+        let guilds = Arc::new(DashMap::new());
+        guilds.insert(GUILD, Arc::new(guild));
+
+        let store = Arc::new(CountingStore::default());
+        let saver = Arc::new(SaveCoordinator::new(guilds, store.clone()));
+        Arc::clone(&saver).spawn();
+        (saver, store)
+    }
+
+    fn saves(store: &CountingStore) -> Vec<usize> {
+        store.saves.lock().unwrap().clone()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_once_changes_settle() {
+        let (saver, store) = coordinator();
+        saver.mark_dirty(GUILD, UserId::new(1));
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        saver.mark_dirty(GUILD, UserId::new(2));
+
+        // five quiet seconds are counted from the last change
+        tokio::time::sleep(Duration::from_millis(4500)).await;
+        assert!(saves(&store).is_empty());
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(saves(&store), vec![2]);
+
+        // nothing changed since, nothing more is written
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(saves(&store), vec![2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn busy_users_still_get_flushed() {
+        let (saver, store) = coordinator();
+        // marks every two seconds never settle
+        for _ in 0..14 {
+            saver.mark_dirty(GUILD, UserId::new(1));
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        assert!(saves(&store).is_empty());
+
+        // the max delay flushes thirty seconds after the first mark anyway
+        saver.mark_dirty(GUILD, UserId::new(1));
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(saves(&store), vec![1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn marks_during_a_flush_start_a_new_debounce() {
+        let (saver, store) = coordinator();
+        *store.mark_during_save.lock().unwrap() = Some(Arc::downgrade(&saver));
+        saver.mark_dirty(GUILD, UserId::new(1));
+
+        // user 2 changes while user 1 is written, five seconds in
+        tokio::time::sleep(Duration::from_millis(5500)).await;
+        assert_eq!(saves(&store), vec![1]);
+
+        // the max delay counts from that change, not from user 1's
+        for _ in 0..13 {
+            saver.mark_dirty(GUILD, UserId::new(2));
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        assert_eq!(saves(&store), vec![1]);
+
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        assert_eq!(saves(&store), vec![1, 1]);
+    }
+}
@@ -0,0 +1,226 @@
+//!---------------------------------------------------------------------!
+//! This file contains economy seasons, a rollover archives everyone's  !
+//! stats, wipes the chosen fields and hands out prestige badges        !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - season_end                                                !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - end_season                                                !
+//!---------------------------------------------------------------------!
+
+use crate::clips::check_mod;
+use crate::config::SeasonField;
+use crate::data::{self, GuildData, SeasonSummary};
+use crate::saver::SaveCoordinator;
+use crate::{serenity, Context, Error};
+use chrono::Utc;
+use poise::serenity_prelude::futures::StreamExt;
+use poise::serenity_prelude::{EditMessage, UserId};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+pub fn field_name(field: SeasonField) -> &'static str {
+    match field {
+        SeasonField::Creds => "creds",
+        SeasonField::Levels => "levels",
+        SeasonField::Luck => "luck",
+        SeasonField::Tickets => "tickets",
+    }
+}
+
+/// Archive every member's season, reset `reset` and give `badges` to the richest in order
+pub async fn end_season(
+    guild_id: serenity::GuildId,
+    guild: &GuildData,
+    saver: &SaveCoordinator,
+    by: UserId,
+    reset: Vec<SeasonField>,
+    badges: &[String],
+) -> SeasonSummary {
+    let mut seasons = guild.seasons();
+    let season = seasons.season();
+
+    // clone the Arcs out first, a DashMap shard stays locked while a ref is held
+    let users: Vec<_> = guild
+        .users
+        .iter()
+        .map(|x| (*x.key(), Arc::clone(x.value())))
+        .collect();
+    let mut standings = Vec::new();
+    for (id, u) in users {
+        standings.push((id, u.read().await.get_creds()));
+    }
+    // ties go to whoever has been around longer, which is the lower id
+    standings.sort_by_key(|(id, creds)| (std::cmp::Reverse(*creds), *id));
+
+    for (rank, (id, _)) in standings.iter().enumerate() {
+        if let Some(u) = guild.users.get(id).map(|u| Arc::clone(&u)) {
+            u.write()
+                .await
+                .end_season(season, rank + 1, badges.get(rank).cloned(), &reset);
+            saver.mark_dirty(guild_id, *id);
+        }
+    }
+
+    let summary = SeasonSummary {
+        season,
+        ended: Utc::now(),
+        by,
+        reset,
+        top: standings
+            .iter()
+            .take(badges.len().max(3))
+            .cloned()
+            .collect(),
+        members: standings.len(),
+    };
+    seasons.history.push(summary.clone());
+    guild.set_seasons(seasons);
+    saver.mark_guild_dirty(guild_id);
+    summary
+}
+
+/// [!] MODERATOR - end the economy season, archive everyone's stats and start over
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn season_end(
+    ctx: Context<'_>,
+    #[description = "reset creds (default from config)"] creds: Option<bool>,
+    #[description = "reset levels and xp (default from config)"] levels: Option<bool>,
+    #[description = "reset rolls and average luck (default from config)"] luck: Option<bool>,
+    #[description = "reset raffle tickets (default from config)"] tickets: Option<bool>,
+) -> Result<(), Error> {
+    let author = ctx.author();
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+
+    let reset: Vec<SeasonField> = [
+        (SeasonField::Creds, creds),
+        (SeasonField::Levels, levels),
+        (SeasonField::Luck, luck),
+        (SeasonField::Tickets, tickets),
+    ]
+    .into_iter()
+    .filter(|(field, chosen)| chosen.unwrap_or(config.seasons.reset.contains(field)))
+    .map(|(field, _)| field)
+    .collect();
+
+    let embed = |desc: String, color: serenity::Color| {
+        serenity::CreateEmbed::new()
+            .title("Season End")
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            ))
+    };
+
+    // tickets are the raffle entries, wiping them would void an open raffle
+    if reset.contains(&SeasonField::Tickets) && guild.raffle().open.is_some() {
+        ctx.send(
+            poise::CreateReply::default().embed(embed(
+                "The raffle is still open, draw it with **/raffle_draw** before resetting tickets"
+                    .to_string(),
+                data::EMBED_ERROR,
+            )),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let season = guild.seasons().season();
+    let wiped: Vec<&str> = reset.iter().map(|f| field_name(*f)).collect();
+    let desc = format!(
+        "End season **{}**? Everyone's stats are archived and the top {} get a prestige badge.\n\nReset: **{}**",
+        season,
+        config.seasons.badges.len(),
+        if wiped.is_empty() {
+            "nothing".to_string()
+        } else {
+            wiped.join(", ")
+        }
+    );
+
+    let buttons = vec![
+        serenity::CreateButton::new("season-confirm")
+            .label("End Season")
+            .style(poise::serenity_prelude::ButtonStyle::Danger),
+        serenity::CreateButton::new("season-cancel")
+            .label("Cancel")
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+    ];
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(embed(desc, data::EMBED_MOD))
+                .components(vec![serenity::CreateActionRow::Buttons(buttons)]),
+        )
+        .await?;
+
+    let msg_og = Arc::new(RwLock::new(reply.into_message().await?));
+
+    let msg = Arc::clone(&msg_og);
+
+    let mut reactions = msg
+        .read()
+        .await
+        .await_component_interactions(ctx)
+        .author_id(author.id)
+        .timeout(Duration::new(60, 0))
+        .stream();
+
+    let saver = Arc::clone(&ctx.data().saver);
+    let ctx = ctx.serenity_context().clone();
+    let by = author.id;
+
+    tokio::spawn(async move {
+        let (desc, color) = match reactions.next().await {
+            Some(reaction) if reaction.data.custom_id == "season-confirm" => {
+                let summary =
+                    end_season(guild_id, &guild, &saver, by, reset, &config.seasons.badges).await;
+
+                let mut desc = format!("Season **{}** has ended!\n\n", summary.season);
+                for (place, (id, creds)) in summary.top.iter().enumerate() {
+                    desc += format!(
+                        "{} <@{}> with **{}** creds\n",
+                        config
+                            .seasons
+                            .badges
+                            .get(place)
+                            .cloned()
+                            .unwrap_or(format!("**{}.**", place + 1)),
+                        id,
+                        creds
+                    )
+                    .as_str();
+                }
+                desc += format!(
+                    "\n{} members were archived, see how you did with **/wallet season:{}**. Season **{}** starts now!",
+                    summary.members,
+                    summary.season,
+                    summary.season + 1
+                )
+                .as_str();
+                (desc, data::EMBED_SUCCESS)
+            }
+            Some(_) => ("Season end cancelled".to_string(), data::EMBED_ERROR),
+            None => ("Season end timed out".to_string(), data::EMBED_ERROR),
+        };
+
+        msg.write()
+            .await
+            .edit(
+                &ctx,
+                EditMessage::default()
+                    .embed(embed(desc, color))
+                    .components(Vec::new()),
+            )
+            .await
+            .unwrap();
+    });
+
+    Ok(())
+}
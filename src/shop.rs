@@ -0,0 +1,553 @@
+//!---------------------------------------------------------------------!
+//! This file contains the server shop, moderators stock it with items  !
+//! and roles to rent, members buy them with creds                      !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - shop                                                      !
+//!     [x] - inventory                                                 !
+//!     [x] - shop_add                                                  !
+//!     [x] - shop_remove                                               !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - buy                                                       !
+//!     [x] - refund                                                    !
+//!---------------------------------------------------------------------!
+
+use crate::clips::check_mod;
+use crate::data::{self, GuildData, RentalTerms, ShopItem, UserData};
+use crate::rentals;
+use crate::{serenity, Context, Error};
+use poise::serenity_prelude::futures::StreamExt;
+use poise::serenity_prelude::{ComponentInteractionDataKind, EditMessage};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+// Discord allows at most this many options in a select menu
+const MENU_LIMIT: usize = 25;
+
+/// Buy one of `name` for a user, stock and limits are checked while the shop is locked
+pub fn buy(guild: &GuildData, user: &mut UserData, name: &str) -> Result<ShopItem, String> {
+    guild.update_shop(|shop| {
+        let item = match shop.iter_mut().find(|i| i.name == name) {
+            Some(item) => item,
+            None => return Err(format!("**{}** isn't sold here anymore", name)),
+        };
+
+        if item.stock == Some(0) {
+            return Err(format!("**{}** is sold out", item.name));
+        }
+        if let Some(limit) = item.per_user {
+            if user.get_purchases(&item.name) >= limit {
+                return Err(format!(
+                    "You can only buy **{}** {} time(s)",
+                    item.name, limit
+                ));
+            }
+        }
+        if item.price > user.get_creds() {
+            return Err(format!(
+                "**{}** costs **{}** creds, you only have **{}**",
+                item.name,
+                item.price,
+                user.get_creds()
+            ));
+        }
+
+        if let Some(stock) = item.stock.as_mut() {
+            *stock -= 1;
+        }
+        user.sub_creds(
+            item.price,
+            data::CredReason::Shop {
+                item: item.name.clone(),
+            },
+        );
+        // rented roles are tracked by the rental job, not the inventory
+        if item.rental.is_none() {
+            user.add_item(&item.name, 1);
+        }
+        Ok(item.clone())
+    })
+}
+
+/// Undo a purchase that couldn't be handed out, stock goes back and creds are returned
+pub fn refund(guild: &GuildData, user: &mut UserData, item: &ShopItem) {
+    guild.update_shop(|shop| {
+        if let Some(stock) = shop
+            .iter_mut()
+            .find(|i| i.name == item.name)
+            .and_then(|i| i.stock.as_mut())
+        {
+            *stock += 1;
+        }
+    });
+    if item.rental.is_none() {
+        user.remove_item(&item.name, 1);
+    }
+    user.add_creds(
+        item.price,
+        data::CredReason::ShopRefund {
+            item: item.name.clone(),
+        },
+    );
+}
+
+fn shop_embed(
+    items: &[ShopItem],
+    category: &str,
+    selected: Option<&str>,
+    status: &str,
+) -> serenity::CreateEmbed {
+    let mut desc = String::new();
+    for item in items.iter().filter(|i| i.category == category) {
+        let stock = match item.stock {
+            Some(0) => " (sold out)".to_string(),
+            Some(stock) => format!(" ({} left)", stock),
+            None => String::new(),
+        };
+        let marker = match selected == Some(item.name.as_str()) {
+            true => "\u{25B6} ",
+            false => "",
+        };
+        let rental = match &item.rental {
+            Some(terms) => format!("\nRents <@&{}> for {} hour(s)", terms.role, terms.hours),
+            None => String::new(),
+        };
+        desc += format!(
+            "{}**{}** \u{2022} {} creds{}\n{}{}\n\n",
+            marker, item.name, item.price, stock, item.description, rental
+        )
+        .as_str();
+    }
+    desc += status;
+
+    serenity::CreateEmbed::new()
+        .title(format!("Shop \u{2022} {}", category))
+        .description(desc)
+        .color(data::EMBED_GOLD)
+        .footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ))
+}
+
+fn shop_components(
+    items: &[ShopItem],
+    category: &str,
+    selected: Option<&str>,
+) -> Vec<serenity::CreateActionRow> {
+    // a select menu without options is rejected by discord
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let mut categories: Vec<&str> = items.iter().map(|i| i.category.as_str()).collect();
+    categories.sort();
+    categories.dedup();
+
+    let category_options = categories
+        .iter()
+        .take(MENU_LIMIT)
+        .map(|c| serenity::CreateSelectMenuOption::new(*c, *c).default_selection(*c == category))
+        .collect();
+    let item_options: Vec<serenity::CreateSelectMenuOption> = items
+        .iter()
+        .filter(|i| i.category == category)
+        .take(MENU_LIMIT)
+        .map(|i| {
+            serenity::CreateSelectMenuOption::new(
+                format!("{} ({} creds)", i.name, i.price),
+                i.name.as_str(),
+            )
+            .default_selection(selected == Some(i.name.as_str()))
+        })
+        .collect();
+
+    vec![
+        serenity::CreateActionRow::SelectMenu(
+            serenity::CreateSelectMenu::new(
+                "shop-category",
+                serenity::CreateSelectMenuKind::String {
+                    options: category_options,
+                },
+            )
+            .placeholder("Pick a category"),
+        ),
+        serenity::CreateActionRow::SelectMenu(
+            serenity::CreateSelectMenu::new(
+                "shop-item",
+                serenity::CreateSelectMenuKind::String {
+                    options: item_options,
+                },
+            )
+            .placeholder("Pick an item"),
+        ),
+        serenity::CreateActionRow::Buttons(vec![serenity::CreateButton::new("shop-buy")
+            .label("Buy")
+            .style(poise::serenity_prelude::ButtonStyle::Success)
+            .disabled(selected.is_none())]),
+    ]
+}
+
+/// browse the server shop and spend creds on items
+#[poise::command(slash_command, guild_only)]
+pub async fn shop(ctx: Context<'_>) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let items = guild.shop();
+
+    let mut category = match items.iter().map(|i| i.category.clone()).min() {
+        Some(category) => category,
+        None => {
+            ctx.send(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::new()
+                        .title("Shop")
+                        .description("The shop is empty, check back later!")
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(shop_embed(&items, &category, None, ""))
+                .components(shop_components(&items, &category, None)),
+        )
+        .await?;
+
+    let msg_og = Arc::new(RwLock::new(reply.into_message().await?));
+    let msg = Arc::clone(&msg_og);
+
+    let mut reactions = msg
+        .read()
+        .await
+        .await_component_interactions(ctx)
+        .timeout(Duration::new(120, 0))
+        .author_id(user_id)
+        .stream();
+
+    let u = Arc::clone(&guild.users.get(&user_id).unwrap());
+    let saver = Arc::clone(&ctx.data().saver);
+    let ctx = ctx.serenity_context().clone();
+
+    tokio::spawn(async move {
+        let mut items = items;
+        let mut selected: Option<String> = None;
+        while let Some(reaction) = reactions.next().await {
+            let mut status = String::new();
+            let value = match &reaction.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values.first().cloned(),
+                _ => None,
+            };
+
+            match reaction.data.custom_id.as_str() {
+                "shop-category" => {
+                    if let Some(value) = value {
+                        category = value;
+                        selected = None;
+                    }
+                }
+                "shop-item" => selected = value,
+                "shop-buy" => {
+                    if let Some(name) = &selected {
+                        // charge first, the lock isn't held while discord hands out a role
+                        let bought = {
+                            let mut user_data = u.write().await;
+                            buy(&guild, &mut user_data, name)
+                                .map(|item| (item, user_data.get_creds()))
+                        };
+                        if bought.is_ok() {
+                            saver.mark_dirty(guild_id, user_id);
+                            saver.mark_guild_dirty(guild_id);
+                        }
+
+                        status = match bought {
+                            Ok((item, creds)) => match &item.rental {
+                                Some(terms) => match rentals::start_rental(
+                                    &ctx.http, guild_id, &guild, user_id, &item, terms,
+                                )
+                                .await
+                                {
+                                    Ok(expires) => format!(
+                                        "You rented **{}** for **{}** creds! It expires <t:{}:R> ({} creds left)",
+                                        item.name,
+                                        item.price,
+                                        expires.timestamp(),
+                                        creds
+                                    ),
+                                    Err(e) => {
+                                        println!("Failed to give rented role: {}", e);
+                                        refund(&guild, &mut *u.write().await, &item);
+                                        saver.mark_dirty(guild_id, user_id);
+                                        saver.mark_guild_dirty(guild_id);
+                                        format!(
+                                            "professorBot couldn't give you the role for **{}**, your creds were refunded",
+                                            item.name
+                                        )
+                                    }
+                                },
+                                None => format!(
+                                    "You bought **{}** for **{}** creds! ({} creds left)",
+                                    item.name, item.price, creds
+                                ),
+                            },
+                            Err(e) => e,
+                        };
+                    }
+                    // stock and prices may have changed since the menu was opened
+                    items = guild.shop();
+                    if !items.iter().any(|i| i.category == category) {
+                        if let Some(first) = items.iter().map(|i| i.category.clone()).min() {
+                            category = first;
+                        }
+                        selected = None;
+                    }
+                }
+                _ => (),
+            };
+
+            reaction
+                .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await
+                .unwrap();
+
+            msg.write()
+                .await
+                .edit(
+                    &ctx,
+                    EditMessage::default()
+                        .embed(shop_embed(&items, &category, selected.as_deref(), &status))
+                        .components(shop_components(&items, &category, selected.as_deref())),
+                )
+                .await
+                .unwrap();
+        }
+
+        msg.write()
+            .await
+            .edit(&ctx, EditMessage::default().components(Vec::new()))
+            .await
+            .unwrap();
+    });
+
+    Ok(())
+}
+
+/// see the items you bought in the shop
+#[poise::command(slash_command, guild_only)]
+pub async fn inventory(ctx: Context<'_>) -> Result<(), Error> {
+    let user = ctx.author();
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let shop = guild.shop();
+    let u = guild.users.get(&user.id).unwrap();
+    let user_data = u.read().await;
+
+    // items that left the shop are still owned, they just lost their category
+    let mut categories: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (item, count) in user_data.get_inventory() {
+        let category = shop
+            .iter()
+            .find(|i| &i.name == item)
+            .map_or("Other".to_string(), |i| i.category.clone());
+        categories
+            .entry(category)
+            .or_default()
+            .push(format!("{} x{}", item, count));
+    }
+    drop(user_data);
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Inventory")
+        .thumbnail(user.avatar_url().unwrap_or_default())
+        .color(data::EMBED_GOLD)
+        .footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ));
+    if categories.is_empty() {
+        embed = embed
+            .description("Nothing here yet, take a look at the **/shop**!")
+            .color(data::EMBED_ERROR);
+    }
+    for (category, items) in categories {
+        embed = embed.field(category, items.join("\n"), true);
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+async fn autocomplete_item(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Vec::new(),
+    };
+    ctx.data()
+        .guild(guild_id)
+        .shop()
+        .into_iter()
+        .map(|i| i.name)
+        .filter(|name| name.to_lowercase().starts_with(&partial.to_lowercase()))
+        .take(MENU_LIMIT)
+        .collect()
+}
+
+/// [!] MODERATOR - put an item up for sale, or change one that is already sold
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+#[allow(clippy::too_many_arguments)]
+pub async fn shop_add(
+    ctx: Context<'_>,
+    #[description = "item name"]
+    #[max_length = 80]
+    #[autocomplete = "autocomplete_item"]
+    name: String,
+    #[description = "price in creds"]
+    #[min = 1]
+    price: u32,
+    #[description = "category, like Cosmetics or Perks"]
+    #[max_length = 50]
+    category: String,
+    #[description = "what the item is"]
+    #[max_length = 200]
+    description: String,
+    #[description = "how many can be sold in total (default no limit)"] stock: Option<u32>,
+    #[description = "how many one user can buy (default no limit)"]
+    #[min = 1]
+    per_user: Option<u32>,
+    #[description = "role the item rents out, needs hours too"] role: Option<serenity::Role>,
+    #[description = "how many hours the role is rented for"]
+    #[min = 1]
+    hours: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+
+    let rental = match (role, hours) {
+        (Some(role), Some(hours)) => Some(RentalTerms {
+            role: role.id,
+            hours,
+        }),
+        (None, None) => None,
+        _ => {
+            ctx.send(
+                poise::CreateReply::default().embed(
+                    serenity::CreateEmbed::new()
+                        .title("Shop Add")
+                        .description("A role rental needs both a role and hours")
+                        .color(data::EMBED_ERROR)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "@~ powered by UwUntu & RustyBamboo",
+                        )),
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let item = ShopItem {
+        name: name.trim().to_string(),
+        category: category.trim().to_string(),
+        description,
+        price: price.min(i32::MAX as u32) as i32,
+        stock,
+        per_user,
+        rental,
+    };
+
+    let replaced = guild.update_shop(|shop| match shop.iter_mut().find(|i| i.name == item.name) {
+        Some(old) => {
+            *old = item.clone();
+            true
+        }
+        None => {
+            shop.push(item.clone());
+            false
+        }
+    });
+    ctx.data().saver.mark_guild_dirty(guild_id);
+
+    let stock = match item.stock {
+        Some(stock) => stock.to_string(),
+        None => "no limit".to_string(),
+    };
+    let per_user = match item.per_user {
+        Some(limit) => limit.to_string(),
+        None => "no limit".to_string(),
+    };
+    let rents = match &item.rental {
+        Some(terms) => format!("<@&{}> for {}h", terms.role, terms.hours),
+        None => "nothing".to_string(),
+    };
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Shop Add")
+                .description(format!(
+                    "{} **{}** in {}",
+                    if replaced { "Updated" } else { "Now selling" },
+                    item.name,
+                    item.category
+                ))
+                .field("Price", item.price.to_string(), true)
+                .field("Stock", stock, true)
+                .field("Per User", per_user, true)
+                .field("Rents", rents, true)
+                .color(data::EMBED_MOD)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "@~ powered by UwUntu & RustyBamboo",
+                )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// [!] MODERATOR - stop selling an item, users keep the ones they bought
+#[poise::command(slash_command, guild_only, check = "check_mod")]
+pub async fn shop_remove(
+    ctx: Context<'_>,
+    #[description = "item name"]
+    #[autocomplete = "autocomplete_item"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+
+    let removed = guild.update_shop(|shop| {
+        let before = shop.len();
+        shop.retain(|i| i.name != name);
+        before != shop.len()
+    });
+
+    let embed = match removed {
+        true => {
+            ctx.data().saver.mark_guild_dirty(guild_id);
+            serenity::CreateEmbed::new()
+                .title("Shop Remove")
+                .description(format!("**{}** is no longer sold", name))
+                .color(data::EMBED_MOD)
+        }
+        false => serenity::CreateEmbed::new()
+            .title("Shop Remove")
+            .description(format!("There is no **{}** in the shop", name))
+            .color(data::EMBED_ERROR),
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(embed.footer(serenity::CreateEmbedFooter::new(
+            "@~ powered by UwUntu & RustyBamboo",
+        ))),
+    )
+    .await?;
+    Ok(())
+}
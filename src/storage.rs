@@ -0,0 +1,718 @@
+//!---------------------------------------------------------------------!
+//! This file contains the storage backends behind professorBot's save  !
+//! data, either a single json file or an embedded sqlite database      !
+//!                                                                     !
+//! Backends:                                                           !
+//!     [x] - JsonStore                                                 !
+//!     [x] - SqliteStore                                               !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - open_store                                                !
+//!     [x] - write_atomic                                              !
+//!     [x] - snapshot                                                  !
+//!     [x] - read_save                                                 !
+//!---------------------------------------------------------------------!
+
+use crate::data::SaveData;
+use crate::migrate;
+use crate::{serenity, Error};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const SAVE_FILE: &str = "data.json";
+pub const SQLITE_FILE: &str = "data.db";
+pub const BACKUP_DIR: &str = "backups";
+
+// How many snapshots to keep around
+const BACKUP_COUNT: usize = 24;
+// How many minutes between snapshots
+const BACKUP_INTERVAL: i64 = 60;
+
+/// A place user data can be loaded from and saved to
+pub trait UserStore: Send + Sync {
+    /// Reads everything that was saved, None if the store is empty
+    fn load(&self) -> Result<Option<SaveData>, Error>;
+
+    /// Writes every user in `data` to the store
+    fn save(&self, data: &SaveData) -> Result<(), Error>;
+
+    /// Writes `data` as the whole save, anything stored that isn't in it is dropped
+    fn replace(&self, data: &SaveData) -> Result<(), Error> {
+        self.save(data)
+    }
+
+    /// Whether a save may hold only the users that changed, otherwise it
+    /// must hold everyone since the store is rewritten as a whole
+    fn incremental(&self) -> bool {
+        false
+    }
+
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+}
+
+/// Picks the backend from the STORAGE env var ("json" or "sqlite"), json by default
+pub fn open_store() -> Result<Box<dyn UserStore>, Error> {
+    let backend = env::var("STORAGE").unwrap_or_else(|_| "json".to_string());
+    match backend.as_str() {
+        "json" => Ok(Box::new(JsonStore::new(SAVE_FILE, BACKUP_DIR))),
+        "sqlite" => {
+            let path = env::var("SQLITE_PATH").unwrap_or_else(|_| SQLITE_FILE.to_string());
+            let store = SqliteStore::open(&path)?;
+
+            // carry over the json save the first time the database is used
+            if store.is_empty()? {
+                if let Some(save) = JsonStore::new(SAVE_FILE, BACKUP_DIR).load()? {
+                    println!("Importing {} into {}", SAVE_FILE, path);
+                    store.replace(&save)?;
+                }
+            }
+            Ok(Box::new(store))
+        }
+        other => Err(format!("Unknown STORAGE backend: {}", other).into()),
+    }
+}
+
+/// The whole save is one json document, rewritten atomically on every save
+pub struct JsonStore {
+    path: PathBuf,
+    backup_dir: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(path: impl Into<PathBuf>, backup_dir: impl Into<PathBuf>) -> Self {
+        JsonStore {
+            path: path.into(),
+            backup_dir: backup_dir.into(),
+        }
+    }
+}
+
+impl UserStore for JsonStore {
+    fn load(&self) -> Result<Option<SaveData>, Error> {
+        Ok(read_save(&self.path, &self.backup_dir)?)
+    }
+
+    fn save(&self, data: &SaveData) -> Result<(), Error> {
+        let encoded = serde_json::to_string(data)?;
+        write_atomic(&self.path, encoded.as_bytes())?;
+        if let Err(e) = snapshot(&self.path, &self.backup_dir) {
+            println!("Failed to snapshot save file: {}", e);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "json"
+    }
+}
+
+/// Every user is a row in an embedded database, only rows that changed get written
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+    /// Last encoding written for each row, used to skip unchanged rows
+    written: Mutex<Written>,
+}
+
+/// Guilds and users a full write deleted
+type Removed = (
+    Vec<serenity::GuildId>,
+    Vec<(serenity::GuildId, serenity::UserId)>,
+);
+
+#[derive(Default)]
+struct Written {
+    guilds: HashMap<serenity::GuildId, String>,
+    users: HashMap<(serenity::GuildId, serenity::UserId), String>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS guilds (
+                 id   INTEGER PRIMARY KEY,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS guild_users (
+                 guild_id INTEGER NOT NULL,
+                 user_id  INTEGER NOT NULL,
+                 data     TEXT NOT NULL,
+                 PRIMARY KEY (guild_id, user_id)
+             );",
+        )?;
+
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+            written: Mutex::new(Written::default()),
+        })
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        let conn = self.conn.lock().unwrap();
+        let row: Option<i64> = conn
+            .query_row("SELECT guild_id FROM guild_users LIMIT 1", [], |r| r.get(0))
+            .optional()?;
+        Ok(row.is_none() && !has_legacy_users(&conn)?)
+    }
+
+    /// Upserts the rows in `data`, a `full` write also deletes the rows it doesn't hold
+    fn write(
+        conn: &mut Connection,
+        written: &mut Written,
+        data: &SaveData,
+        full: bool,
+    ) -> Result<(), Error> {
+        // only remembered once the transaction commits, a rollback must not
+        // leave rows marked as written
+        let mut pending = Written::default();
+        let mut removed = (Vec::new(), Vec::new());
+        let tx = conn.transaction()?;
+        {
+            let mut guild_stmt = tx.prepare_cached(
+                "INSERT INTO guilds (id, data) VALUES (?1, ?2)
+                 ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            )?;
+            let mut user_stmt = tx.prepare_cached(
+                "INSERT INTO guild_users (guild_id, user_id, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(guild_id, user_id) DO UPDATE SET data = excluded.data",
+            )?;
+
+            for g in data.guilds.iter() {
+                let (guild_id, guild) = g.pair();
+
+                // the guild row holds everything but the users, they get their own rows
+                let mut encoded = serde_json::to_value(guild)?;
+                encoded.as_object_mut().unwrap().remove("users");
+                let encoded = encoded.to_string();
+                if written.guilds.get(guild_id) != Some(&encoded) {
+                    guild_stmt.execute(params![guild_id.get() as i64, encoded])?;
+                    pending.guilds.insert(*guild_id, encoded);
+                }
+
+                for x in guild.users.iter() {
+                    let (id, user) = x.pair();
+                    let encoded = serde_json::to_string(user)?;
+                    if written.users.get(&(*guild_id, *id)) == Some(&encoded) {
+                        continue;
+                    }
+                    user_stmt.execute(params![guild_id.get() as i64, id.get() as i64, encoded])?;
+                    pending.users.insert((*guild_id, *id), encoded);
+                }
+            }
+        }
+        if full {
+            removed = Self::delete_missing(&tx, data)?;
+        }
+        tx.pragma_update(None, "user_version", data.version)?;
+        tx.commit()?;
+
+        written.guilds.extend(pending.guilds);
+        written.users.extend(pending.users);
+        let (guilds, users) = removed;
+        for guild_id in guilds {
+            written.guilds.remove(&guild_id);
+        }
+        for key in users {
+            written.users.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Deletes the guilds and users that aren't in `data`, returns what went
+    fn delete_missing(tx: &Connection, data: &SaveData) -> Result<Removed, Error> {
+        let mut users = Vec::new();
+        let mut guilds = Vec::new();
+
+        let mut stmt = tx.prepare("SELECT guild_id, user_id FROM guild_users")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (guild_id, id) = row?;
+            let key = (
+                serenity::GuildId::new(guild_id as u64),
+                serenity::UserId::new(id as u64),
+            );
+            let kept = data
+                .guilds
+                .get(&key.0)
+                .is_some_and(|g| g.users.contains_key(&key.1));
+            if !kept {
+                users.push(key);
+            }
+        }
+
+        let mut stmt = tx.prepare("SELECT id FROM guilds")?;
+        let rows = stmt.query_map([], |r| r.get::<_, i64>(0))?;
+        for row in rows {
+            let guild_id = serenity::GuildId::new(row? as u64);
+            if !data.guilds.contains_key(&guild_id) {
+                guilds.push(guild_id);
+            }
+        }
+
+        let mut user_stmt =
+            tx.prepare("DELETE FROM guild_users WHERE guild_id = ?1 AND user_id = ?2")?;
+        for (guild_id, id) in users.iter() {
+            user_stmt.execute(params![guild_id.get() as i64, id.get() as i64])?;
+        }
+        let mut guild_stmt = tx.prepare("DELETE FROM guilds WHERE id = ?1")?;
+        for guild_id in guilds.iter() {
+            guild_stmt.execute(params![guild_id.get() as i64])?;
+        }
+        Ok((guilds, users))
+    }
+}
+
+/// databases from before guilds kept a single users table
+fn has_legacy_users(conn: &Connection) -> Result<bool, Error> {
+    let table: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'users'",
+            [],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(table.is_some())
+}
+
+impl UserStore for SqliteStore {
+    fn load(&self) -> Result<Option<SaveData>, Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut written = self.written.lock().unwrap();
+
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+
+        // rebuild the json document so rows go through the same migrations as files
+        let mut guilds = Map::new();
+        {
+            let mut stmt = conn.prepare("SELECT id, data FROM guilds")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, encoded) = row?;
+                let mut guild: Value = serde_json::from_str(&encoded)?;
+                guild["users"] = json!({});
+                guilds.insert((id as u64).to_string(), guild);
+            }
+
+            let mut stmt = conn.prepare("SELECT guild_id, user_id, data FROM guild_users")?;
+            let rows = stmt.query_map([], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, i64>(1)?,
+                    r.get::<_, String>(2)?,
+                ))
+            })?;
+            for row in rows {
+                let (guild_id, id, encoded) = row?;
+                let guild = guilds
+                    .entry((guild_id as u64).to_string())
+                    .or_insert_with(|| json!({ "users": {} }));
+                guild["users"][(id as u64).to_string()] = serde_json::from_str(&encoded)?;
+            }
+        }
+
+        let save = if !guilds.is_empty() {
+            json!({ "version": version, "guilds": guilds })
+        } else if has_legacy_users(&conn)? {
+            let mut users = Map::new();
+            let mut stmt = conn.prepare("SELECT id, data FROM users")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, encoded) = row?;
+                users.insert((id as u64).to_string(), serde_json::from_str(&encoded)?);
+            }
+            json!({ "version": version, "users": users })
+        } else {
+            return Ok(None);
+        };
+
+        let upgraded = migrate::upgrade(save)?;
+        if version < migrate::CURRENT_VERSION {
+            // rewrite every row in the new format before anything else touches it
+            println!(
+                "Upgrading sqlite store from v{} to v{}",
+                version,
+                migrate::CURRENT_VERSION
+            );
+            *written = Written::default();
+            Self::write(&mut conn, &mut written, &upgraded, true)?;
+            conn.execute_batch("DROP TABLE IF EXISTS users")?;
+        } else {
+            for g in upgraded.guilds.iter() {
+                let mut encoded = serde_json::to_value(g.value())?;
+                encoded.as_object_mut().unwrap().remove("users");
+                written.guilds.insert(*g.key(), encoded.to_string());
+                for x in g.users.iter() {
+                    written
+                        .users
+                        .insert((*g.key(), *x.key()), serde_json::to_string(x.value())?);
+                }
+            }
+        }
+        Ok(Some(upgraded))
+    }
+
+    fn save(&self, data: &SaveData) -> Result<(), Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut written = self.written.lock().unwrap();
+        Self::write(&mut conn, &mut written, data, false)
+    }
+
+    fn replace(&self, data: &SaveData) -> Result<(), Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut written = self.written.lock().unwrap();
+        Self::write(&mut conn, &mut written, data, true)
+    }
+
+    fn incremental(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+}
+
+/// write to a temp file, fsync it and rename it over the target
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+
+    // make sure the rename itself survives a crash
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// copy the save file into the backup folder if the newest snapshot is stale,
+/// then drop the oldest snapshots past the limit
+pub fn snapshot(path: &Path, backup_dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(backup_dir)?;
+
+    let now = Utc::now();
+    let snapshots = list_snapshots(backup_dir);
+    if let Some(newest) = snapshots.last() {
+        let modified = fs::metadata(newest)?.modified()?;
+        let age = now - chrono::DateTime::<Utc>::from(modified);
+        if age.num_minutes() < BACKUP_INTERVAL {
+            return Ok(());
+        }
+    }
+
+    let name = format!("data-{}.json", now.format("%Y%m%d-%H%M%S"));
+    write_atomic(&backup_dir.join(name), &fs::read(path)?)?;
+
+    let snapshots = list_snapshots(backup_dir);
+    if snapshots.len() > BACKUP_COUNT {
+        for old in &snapshots[..snapshots.len() - BACKUP_COUNT] {
+            fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+/// snapshots sorted from oldest to newest, the timestamped names sort naturally
+pub fn list_snapshots(backup_dir: &Path) -> Vec<PathBuf> {
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(backup_dir)
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| {
+                    let name = p.file_name().unwrap_or_default().to_string_lossy();
+                    name.starts_with("data-") && name.ends_with(".json")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    snapshots.sort();
+    snapshots
+}
+
+/// parse a save of any known version, older files are copied aside before upgrading
+fn parse_save(path: &Path, backup_dir: &Path) -> Result<SaveData, String> {
+    let file = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let raw: Value = serde_json::from_str(&file).map_err(|e| e.to_string())?;
+
+    let version = migrate::version_of(&raw);
+    if version < migrate::CURRENT_VERSION {
+        println!(
+            "Upgrading {} from v{} to v{}",
+            path.display(),
+            version,
+            migrate::CURRENT_VERSION
+        );
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let keep = backup_dir.join(format!("premigrate-v{}-{}", version, name));
+        if !keep.exists() {
+            fs::create_dir_all(backup_dir).map_err(|e| e.to_string())?;
+            fs::copy(path, keep).map_err(|e| e.to_string())?;
+        }
+    }
+
+    migrate::upgrade(raw)
+}
+
+/// Reads the save file, falling back to the newest valid snapshot when the
+/// primary is missing or corrupt. Returns None if there is nothing to load.
+pub fn read_save(path: &Path, backup_dir: &Path) -> Result<Option<SaveData>, String> {
+    let primary_exists = path.exists();
+    if primary_exists {
+        match parse_save(path, backup_dir) {
+            Ok(save) => return Ok(Some(save)),
+            Err(e) => {
+                println!("Failed to read {}: {}", path.display(), e);
+
+                // keep the broken file around so the next save doesn't bury it
+                let mut corrupt = path.as_os_str().to_owned();
+                corrupt.push(format!(".corrupt-{}", Utc::now().format("%Y%m%d-%H%M%S")));
+                if let Err(e) = fs::copy(path, PathBuf::from(&corrupt)) {
+                    println!("Failed to preserve corrupt save file: {}", e);
+                }
+            }
+        }
+    }
+
+    for snapshot in list_snapshots(backup_dir).iter().rev() {
+        match parse_save(snapshot, backup_dir) {
+            Ok(save) => {
+                println!("Recovered save data from {}", snapshot.display());
+                return Ok(Some(save));
+            }
+            Err(e) => println!("Skipping snapshot {}: {}", snapshot.display(), e),
+        }
+    }
+
+    if primary_exists {
+        return Err(format!(
+            "{} is corrupt and no valid snapshot was found in {}",
+            path.display(),
+            backup_dir.display()
+        ));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{CredReason, GuildSave, UserData};
+    use dashmap::DashMap;
+    use serenity::{GuildId, UserId};
+
+    const GUILD: GuildId = GuildId::new(1194668798830194850);
+
+    /// Empty scratch folder for one test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("professor_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn save_with(creds: &[(u64, i32)]) -> SaveData {
+        let users = DashMap::new();
+        for &(id, amount) in creds {
+            let mut user = UserData::default();
+            user.add_creds(amount, CredReason::Voice);
+            users.insert(UserId::new(id), user);
+        }
+        let guilds = DashMap::new();
+        guilds.insert(
+            GUILD,
+            GuildSave {
+                users,
+                ..Default::default()
+            },
+        );
+        SaveData::new(guilds)
+    }
+
+    fn creds_of(save: &SaveData, id: u64) -> i32 {
+        save.guilds
+            .get(&GUILD)
+            .unwrap()
+            .users
+            .get(&UserId::new(id))
+            .unwrap()
+            .get_creds()
+    }
+
+    fn set_creds(save: &SaveData, id: u64, amount: i32) {
+        let guild = save.guilds.get(&GUILD).unwrap();
+        let mut user = guild.users.get_mut(&UserId::new(id)).unwrap();
+        let creds = user.get_creds();
+        user.add_creds(amount - creds, CredReason::Voice);
+    }
+
+    /// The raw row of a user, read around the store's write cache
+    fn row(path: &Path, id: u64) -> String {
+        Connection::open(path)
+            .unwrap()
+            .query_row(
+                "SELECT data FROM guild_users WHERE guild_id = ?1 AND user_id = ?2",
+                params![GUILD.get() as i64, id as i64],
+                |r| r.get(0),
+            )
+            .unwrap()
+    }
+
+    fn row_creds(path: &Path, id: u64) -> i64 {
+        let user: Value = serde_json::from_str(&row(path, id)).unwrap();
+        user["creds"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = scratch("write_atomic");
+        let path = dir.join("data.json");
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        // the temp file was renamed over the target, nothing is left behind
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_store_round_trips() {
+        let dir = scratch("json_store");
+        let store = JsonStore::new(dir.join("data.json"), dir.join("backups"));
+        assert!(store.load().unwrap().is_none());
+
+        store.save(&save_with(&[(1, 500), (2, 20)])).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.user_count(), 2);
+        assert_eq!(creds_of(&loaded, 1), 500);
+        assert!(!dir.join("data.json.tmp").exists());
+
+        // a second save right away doesn't take another snapshot
+        store.save(&loaded).unwrap();
+        assert_eq!(list_snapshots(&dir.join("backups")).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_store_round_trips() {
+        let dir = scratch("sqlite_store");
+        let path = dir.join("data.db");
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        assert!(store.is_empty().unwrap());
+        assert!(store.load().unwrap().is_none());
+
+        store.save(&save_with(&[(1, 500), (2, 20)])).unwrap();
+        drop(store);
+
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.version, migrate::CURRENT_VERSION);
+        assert_eq!(creds_of(&loaded, 1), 500);
+        assert_eq!(creds_of(&loaded, 2), 20);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_store_only_writes_changed_rows() {
+        let dir = scratch("sqlite_incremental");
+        let path = dir.join("data.db");
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        let save = save_with(&[(1, 500), (2, 20)]);
+        store.save(&save).unwrap();
+
+        // touch user 2's row behind the store's back, it is only rewritten if it changes
+        Connection::open(&path)
+            .unwrap()
+            .execute(
+                "UPDATE guild_users SET data = 'untouched' WHERE user_id = 2",
+                [],
+            )
+            .unwrap();
+
+        set_creds(&save, 1, 650);
+        store.save(&save).unwrap();
+        assert_eq!(row_creds(&path, 1), 650);
+        assert_eq!(row(&path, 2), "untouched");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_store_retries_rows_after_a_failed_save() {
+        let dir = scratch("sqlite_rollback");
+        let path = dir.join("data.db");
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        let save = save_with(&[(1, 500), (2, 20)]);
+        store.save(&save).unwrap();
+
+        let other = Connection::open(&path).unwrap();
+        other
+            .execute_batch(
+                "CREATE TRIGGER refuse BEFORE UPDATE ON guild_users WHEN NEW.user_id = 2
+                 BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+            )
+            .unwrap();
+
+        set_creds(&save, 1, 650);
+        set_creds(&save, 2, 40);
+        assert!(store.save(&save).is_err());
+        assert_eq!(row_creds(&path, 1), 500);
+
+        // the rolled back rows weren't remembered as written, the retry writes them
+        other.execute_batch("DROP TRIGGER refuse").unwrap();
+        store.save(&save).unwrap();
+        assert_eq!(row_creds(&path, 1), 650);
+        assert_eq!(row_creds(&path, 2), 40);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_store_replace_drops_missing_rows() {
+        let dir = scratch("sqlite_replace");
+        let path = dir.join("data.db");
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        let save = save_with(&[(1, 500), (2, 20), (3, 70)]);
+        let gone = GuildId::new(1);
+        save.guilds.insert(gone, GuildSave::default());
+        store.save(&save).unwrap();
+
+        // a partial save leaves the rows it doesn't mention alone
+        store.save(&save_with(&[(1, 500)])).unwrap();
+        assert_eq!(store.load().unwrap().unwrap().user_count(), 3);
+
+        store.replace(&save_with(&[(1, 500), (3, 90)])).unwrap();
+        drop(store);
+
+        let store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.user_count(), 2);
+        assert_eq!(creds_of(&loaded, 3), 90);
+        assert!(!loaded.guilds.contains_key(&gone));
+
+        // the dropped user was forgotten as written, saving them again brings the row back
+        store.save(&save).unwrap();
+        assert_eq!(row_creds(&path, 2), 20);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
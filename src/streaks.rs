@@ -0,0 +1,86 @@
+//!---------------------------------------------------------------------!
+//! This file contains daily streak freezes, bought ahead of time so a  !
+//! missed /uwu doesn't break a streak                                  !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - buy_freeze                                                !
+//!---------------------------------------------------------------------!
+
+use crate::data;
+use crate::{serenity, Context, Error};
+
+fn streak_embed(title: &str, desc: String, color: serenity::Color) -> poise::CreateReply {
+    poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title(title)
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    )
+}
+
+/// buy streak freezes, each one saves your daily streak from one missed day
+#[poise::command(slash_command, guild_only)]
+pub async fn buy_freeze(
+    ctx: Context<'_>,
+    #[description = "how many freezes to buy (default 1)"]
+    #[min = 1]
+    #[max = 10]
+    amount: Option<u32>,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+    let streaks = &config.streaks;
+    let amount = amount.unwrap_or(1);
+
+    let u = guild.users.get(&user_id).unwrap();
+    let mut user_data = u.write().await;
+
+    let owned = user_data.get_freezes();
+    let cost = streaks.freeze_price.saturating_mul(amount as i32);
+    let (desc, color) = if owned + amount > streaks.max_freezes {
+        (
+            format!(
+                "You can hold at most **{}** streak freezes, you have **{}**",
+                streaks.max_freezes, owned
+            ),
+            data::EMBED_ERROR,
+        )
+    } else if cost > user_data.get_creds() {
+        (
+            format!(
+                "**{}** streak freeze(s) cost **{}** creds, you only have **{}**",
+                amount,
+                cost,
+                user_data.get_creds()
+            ),
+            data::EMBED_ERROR,
+        )
+    } else {
+        user_data.sub_creds(
+            cost,
+            data::CredReason::StreakFreeze {
+                count: amount as i32,
+            },
+        );
+        user_data.add_freezes(amount);
+        ctx.data().saver.mark_dirty(guild_id, user_id);
+        (
+            format!(
+                "You bought **{}** streak freeze(s) for **{}** creds! You now have **{}**, they are used up on their own when you miss a day",
+                amount,
+                cost,
+                user_data.get_freezes()
+            ),
+            data::EMBED_GOLD,
+        )
+    };
+    drop(user_data);
+
+    ctx.send(streak_embed("Streak Freeze", desc, color)).await?;
+    Ok(())
+}
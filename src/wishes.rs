@@ -0,0 +1,363 @@
+//!---------------------------------------------------------------------!
+//! This file contains the wish gacha, creds buy wishes and wishes      !
+//! pull collectibles from the banners set up in config.toml            !
+//!                                                                     !
+//! Commands:                                                           !
+//!     [x] - buy_wishes                                                !
+//!     [x] - wish                                                      !
+//!     [x] - collection                                                !
+//!                                                                     !
+//! Functions:                                                          !
+//!     [x] - pull                                                      !
+//!---------------------------------------------------------------------!
+
+use crate::config::{WishBanner, WishConfig};
+use crate::data::{self, UserData};
+use crate::{serenity, Context, Error};
+use poise::serenity_prelude::futures::StreamExt;
+use poise::serenity_prelude::EditMessage;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Make one wish on a banner, returns the tier and the item pulled
+pub fn pull(
+    wishes: &WishConfig,
+    banner: &WishBanner,
+    user: &mut UserData,
+    rng: &mut impl Rng,
+) -> (String, String) {
+    // the rarest tier whose pity is used up is guaranteed
+    let due = wishes
+        .tiers
+        .iter()
+        .find(|t| t.pity > 0 && user.get_pity(&banner.name, &t.name) + 1 >= t.pity);
+    let tier = match due {
+        Some(tier) => tier,
+        None => {
+            let mut roll = rng.gen_range(0.0..100.0);
+            wishes
+                .tiers
+                .iter()
+                .find(|t| {
+                    roll -= t.chance;
+                    roll < 0.0
+                })
+                .unwrap_or_else(|| wishes.tiers.last().unwrap())
+        }
+    };
+
+    for t in &wishes.tiers {
+        let count = match t.name == tier.name {
+            true => 0,
+            false => user.get_pity(&banner.name, &t.name) + 1,
+        };
+        user.set_pity(&banner.name, &t.name, count);
+    }
+
+    let item = banner.items[&tier.name].choose(rng).unwrap().clone();
+    user.add_to_collection(&item);
+    (tier.name.clone(), item)
+}
+
+async fn autocomplete_banner(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    ctx.data()
+        .config
+        .get()
+        .wishes
+        .banners
+        .iter()
+        .map(|b| b.name.clone())
+        .filter(|name| name.to_lowercase().starts_with(&partial.to_lowercase()))
+        .collect()
+}
+
+fn wish_embed(title: &str, desc: String, color: serenity::Color) -> poise::CreateReply {
+    poise::CreateReply::default().embed(
+        serenity::CreateEmbed::new()
+            .title(title)
+            .description(desc)
+            .color(color)
+            .footer(serenity::CreateEmbedFooter::new(
+                "@~ powered by UwUntu & RustyBamboo",
+            )),
+    )
+}
+
+/// trade creds for wishes
+#[poise::command(slash_command, guild_only)]
+pub async fn buy_wishes(
+    ctx: Context<'_>,
+    #[description = "how many wishes to buy"]
+    #[min = 1]
+    #[max = 100]
+    amount: u32,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let price = ctx.data().config.get().wishes.price;
+
+    let u = guild.users.get(&user_id).unwrap();
+    let mut user_data = u.write().await;
+
+    let amount = amount as i32;
+    let cost = price.saturating_mul(amount);
+    if cost > user_data.get_creds() {
+        let desc = format!(
+            "**{}** wish(es) cost **{}** creds, you only have **{}**",
+            amount,
+            cost,
+            user_data.get_creds()
+        );
+        drop(user_data);
+        ctx.send(wish_embed("Buy Wishes", desc, data::EMBED_ERROR))
+            .await?;
+        return Ok(());
+    }
+
+    user_data.sub_creds(cost, data::CredReason::Wishes { count: amount });
+    user_data.add_wishes(amount);
+    ctx.data().saver.mark_dirty(guild_id, user_id);
+
+    let desc = format!(
+        "You bought **{}** wish(es) for **{}** creds! You now have **{}** wish(es), use them with **/wish**",
+        amount,
+        cost,
+        user_data.get_wishes()
+    );
+    drop(user_data);
+    ctx.send(wish_embed("Buy Wishes", desc, data::EMBED_GOLD))
+        .await?;
+    Ok(())
+}
+
+/// spend wishes on a banner for a chance at rare collectibles
+#[poise::command(slash_command, guild_only)]
+pub async fn wish(
+    ctx: Context<'_>,
+    #[description = "banner to wish on (default the first one)"]
+    #[autocomplete = "autocomplete_banner"]
+    banner: Option<String>,
+    #[description = "how many wishes to make at once (default 1)"]
+    #[min = 1]
+    #[max = 10]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guild(guild_id);
+    let config = ctx.data().config.get();
+    let wishes = &config.wishes;
+    let count = count.unwrap_or(1) as i32;
+
+    let banner = match &banner {
+        Some(name) => wishes
+            .banners
+            .iter()
+            .find(|b| b.name.eq_ignore_ascii_case(name)),
+        None => wishes.banners.first(),
+    };
+    let banner = match banner {
+        Some(banner) => banner,
+        None => {
+            let names: Vec<&str> = wishes.banners.iter().map(|b| b.name.as_str()).collect();
+            ctx.send(wish_embed(
+                "Wish",
+                format!("There is no such banner, try one of: {}", names.join(", ")),
+                data::EMBED_ERROR,
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let u = guild.users.get(&user_id).unwrap();
+    let mut user_data = u.write().await;
+
+    if !user_data.sub_wishes(count) {
+        let desc = format!(
+            "You need **{}** wish(es) but only have **{}**, get more with **/buy_wishes**",
+            count,
+            user_data.get_wishes()
+        );
+        drop(user_data);
+        ctx.send(wish_embed("Wish", desc, data::EMBED_ERROR))
+            .await?;
+        return Ok(());
+    }
+
+    let pulls: Vec<(String, String)> = {
+        let mut rng = ctx
+            .data()
+            .rng
+            .draw(&format!("wish {} {}", guild_id, user_id));
+        (0..count)
+            .map(|_| pull(wishes, banner, &mut user_data, &mut rng))
+            .collect()
+    };
+    ctx.data().saver.mark_dirty(guild_id, user_id);
+
+    // tiers are listed rarest first, so a lower rank is a better pull
+    let rank = |tier: &str| wishes.tiers.iter().position(|t| t.name == tier).unwrap();
+    let best = pulls.iter().map(|(tier, _)| rank(tier)).min().unwrap();
+
+    let mut desc = String::new();
+    for (tier, item) in &pulls {
+        if rank(tier) == 0 {
+            desc += format!("{} **{}** \u{2728}\n", tier, item).as_str();
+        } else {
+            desc += format!("{} {}\n", tier, item).as_str();
+        }
+    }
+
+    desc += "\n";
+    for tier in wishes.tiers.iter().filter(|t| t.pity > 0) {
+        desc += format!(
+            "{} pity: {}/{}\n",
+            tier.name,
+            user_data.get_pity(&banner.name, &tier.name),
+            tier.pity
+        )
+        .as_str();
+    }
+    desc += format!("Wishes left: **{}**", user_data.get_wishes()).as_str();
+    drop(user_data);
+
+    let color = match best {
+        0 => data::EMBED_GOLD,
+        1 => data::EMBED_LEVEL,
+        _ => data::EMBED_CYAN,
+    };
+    ctx.send(wish_embed(&format!("{} Banner", banner.name), desc, color))
+        .await?;
+    Ok(())
+}
+
+/// browse everything you have pulled with /wish
+#[poise::command(slash_command, guild_only)]
+pub async fn collection(ctx: Context<'_>) -> Result<(), Error> {
+    let user = ctx.author();
+    let avatar = user.avatar_url().unwrap_or_default();
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let config = ctx.data().config.get();
+    let u = guild.users.get(&user.id).unwrap();
+    let user_data = u.read().await;
+
+    // items that left every banner still show, after the current tiers
+    let tier_of = |item: &str| {
+        config.wishes.tiers.iter().enumerate().find(|(_, t)| {
+            config.wishes.banners.iter().any(|b| {
+                b.items
+                    .get(&t.name)
+                    .is_some_and(|i| i.iter().any(|i| i == item))
+            })
+        })
+    };
+    let mut items: Vec<(usize, String)> = user_data
+        .get_collection()
+        .iter()
+        .map(|(item, count)| match tier_of(item) {
+            Some((rank, tier)) => (rank, format!("{} {} x{}", tier.name, item, count)),
+            None => (usize::MAX, format!("(retired) {} x{}", item, count)),
+        })
+        .collect();
+    items.sort();
+    let lines: Vec<String> = items.into_iter().map(|(_, line)| line).collect();
+    drop(user_data);
+
+    if lines.is_empty() {
+        ctx.send(wish_embed(
+            "Collection",
+            "Nothing here yet, try **/wish**!".to_string(),
+            data::EMBED_ERROR,
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let pages: Vec<String> = lines.chunks(10).map(|page| page.join("\n")).collect();
+    let total_pages = pages.len();
+    let collection_embed = move |page: usize| {
+        serenity::CreateEmbed::new()
+            .title("Collection")
+            .description(pages[page].clone())
+            .thumbnail(&avatar)
+            .color(data::EMBED_GOLD)
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Page {}/{} | @~ powered by UwUntu & RustyBamboo",
+                page + 1,
+                pages.len()
+            )))
+    };
+
+    let buttons = vec![
+        serenity::CreateButton::new("open_modal")
+            .label("<")
+            .custom_id("back".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+        serenity::CreateButton::new("open_modal")
+            .label(">")
+            .custom_id("next".to_string())
+            .style(poise::serenity_prelude::ButtonStyle::Secondary),
+    ];
+    let components = if total_pages > 1 {
+        vec![serenity::CreateActionRow::Buttons(buttons)]
+    } else {
+        Vec::new()
+    };
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(collection_embed(0))
+                .components(components),
+        )
+        .await?;
+
+    let msg_og = Arc::new(RwLock::new(reply.into_message().await?));
+    let msg = Arc::clone(&msg_og);
+
+    let mut reactions = msg
+        .read()
+        .await
+        .await_component_interactions(ctx)
+        .timeout(Duration::new(60, 0))
+        .author_id(user.id)
+        .stream();
+
+    let ctx = ctx.serenity_context().clone();
+
+    tokio::spawn(async move {
+        let mut current_page: usize = 0;
+        while let Some(reaction) = reactions.next().await {
+            match reaction.data.custom_id.as_str() {
+                "back" if current_page > 0 => {
+                    current_page -= 1;
+                }
+                "next" if current_page < total_pages - 1 => {
+                    current_page += 1;
+                }
+                _ => (),
+            };
+
+            reaction
+                .create_response(&ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await
+                .unwrap();
+
+            msg.write()
+                .await
+                .edit(
+                    &ctx,
+                    EditMessage::default().embed(collection_embed(current_page)),
+                )
+                .await
+                .unwrap();
+        }
+    });
+
+    Ok(())
+}